@@ -0,0 +1,47 @@
+use anyxplore::format::image::{ImageFormat, codec::ImageCodec, dxt1::DXT1, dxt5::DXT5};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+const WIDTH: u32 = 1024;
+const HEIGHT: u32 = 1024;
+
+// Deterministic noise, so every block ends up with distinct endpoints and indices
+fn surface(format: ImageFormat) -> Vec<u8> {
+    let mut state = 0x2545_f491u32;
+
+    (0..format.surface_size(WIDTH, HEIGHT))
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(WIDTH as u64 * HEIGHT as u64));
+
+    let mut out = vec![0u8; WIDTH as usize * HEIGHT as usize * 4];
+
+    let dxt1 = DXT1::from_bytes(&surface(ImageFormat::DXT1), WIDTH, HEIGHT).unwrap();
+    let dxt5 = DXT5::from_bytes(&surface(ImageFormat::DXT5), WIDTH, HEIGHT).unwrap();
+
+    group.bench_function("dxt1_into", |b| {
+        b.iter(|| dxt1.decode_into(black_box(&mut out)).unwrap())
+    });
+
+    group.bench_function("dxt1_alloc", |b| b.iter(|| black_box(dxt1.decode_rgba())));
+
+    group.bench_function("dxt5_into", |b| {
+        b.iter(|| dxt5.decode_into(black_box(&mut out)).unwrap())
+    });
+
+    group.bench_function("dxt5_alloc", |b| b.iter(|| black_box(dxt5.decode_rgba())));
+
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
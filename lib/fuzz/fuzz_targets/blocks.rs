@@ -0,0 +1,44 @@
+#![no_main]
+
+use anyxplore::format::image::{
+    alpha::{ExplicitAlphaBlock, InterpolatedAlphaBlock},
+    dxt1::DXT1Block,
+    dxt2::DXT2Block,
+    dxt3::DXT3Block,
+    dxt4::DXT4Block,
+    dxt5::DXT5Block,
+};
+use libfuzzer_sys::fuzz_target;
+
+// The single block parsers, which may be handed slices of any length
+fuzz_target!(|data: &[u8]| {
+    if let Ok(block) = DXT1Block::from_bytes(data) {
+        let _ = block.rows_rgba();
+        assert_eq!(block.to_bytes(), data[..8]);
+    }
+
+    if let Ok(block) = DXT2Block::from_bytes(data) {
+        let _ = block.rows();
+    }
+
+    if let Ok(block) = DXT3Block::from_bytes(data) {
+        let _ = block.rows();
+    }
+
+    if let Ok(block) = DXT4Block::from_bytes(data) {
+        let _ = block.rows();
+    }
+
+    if let Ok(block) = DXT5Block::from_bytes(data) {
+        let _ = block.rows();
+    }
+
+    if let Ok(block) = ExplicitAlphaBlock::from_bytes(data) {
+        let _ = block.values();
+    }
+
+    if let Ok(block) = InterpolatedAlphaBlock::from_bytes(data) {
+        let _ = block.values();
+        assert_eq!(block.to_bytes(), data[..8]);
+    }
+});
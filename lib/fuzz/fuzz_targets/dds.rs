@@ -0,0 +1,24 @@
+#![no_main]
+
+use anyxplore::format::{dds::DDS, image::codec::ImageCodec};
+use libfuzzer_sys::fuzz_target;
+
+// DDS headers straight from disk, then every surface they describe
+fuzz_target!(|data: &[u8]| {
+    let Ok(dds) = DDS::from_bytes(data) else {
+        return;
+    };
+
+    for level in 0..dds.level_count() {
+        for index in 0..dds.surface_count(level) {
+            if let Ok(image) = dds.decode(index, level) {
+                let _ = image.decode_rgba();
+            }
+        }
+    }
+
+    if let Ok(bytes) = dds.to_bytes() {
+        let reparsed = DDS::from_bytes(&bytes).unwrap();
+        assert_eq!(reparsed.data(), dds.data());
+    }
+});
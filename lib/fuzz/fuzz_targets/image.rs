@@ -0,0 +1,29 @@
+#![no_main]
+
+use anyxplore::format::image::codec::ImageCodec;
+use anyxplore_fuzz::{dimension, image_format};
+use libfuzzer_sys::{arbitrary::Unstructured, fuzz_target};
+
+// Every surface parser behind `ImageFormat::decode`, which covers the `from_bytes` constructor of
+// each image type
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+
+    let (Ok(format), Ok(width), Ok(height), Ok(palette)) = (
+        image_format(&mut u),
+        dimension(&mut u),
+        dimension(&mut u),
+        u.arbitrary::<Vec<u8>>(),
+    ) else {
+        return;
+    };
+
+    let bytes = u.take_rest();
+
+    if let Ok(image) = format.decode(bytes, width, height, Some(&palette)) {
+        let rgba = image.decode_rgba();
+        assert_eq!(rgba.len(), width as usize * height as usize * 4);
+
+        let _ = image.to_surface_bytes();
+    }
+});
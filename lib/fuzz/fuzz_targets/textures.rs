@@ -0,0 +1,53 @@
+#![no_main]
+
+use anyxplore::format::image::{
+    codec::ImageCodec, cube::CubeMap, mip::MipChain, volume::VolumeTexture,
+};
+use anyxplore_fuzz::{dimension, image_format};
+use libfuzzer_sys::{arbitrary::Unstructured, fuzz_target};
+
+// Mip chains, cube maps and volumes parsed from a run of surfaces
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+
+    let (Ok(format), Ok(width), Ok(height), Ok(depth), Ok(level_count), Ok(palette)) = (
+        image_format(&mut u),
+        dimension(&mut u),
+        dimension(&mut u),
+        dimension(&mut u),
+        u.arbitrary::<u32>(),
+        u.arbitrary::<Vec<u8>>(),
+    ) else {
+        return;
+    };
+
+    let bytes = u.take_rest();
+
+    if let Ok(mut chain) = MipChain::from_bytes(bytes, width, height, format, level_count) {
+        chain.set_palette(palette.clone());
+
+        for level in 0..chain.level_count() {
+            if let Ok(image) = chain.decode_level(level) {
+                let _ = image.decode_rgba();
+            }
+        }
+    }
+
+    if let Ok(mut cube) = CubeMap::from_bytes(bytes, width, format, level_count) {
+        cube.set_palette(palette.clone());
+
+        for level in 0..cube.level_count() {
+            let _ = cube.to_rgba(level, Default::default());
+        }
+    }
+
+    if let Ok(mut volume) =
+        VolumeTexture::from_bytes(bytes, width, height, depth, format, level_count)
+    {
+        volume.set_palette(palette);
+
+        for level in 0..volume.level_count() {
+            let _ = volume.to_rgba(level);
+        }
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use anyxplore::format::image::interchange::read_tga;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((rgba, width, height)) = read_tga(data) {
+        assert_eq!(rgba.len(), width as usize * height as usize * 4);
+    }
+});
@@ -0,0 +1,44 @@
+// Helpers shared by the fuzz targets. Run a target from the lib directory with
+// `cargo +nightly fuzz run <target>`, where the targets are image, blocks, textures, dds and tga.
+
+use anyxplore::format::image::{ImageFormat, raw::PixelFormat, swizzle::Layout};
+use libfuzzer_sys::arbitrary::{Result, Unstructured};
+
+const PIXEL_FORMATS: [PixelFormat; 8] = [
+    PixelFormat::A8R8G8B8,
+    PixelFormat::X8R8G8B8,
+    PixelFormat::R5G6B5,
+    PixelFormat::X1R5G5B5,
+    PixelFormat::A1R5G5B5,
+    PixelFormat::A4R4G4B4,
+    PixelFormat::L8,
+    PixelFormat::A8,
+];
+
+pub fn image_format(u: &mut Unstructured) -> Result<ImageFormat> {
+    let layout = if u.arbitrary()? {
+        Layout::Swizzled
+    } else {
+        Layout::Linear
+    };
+
+    Ok(match u.int_in_range(0..=6u8)? {
+        0 => ImageFormat::DXT1,
+        1 => ImageFormat::DXT2,
+        2 => ImageFormat::DXT3,
+        3 => ImageFormat::DXT4,
+        4 => ImageFormat::DXT5,
+        5 => ImageFormat::Raw(*u.choose(&PIXEL_FORMATS)?, layout),
+        _ => ImageFormat::P8(layout),
+    })
+}
+
+// Mostly small sizes so that the input can actually cover the surface, with the occasional huge
+// one to exercise the size checks
+pub fn dimension(u: &mut Unstructured) -> Result<u32> {
+    if u.ratio(1, 8)? {
+        u.arbitrary()
+    } else {
+        u.int_in_range(0..=64)
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt;
+
+// Everything that can go wrong while reading, converting or writing textures. Sizes are in bytes
+// unless noted otherwise.
+#[derive(Debug)]
+pub enum Error {
+    // The input ended before everything it describes
+    InsufficientData {
+        expected: usize,
+        actual: usize,
+    },
+    // The caller supplied output buffer can't hold the result
+    BufferTooSmall {
+        expected: usize,
+        actual: usize,
+    },
+    // Sizes in texels. `depth` is 1 for anything that isn't a volume.
+    InvalidDimensions {
+        width: u32,
+        height: u32,
+        depth: u32,
+        reason: &'static str,
+    },
+    InvalidLevelCount {
+        level_count: u32,
+        max: u32,
+    },
+    // A cube face, volume slice or mip level that the texture doesn't have
+    SurfaceOutOfRange {
+        index: u32,
+        level: u32,
+    },
+    UnsupportedFormat(String),
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    // Input that is long enough but doesn't make sense
+    InvalidData(String),
+    // Arguments that can't work together, independent of any file contents
+    InvalidArgument(String),
+    Io(std::io::Error),
+}
+
+impl Error {
+    pub(crate) fn insufficient_data(expected: usize, actual: usize) -> Error {
+        Error::InsufficientData { expected, actual }
+    }
+
+    pub(crate) fn invalid_dimensions(width: u32, height: u32, reason: &'static str) -> Error {
+        Error::InvalidDimensions {
+            width,
+            height,
+            depth: 1,
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InsufficientData { expected, actual } => write!(
+                f,
+                "Not enough data (need {} bytes, only have {}).",
+                expected, actual
+            ),
+            Error::BufferTooSmall { expected, actual } => write!(
+                f,
+                "The output buffer is not large enough (need {} bytes, only have {}).",
+                expected, actual
+            ),
+            Error::InvalidDimensions {
+                width,
+                height,
+                depth: 1,
+                reason,
+            } => write!(f, "Invalid size {} x {}: {}.", width, height, reason),
+            Error::InvalidDimensions {
+                width,
+                height,
+                depth,
+                reason,
+            } => write!(
+                f,
+                "Invalid size {} x {} x {}: {}.",
+                width, height, depth, reason
+            ),
+            Error::InvalidLevelCount { level_count, max } => write!(
+                f,
+                "Invalid mip level count {} (must be between 1 and {}).",
+                level_count, max
+            ),
+            Error::SurfaceOutOfRange { index, level } => write!(
+                f,
+                "Surface {} of mip level {} does not exist.",
+                index, level
+            ),
+            Error::UnsupportedFormat(format) => write!(f, "Unsupported format: {}.", format),
+            Error::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "Checksum mismatch (expected {:08X}, got {:08X}).",
+                expected, actual
+            ),
+            Error::InvalidData(message) | Error::InvalidArgument(message) => f.write_str(message),
+            Error::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+// Lets code that deals in io::Error keep using `?`
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> std::io::Error {
+        if let Error::Io(error) = error {
+            return error;
+        }
+
+        let kind = match &error {
+            Error::UnsupportedFormat(_) => std::io::ErrorKind::Unsupported,
+            Error::BufferTooSmall { .. } | Error::InvalidArgument(_) => {
+                std::io::ErrorKind::InvalidInput
+            }
+            Error::InsufficientData { .. } => std::io::ErrorKind::UnexpectedEof,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+
+        std::io::Error::new(kind, error)
+    }
+}
+
+#[cfg(feature = "interchange")]
+impl From<png::DecodingError> for Error {
+    fn from(error: png::DecodingError) -> Error {
+        match error {
+            png::DecodingError::IoError(error) => Error::Io(error),
+            error => Error::InvalidData(error.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "interchange")]
+impl From<png::EncodingError> for Error {
+    fn from(error: png::EncodingError) -> Error {
+        match error {
+            png::EncodingError::IoError(error) => Error::Io(error),
+            error => Error::InvalidArgument(error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,333 @@
+// Files whose contents are themselves a set of named entries. The format specific parsing lives
+// behind `ArchiveReader`, so formats this crate doesn't know about can still be mounted. Open
+// archives count against a memory budget, and the least recently used are closed to stay under it.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, Weak},
+};
+
+use crate::Error;
+use crate::file::{FileBytes, FileHandle, FileKind, VirtualFile, budget::MemoryBudget};
+
+// Readers get the archive's mapped contents, so they can parse the entry table up front and only
+// decode an entry once it is read. Format specific readers can be reached through
+// `VirtualFile::archive_reader` and downcast, to get at more than the entries.
+pub trait ArchiveReader: Any + Send + Sync {
+    // In the order they should be listed
+    fn entry_names(&self) -> Vec<String>;
+
+    fn read_entry(&self, name: &str) -> Result<Vec<u8>, Error>;
+
+    // Stored entries can return a slice of the archive instead of a copy
+    fn map_entry(&self, name: &str) -> Result<FileBytes, Error> {
+        Ok(self.read_entry(name)?.into())
+    }
+
+    fn entry_size(&self, name: &str) -> Result<u64, Error> {
+        Ok(self.read_entry(name)?.len() as u64)
+    }
+
+    // The key an entry's own format is registered under, if it may be an archive too. Formats
+    // that don't name their entries by extension can tell from their asset types instead.
+    fn entry_format(&self, name: &str) -> Option<String> {
+        extension(name)
+    }
+
+    // Everything the reader keeps alive, which is what its archive is charged against the budget.
+    // That includes the archive's contents if the reader holds on to them, and decoded tables or
+    // caches.
+    fn memory_size(&self) -> usize {
+        0
+    }
+}
+
+// Parses an archive from the contents of its file
+pub type ArchiveOpener =
+    Arc<dyn Fn(FileBytes) -> Result<Box<dyn ArchiveReader>, Error> + Send + Sync>;
+
+// Shared by every archive of a filesystem, including nested ones. Open archives count as what their
+// reader reports keeping.
+pub type ArchiveBudget = MemoryBudget<ArchiveKey>;
+
+// Identifies an archive in its budget without keeping it alive
+#[derive(Clone)]
+pub struct ArchiveKey(Weak<Archive>);
+
+impl PartialEq for ArchiveKey {
+    fn eq(&self, other: &ArchiveKey) -> bool {
+        Weak::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ArchiveKey {}
+
+impl std::hash::Hash for ArchiveKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.0.as_ptr(), state);
+    }
+}
+
+// Openers keyed by lowercase format name, which is the extension for files on disk
+#[derive(Clone, Default)]
+pub struct ArchiveFormats {
+    openers: HashMap<String, ArchiveOpener>,
+}
+
+impl ArchiveFormats {
+    pub fn new() -> ArchiveFormats {
+        ArchiveFormats::default()
+    }
+
+    pub fn insert(&mut self, format: &str, opener: ArchiveOpener) {
+        self.openers.insert(format.to_ascii_lowercase(), opener);
+    }
+
+    pub fn get(&self, format: &str) -> Option<&ArchiveOpener> {
+        self.openers.get(&format.to_ascii_lowercase())
+    }
+
+    pub fn for_name(&self, name: &str) -> Option<&ArchiveOpener> {
+        self.get(&extension(name)?)
+    }
+}
+
+pub(crate) fn extension(name: &str) -> Option<String> {
+    Path::new(name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+}
+
+// A file mounted as a directory of its entries. Nothing is read until the entries are first needed.
+// Entries in one of `formats` are mounted too, so archives can be nested to any depth.
+pub struct Archive {
+    file: Arc<dyn VirtualFile>,
+    opener: ArchiveOpener,
+    formats: Arc<ArchiveFormats>,
+    budget: Arc<ArchiveBudget>,
+    reader: Mutex<Option<Arc<dyn ArchiveReader>>>,
+    // Keyed by entry name, and dropped along with the reader
+    nested: Mutex<HashMap<String, Weak<Archive>>>,
+    this: Weak<Archive>,
+}
+
+impl Archive {
+    pub fn mount(
+        file: Arc<dyn VirtualFile>,
+        opener: ArchiveOpener,
+        formats: Arc<ArchiveFormats>,
+        budget: Arc<ArchiveBudget>,
+    ) -> Arc<Archive> {
+        Arc::new_cyclic(|this| Archive {
+            file,
+            opener,
+            formats,
+            budget,
+            reader: Mutex::new(None),
+            nested: Mutex::new(HashMap::new()),
+            this: this.clone(),
+        })
+    }
+
+    // Failures aren't kept, so an archive that couldn't be parsed is retried next time
+    pub fn reader(&self) -> Result<Arc<dyn ArchiveReader>, Error> {
+        let mut reader = self.reader.lock().unwrap();
+
+        if let Some(reader) = reader.as_ref() {
+            let reader = reader.clone();
+            self.budget.touch(&self.key());
+
+            return Ok(reader);
+        }
+
+        let opened: Arc<dyn ArchiveReader> = (self.opener)(self.file.map()?)?.into();
+        *reader = Some(opened.clone());
+
+        // Other archives are closed without holding this one's lock, or two threads opening
+        // archives at once could each wait on the other
+        drop(reader);
+
+        for key in self.budget.insert(self.key(), opened.memory_size()) {
+            if let Some(archive) = key.0.upgrade() {
+                archive.close();
+            }
+        }
+
+        Ok(opened)
+    }
+
+    // Frees the parsed archive, which is opened again when next needed, including by entries that
+    // were already listed. Nested archives are closed too, as their contents are part of this one's.
+    pub fn close(&self) {
+        *self.reader.lock().unwrap() = None;
+        self.budget.remove(&self.key());
+
+        // Closed without holding this one's lock, for the same reason as in `reader`
+        let nested: Vec<Arc<Archive>> = self
+            .nested
+            .lock()
+            .unwrap()
+            .drain()
+            .filter_map(|(_, archive)| archive.upgrade())
+            .collect();
+
+        for archive in nested {
+            archive.close();
+        }
+    }
+
+    fn key(&self) -> ArchiveKey {
+        ArchiveKey(self.this.clone())
+    }
+}
+
+// Mounts are only reused while something else holds them. Every archive holds its parent, so a
+// cache keeping them alive would also keep alive whatever owns the cache.
+pub(crate) fn mount_cached<K: std::hash::Hash + Eq>(
+    cache: &mut HashMap<K, Weak<Archive>>,
+    key: K,
+    mount: impl FnOnce() -> Arc<Archive>,
+) -> Arc<Archive> {
+    let cached = cache.entry(key).or_default();
+
+    cached.upgrade().unwrap_or_else(|| {
+        let archive = mount();
+        *cached = Arc::downgrade(&archive);
+        archive
+    })
+}
+
+impl Drop for Archive {
+    fn drop(&mut self) {
+        self.budget.remove(&self.key());
+    }
+}
+
+impl VirtualFile for Archive {
+    fn name(&self) -> &str {
+        self.file.name()
+    }
+
+    fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    fn kind(&self) -> FileKind {
+        FileKind::Archive
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        self.file.size()
+    }
+
+    fn open(&self) -> Result<Box<dyn FileHandle>, Error> {
+        self.file.open()
+    }
+
+    fn map(&self) -> Result<FileBytes, Error> {
+        self.file.map()
+    }
+
+    // Closed first, so the old contents aren't mapped while they are replaced. Entries listed
+    // before the write read the new contents, or the old ones again if the write failed.
+    fn write(&self, bytes: &[u8]) -> Result<(), Error> {
+        self.close();
+        self.file.write(bytes)
+    }
+
+    fn is_open(&self) -> bool {
+        self.reader.lock().unwrap().is_some()
+    }
+
+    fn archive_reader(&self) -> Result<Option<Arc<dyn ArchiveReader>>, Error> {
+        Ok(Some(self.reader()?))
+    }
+
+    fn children(&self) -> Result<Vec<Arc<dyn VirtualFile>>, Error> {
+        let reader = self.reader()?;
+
+        // Archives only exist inside an Arc, which must still be alive while it is borrowed
+        let archive = self.this.upgrade().unwrap();
+
+        let mut nested = self.nested.lock().unwrap();
+
+        Ok(reader
+            .entry_names()
+            .into_iter()
+            .map(|name| {
+                let opener = reader
+                    .entry_format(&name)
+                    .and_then(|format| self.formats.get(&format));
+
+                let entry = Arc::new(ArchiveEntry {
+                    archive: archive.clone(),
+                    path: archive.path().join(&name),
+                    name,
+                });
+
+                match opener {
+                    Some(opener) => mount_cached(&mut nested, entry.name.clone(), || {
+                        Archive::mount(
+                            entry,
+                            opener.clone(),
+                            self.formats.clone(),
+                            self.budget.clone(),
+                        )
+                    }) as Arc<dyn VirtualFile>,
+                    None => entry,
+                }
+            })
+            .collect())
+    }
+
+    fn parent(&self) -> Option<Arc<dyn VirtualFile>> {
+        self.file.parent()
+    }
+}
+
+// Entries only hold their archive, which holds no contents once it is closed. They go through its
+// reader on every read, opening it again if needed.
+struct ArchiveEntry {
+    archive: Arc<Archive>,
+    name: String,
+    path: PathBuf,
+}
+
+impl VirtualFile for ArchiveEntry {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn kind(&self) -> FileKind {
+        FileKind::File
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        self.archive.reader()?.entry_size(&self.name)
+    }
+
+    fn open(&self) -> Result<Box<dyn FileHandle>, Error> {
+        Ok(Box::new(Cursor::new(
+            self.archive.reader()?.read_entry(&self.name)?,
+        )))
+    }
+
+    fn map(&self) -> Result<FileBytes, Error> {
+        self.archive.reader()?.map_entry(&self.name)
+    }
+
+    fn children(&self) -> Result<Vec<Arc<dyn VirtualFile>>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn parent(&self) -> Option<Arc<dyn VirtualFile>> {
+        Some(self.archive.clone())
+    }
+}
@@ -0,0 +1,113 @@
+// Keeps loaded data under a memory limit by picking the least recently used items to unload. The
+// budget only does the bookkeeping, unloading is left to whoever owns the items.
+
+use std::{collections::HashMap, hash::Hash, sync::Mutex};
+
+pub const DEFAULT_MEMORY_BUDGET: usize = 1 << 30;
+
+pub struct MemoryBudget<K> {
+    limit: usize,
+    state: Mutex<BudgetState<K>>,
+}
+
+struct BudgetState<K> {
+    used: usize,
+    // Bumped on every use, so larger is more recent
+    clock: u64,
+    // Size and last use of everything loaded
+    loaded: HashMap<K, (usize, u64)>,
+}
+
+impl<K: Hash + Eq + Clone> MemoryBudget<K> {
+    pub fn new(limit: usize) -> MemoryBudget<K> {
+        MemoryBudget {
+            limit,
+            state: Mutex::new(BudgetState {
+                used: 0,
+                clock: 0,
+                loaded: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn used(&self) -> usize {
+        self.state.lock().unwrap().used
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.state.lock().unwrap().loaded.contains_key(key)
+    }
+
+    // Records `key` as loaded and returns what has to be unloaded to make room, least recently
+    // used first. `key` itself is never returned, even if it is over the limit on its own.
+    pub fn insert(&self, key: K, size: usize) -> Vec<K> {
+        let mut state = self.state.lock().unwrap();
+
+        state.clock += 1;
+        let clock = state.clock;
+
+        if let Some((old_size, _)) = state.loaded.insert(key.clone(), (size, clock)) {
+            state.used -= old_size;
+        }
+
+        state.used += size;
+
+        if state.used <= self.limit {
+            return Vec::new();
+        }
+
+        let mut candidates: Vec<(u64, K, usize)> = state
+            .loaded
+            .iter()
+            .filter(|(loaded, _)| **loaded != key)
+            .map(|(loaded, (size, last_used))| (*last_used, loaded.clone(), *size))
+            .collect();
+
+        candidates.sort_by_key(|(last_used, _, _)| *last_used);
+
+        let mut evicted = Vec::new();
+
+        for (_, loaded, size) in candidates {
+            if state.used <= self.limit {
+                break;
+            }
+
+            state.loaded.remove(&loaded);
+            state.used -= size;
+            evicted.push(loaded);
+        }
+
+        evicted
+    }
+
+    // Marks `key` as recently used, if it is loaded
+    pub fn touch(&self, key: &K) {
+        let mut state = self.state.lock().unwrap();
+
+        state.clock += 1;
+        let clock = state.clock;
+
+        if let Some((_, last_used)) = state.loaded.get_mut(key) {
+            *last_used = clock;
+        }
+    }
+
+    // For items unloaded by their owner
+    pub fn remove(&self, key: &K) {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some((size, _)) = state.loaded.remove(key) {
+            state.used -= size;
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> Default for MemoryBudget<K> {
+    fn default() -> MemoryBudget<K> {
+        MemoryBudget::new(DEFAULT_MEMORY_BUDGET)
+    }
+}
@@ -0,0 +1,89 @@
+// The contents of a file, either mapped from disk or read into memory. Clones and slices share the
+// same memory, so an archive can hand out its entries without copying them.
+
+use std::{fs::File, ops::Deref, ops::Range, sync::Arc};
+
+use memmap2::Mmap;
+
+use crate::Error;
+
+#[derive(Clone)]
+pub struct FileBytes {
+    source: Arc<Source>,
+    range: Range<usize>,
+}
+
+enum Source {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl FileBytes {
+    pub fn map(file: &File) -> Result<FileBytes, Error> {
+        // Empty files can't be mapped on every platform
+        if file.metadata()?.len() == 0 {
+            return Ok(FileBytes::from(Vec::new()));
+        }
+
+        // SAFETY: This crate never truncates or writes in place a file that may be mapped, writes go
+        // to a new file that `os::replace` renames over the old one. Changes made to the file by
+        // other processes while it is mapped are undefined behaviour, which is accepted.
+        let mapped = unsafe { Mmap::map(file)? };
+
+        Ok(FileBytes {
+            range: 0..mapped.len(),
+            source: Arc::new(Source::Mapped(mapped)),
+        })
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        matches!(*self.source, Source::Mapped(_))
+    }
+
+    // Relative to the start of these bytes
+    pub fn slice(&self, range: Range<usize>) -> Result<FileBytes, Error> {
+        if range.start > range.end {
+            return Err(Error::InvalidArgument(format!(
+                "{:?} is not a valid range.",
+                range
+            )));
+        }
+
+        if range.end > self.len() {
+            return Err(Error::insufficient_data(range.end, self.len()));
+        }
+
+        Ok(FileBytes {
+            source: self.source.clone(),
+            range: self.range.start + range.start..self.range.start + range.end,
+        })
+    }
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        let bytes: &[u8] = match &*self.source {
+            Source::Mapped(mapped) => mapped,
+            Source::Owned(bytes) => bytes,
+        };
+
+        &bytes[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for FileBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<Vec<u8>> for FileBytes {
+    fn from(bytes: Vec<u8>) -> FileBytes {
+        FileBytes {
+            range: 0..bytes.len(),
+            source: Arc::new(Source::Owned(bytes)),
+        }
+    }
+}
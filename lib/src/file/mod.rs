@@ -0,0 +1,123 @@
+// Virtual filesystem over OS directories and the archives inside them, so tools can walk game data
+// without caring where an asset is actually stored. Nodes are shared as `Arc<dyn VirtualFile>` and
+// only touch the disk when asked for their contents or children.
+
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::Error;
+use crate::file::archive::ArchiveReader;
+
+pub mod archive;
+pub mod budget;
+mod bytes;
+pub mod os;
+pub mod overlay;
+mod path;
+pub mod table;
+
+pub use bytes::FileBytes;
+pub use path::AssetPath;
+
+// An open file's contents
+pub trait FileHandle: Read + Seek + Send {
+    fn size(&mut self) -> Result<u64, Error> {
+        let position = self.stream_position()?;
+        let size = self.seek(SeekFrom::End(0))?;
+        self.seek(SeekFrom::Start(position))?;
+
+        Ok(size)
+    }
+
+    // Everything from the start, regardless of the current position
+    fn read_to_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        self.seek(SeekFrom::Start(0))?;
+
+        let mut bytes = Vec::new();
+        self.read_to_end(&mut bytes)?;
+
+        Ok(bytes)
+    }
+}
+
+impl<T: Read + Seek + Send> FileHandle for T {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Directory,
+    // A file whose contents can also be browsed as a directory
+    Archive,
+}
+
+pub trait VirtualFile: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Archive entries are addressed as the archive's path joined with the entry name
+    fn path(&self) -> &Path;
+
+    fn kind(&self) -> FileKind;
+
+    fn is_dirlike(&self) -> bool {
+        self.kind() != FileKind::File
+    }
+
+    // Size of the contents in bytes, 0 for directories
+    fn size(&self) -> Result<u64, Error>;
+
+    fn open(&self) -> Result<Box<dyn FileHandle>, Error>;
+
+    fn read(&self) -> Result<Vec<u8>, Error> {
+        self.open()?.read_to_bytes()
+    }
+
+    // The whole contents without copying them where possible, files on disk are memory mapped
+    fn map(&self) -> Result<FileBytes, Error> {
+        Ok(self.read()?.into())
+    }
+
+    // Replaces the contents of a file
+    fn write(&self, _bytes: &[u8]) -> Result<(), Error> {
+        Err(read_only(self.path()))
+    }
+
+    // Creates or replaces a file below a directory, along with any directories leading to it
+    fn write_child(&self, _relative: &Path, _bytes: &[u8]) -> Result<(), Error> {
+        Err(read_only(self.path()))
+    }
+
+    // Whether this is an archive that is parsed and in memory, so listing it is cheap
+    fn is_open(&self) -> bool {
+        false
+    }
+
+    // Parses an archive if it isn't open yet. None for anything that isn't an archive.
+    fn archive_reader(&self) -> Result<Option<Arc<dyn ArchiveReader>>, Error> {
+        Ok(None)
+    }
+
+    // Always empty for plain files. Archives are parsed on the first call.
+    fn children(&self) -> Result<Vec<Arc<dyn VirtualFile>>, Error>;
+
+    fn child(&self, name: &str) -> Result<Option<Arc<dyn VirtualFile>>, Error> {
+        Ok(self
+            .children()?
+            .into_iter()
+            .find(|child| child.name() == name))
+    }
+
+    // None for the root of a filesystem
+    fn parent(&self) -> Option<Arc<dyn VirtualFile>>;
+
+    // Whether an overlay serves this from its top layer instead of the base
+    fn is_overridden(&self) -> bool {
+        false
+    }
+}
+
+pub(crate) fn read_only(path: &Path) -> Error {
+    Error::InvalidArgument(format!("{} is read only.", path.display()))
+}
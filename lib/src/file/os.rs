@@ -0,0 +1,300 @@
+// A directory on disk and everything below it. Files with a registered archive extension are
+// mounted as archives, and stay mounted while they are in use, until they are written to. Files
+// are memory mapped rather than read when their whole contents are needed.
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Component, Path, PathBuf},
+    process,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::Error;
+use crate::file::{
+    FileBytes, FileHandle, FileKind, VirtualFile,
+    archive::{Archive, ArchiveBudget, ArchiveFormats, ArchiveOpener, mount_cached},
+    read_only,
+};
+
+pub struct OsFileSystem {
+    root: PathBuf,
+    // Keyed by extension
+    archive_formats: Arc<ArchiveFormats>,
+    budget: Arc<ArchiveBudget>,
+    mounted: Mutex<HashMap<PathBuf, Weak<Archive>>>,
+    read_only: bool,
+}
+
+impl OsFileSystem {
+    pub fn new(root: impl Into<PathBuf>) -> OsFileSystem {
+        OsFileSystem {
+            root: root.into(),
+            archive_formats: Arc::new(ArchiveFormats::new()),
+            budget: Arc::new(ArchiveBudget::default()),
+            mounted: Mutex::new(HashMap::new()),
+            read_only: false,
+        }
+    }
+
+    // Rejects every write, e.g. for original game data
+    pub fn read_only(mut self) -> OsFileSystem {
+        self.read_only = true;
+        self
+    }
+
+    // Can be shared with other filesystems, like both layers of an overlay
+    pub fn with_memory_budget(mut self, budget: Arc<ArchiveBudget>) -> OsFileSystem {
+        self.budget = budget;
+        self
+    }
+
+    // Also used for entries of archives, which can then be browsed into as well
+    pub fn with_archive_format(mut self, extension: &str, opener: ArchiveOpener) -> OsFileSystem {
+        Arc::make_mut(&mut self.archive_formats).insert(extension, opener);
+        self
+    }
+
+    // The root directory, which must already exist
+    pub fn open(self) -> Result<Arc<dyn VirtualFile>, Error> {
+        if !fs::metadata(&self.root)?.is_dir() {
+            return Err(Error::InvalidArgument(format!(
+                "{} is not a directory.",
+                self.root.display()
+            )));
+        }
+
+        let root = self.root.clone();
+        Ok(Arc::new(self).node(root, true))
+    }
+
+    fn node(self: &Arc<Self>, path: PathBuf, is_dir: bool) -> Arc<dyn VirtualFile> {
+        let opener = path
+            .file_name()
+            .filter(|_| !is_dir)
+            .and_then(|name| self.archive_formats.for_name(&name.to_string_lossy()));
+
+        let file = Arc::new(OsFile {
+            fs: self.clone(),
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string()),
+            kind: if is_dir {
+                FileKind::Directory
+            } else {
+                FileKind::File
+            },
+            path,
+        });
+
+        match opener {
+            Some(opener) => {
+                mount_cached(&mut self.mounted.lock().unwrap(), file.path.clone(), || {
+                    Archive::mount(
+                        file,
+                        opener.clone(),
+                        self.archive_formats.clone(),
+                        self.budget.clone(),
+                    )
+                })
+            }
+            None => file,
+        }
+    }
+
+    // Closes the archive mounted for a file that is about to be replaced, so its old contents
+    // aren't mapped any more. It is mounted again from the new file when next listed.
+    fn unmount(&self, path: &Path) {
+        let archive = self
+            .mounted
+            .lock()
+            .unwrap()
+            .remove(path)
+            .and_then(|archive| archive.upgrade());
+
+        if let Some(archive) = archive {
+            archive.close();
+        }
+    }
+}
+
+struct OsFile {
+    fs: Arc<OsFileSystem>,
+    path: PathBuf,
+    name: String,
+    kind: FileKind,
+}
+
+impl VirtualFile for OsFile {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        match self.kind {
+            FileKind::Directory => Ok(0),
+            _ => Ok(fs::metadata(&self.path)?.len()),
+        }
+    }
+
+    fn open(&self) -> Result<Box<dyn FileHandle>, Error> {
+        if self.kind == FileKind::Directory {
+            return Err(Error::InvalidArgument(format!(
+                "{} is a directory.",
+                self.path.display()
+            )));
+        }
+
+        Ok(Box::new(File::open(&self.path)?))
+    }
+
+    fn read(&self) -> Result<Vec<u8>, Error> {
+        if self.kind == FileKind::Directory {
+            return Err(Error::InvalidArgument(format!(
+                "{} is a directory.",
+                self.path.display()
+            )));
+        }
+
+        Ok(fs::read(&self.path)?)
+    }
+
+    fn map(&self) -> Result<FileBytes, Error> {
+        if self.kind == FileKind::Directory {
+            return Err(Error::InvalidArgument(format!(
+                "{} is a directory.",
+                self.path.display()
+            )));
+        }
+
+        FileBytes::map(&File::open(&self.path)?)
+    }
+
+    fn write(&self, bytes: &[u8]) -> Result<(), Error> {
+        if self.fs.read_only || self.kind == FileKind::Directory {
+            return Err(read_only(&self.path));
+        }
+
+        self.fs.unmount(&self.path);
+        replace(&self.path, bytes)
+    }
+
+    fn write_child(&self, relative: &Path, bytes: &[u8]) -> Result<(), Error> {
+        if self.fs.read_only || self.kind != FileKind::Directory {
+            return Err(read_only(&self.path));
+        }
+
+        // Writes must stay below this directory
+        if relative.file_name().is_none()
+            || relative
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(Error::InvalidArgument(format!(
+                "{} is not a relative file path.",
+                relative.display()
+            )));
+        }
+
+        let path = self.path.join(relative);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        self.fs.unmount(&path);
+        replace(&path, bytes)
+    }
+
+    fn children(&self) -> Result<Vec<Arc<dyn VirtualFile>>, Error> {
+        if self.kind != FileKind::Directory {
+            return Ok(Vec::new());
+        }
+
+        let mut paths = fs::read_dir(&self.path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        paths.sort();
+
+        Ok(paths
+            .into_iter()
+            .map(|path| {
+                // Follows symlinks, unlike the entry's own file type
+                let is_dir = path.is_dir();
+                self.fs.node(path, is_dir)
+            })
+            .collect())
+    }
+
+    fn parent(&self) -> Option<Arc<dyn VirtualFile>> {
+        if self.path == self.fs.root {
+            return None;
+        }
+
+        let parent = self.path.parent()?.to_path_buf();
+        Some(self.fs.node(parent, true))
+    }
+}
+
+// Writes next to the file and renames over it, so the file is never truncated while it is mapped.
+// On Unix mappings of the old contents stay valid. Windows can't replace a file that is still
+// mapped, so everything mapping it has to be dropped first, which `unmount` does for archives.
+fn replace(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    let (temporary, mut file) = temporary_file(path)?;
+
+    let written = file.write_all(bytes).and_then(|()| {
+        drop(file);
+        fs::rename(&temporary, path)
+    });
+
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temporary);
+        return Err(e.into());
+    }
+
+    Ok(())
+}
+
+// A new file next to `path`, with a name no other write uses. Existing files are never reused.
+fn temporary_file(path: &Path) -> Result<(PathBuf, File), Error> {
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+
+    let name = path.file_name().unwrap_or_default();
+
+    loop {
+        let mut temporary_name = OsString::from(".");
+        temporary_name.push(name);
+        temporary_name.push(format!(
+            ".{}-{}.tmp",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let temporary = path.with_file_name(temporary_name);
+
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temporary)
+        {
+            Ok(file) => return Ok((temporary, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
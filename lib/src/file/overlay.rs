@@ -0,0 +1,269 @@
+// A writable top layer, usually a mod folder, over read-only base data. Directories are merged,
+// while a file or archive in the top layer hides the base one at the same path entirely. Writes
+// always go to the top layer, so the base is never modified.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, Weak},
+};
+
+use crate::Error;
+use crate::file::{FileBytes, FileHandle, FileKind, VirtualFile, archive::ArchiveReader};
+
+pub struct OverlayFileSystem {
+    base: Arc<dyn VirtualFile>,
+    top: Arc<dyn VirtualFile>,
+}
+
+impl OverlayFileSystem {
+    pub fn new(base: Arc<dyn VirtualFile>, top: Arc<dyn VirtualFile>) -> OverlayFileSystem {
+        OverlayFileSystem { base, top }
+    }
+
+    // Paths in the merged view are given as if everything were in the base layer
+    pub fn open(self) -> Result<Arc<dyn VirtualFile>, Error> {
+        if self.base.kind() != FileKind::Directory || self.top.kind() != FileKind::Directory {
+            return Err(Error::InvalidArgument(
+                "Both layers of an overlay must be directories.".to_string(),
+            ));
+        }
+
+        let layers = Arc::new(self);
+
+        Ok(OverlayFile::new(
+            layers.clone(),
+            PathBuf::new(),
+            layers.base.name().to_string(),
+            Some(layers.top.clone()),
+            Some(layers.base.clone()),
+            None,
+        ))
+    }
+}
+
+struct OverlayFile {
+    layers: Arc<OverlayFileSystem>,
+    // Below the root of both layers
+    relative: PathBuf,
+    path: PathBuf,
+    name: String,
+    // At least one of these is always set. The top layer is looked up again after every write,
+    // which can add it or replace what it was.
+    top: Mutex<Option<Arc<dyn VirtualFile>>>,
+    base: Option<Arc<dyn VirtualFile>>,
+    parent: Option<Arc<dyn VirtualFile>>,
+    // Entries can only be written by writing their whole archive
+    in_archive: bool,
+    this: Weak<OverlayFile>,
+}
+
+impl OverlayFile {
+    fn new(
+        layers: Arc<OverlayFileSystem>,
+        relative: PathBuf,
+        name: String,
+        top: Option<Arc<dyn VirtualFile>>,
+        base: Option<Arc<dyn VirtualFile>>,
+        parent: Option<Arc<OverlayFile>>,
+    ) -> Arc<OverlayFile> {
+        let in_archive = parent
+            .as_ref()
+            .is_some_and(|parent| parent.in_archive || parent.kind() == FileKind::Archive);
+
+        Arc::new_cyclic(|this| OverlayFile {
+            path: layers.base.path().join(&relative),
+            layers,
+            relative,
+            name,
+            top: Mutex::new(top),
+            base,
+            parent: parent.map(|parent| parent as Arc<dyn VirtualFile>),
+            in_archive,
+            this: this.clone(),
+        })
+    }
+
+    fn top(&self) -> Option<Arc<dyn VirtualFile>> {
+        self.top.lock().unwrap().clone()
+    }
+
+    // The layer whose contents are shown
+    fn visible(&self) -> Arc<dyn VirtualFile> {
+        self.top().or_else(|| self.base.clone()).unwrap()
+    }
+
+    fn is_merged(&self) -> bool {
+        matches!(
+            (self.top(), &self.base),
+            (Some(top), Some(base))
+                if top.kind() == FileKind::Directory && base.kind() == FileKind::Directory
+        )
+    }
+
+    // Finds this node in the top layer again, so a write shows through this node and not only
+    // through ones listed after it
+    fn refresh_top(&self) -> Result<(), Error> {
+        let mut top = Some(self.layers.top.clone());
+
+        for component in self.relative.iter() {
+            let Some(dir) = top else { break };
+            top = dir.child(&component.to_string_lossy())?;
+        }
+
+        *self.top.lock().unwrap() = top;
+
+        Ok(())
+    }
+
+    fn writable(&self) -> Result<(), Error> {
+        if self.in_archive {
+            return Err(Error::InvalidArgument(format!(
+                "{} is inside an archive, write the archive instead.",
+                self.path.display()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl VirtualFile for OverlayFile {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn kind(&self) -> FileKind {
+        if self.is_merged() {
+            FileKind::Directory
+        } else {
+            self.visible().kind()
+        }
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        self.visible().size()
+    }
+
+    fn open(&self) -> Result<Box<dyn FileHandle>, Error> {
+        self.visible().open()
+    }
+
+    fn read(&self) -> Result<Vec<u8>, Error> {
+        self.visible().read()
+    }
+
+    fn map(&self) -> Result<FileBytes, Error> {
+        self.visible().map()
+    }
+
+    fn is_open(&self) -> bool {
+        self.visible().is_open()
+    }
+
+    fn archive_reader(&self) -> Result<Option<Arc<dyn ArchiveReader>>, Error> {
+        self.visible().archive_reader()
+    }
+
+    fn write(&self, bytes: &[u8]) -> Result<(), Error> {
+        self.writable()?;
+
+        if self.kind() == FileKind::Directory {
+            return Err(Error::InvalidArgument(format!(
+                "{} is a directory.",
+                self.path.display()
+            )));
+        }
+
+        self.layers.top.write_child(&self.relative, bytes)?;
+        self.refresh_top()
+    }
+
+    fn write_child(&self, relative: &Path, bytes: &[u8]) -> Result<(), Error> {
+        self.writable()?;
+
+        if self.kind() != FileKind::Directory {
+            return Err(Error::InvalidArgument(format!(
+                "{} is not a directory.",
+                self.path.display()
+            )));
+        }
+
+        self.layers
+            .top
+            .write_child(&self.relative.join(relative), bytes)?;
+        self.refresh_top()
+    }
+
+    // Merged directories are sorted by name, anything else keeps the order of its layer
+    fn children(&self) -> Result<Vec<Arc<dyn VirtualFile>>, Error> {
+        type Layers = (Option<Arc<dyn VirtualFile>>, Option<Arc<dyn VirtualFile>>);
+
+        let mut children: Vec<(String, Layers)> = Vec::new();
+
+        if self.is_merged() {
+            let mut index = HashMap::new();
+
+            for (is_top, layer) in [(true, self.top()), (false, self.base.clone())] {
+                for child in layer.unwrap().children()? {
+                    let name = child.name().to_string();
+
+                    let i = *index.entry(name.clone()).or_insert_with(|| {
+                        children.push((name, (None, None)));
+                        children.len() - 1
+                    });
+
+                    if is_top {
+                        children[i].1.0 = Some(child);
+                    } else {
+                        children[i].1.1 = Some(child);
+                    }
+                }
+            }
+
+            children.sort_by(|a, b| a.0.cmp(&b.0));
+        } else {
+            let is_top = self.top().is_some();
+
+            for child in self.visible().children()? {
+                let layers = if is_top {
+                    (Some(child.clone()), None)
+                } else {
+                    (None, Some(child.clone()))
+                };
+
+                children.push((child.name().to_string(), layers));
+            }
+        }
+
+        // Overlay files only exist inside an Arc, which must still be alive while it is borrowed
+        let this = self.this.upgrade().unwrap();
+
+        Ok(children
+            .into_iter()
+            .map(|(name, (top, base))| {
+                OverlayFile::new(
+                    self.layers.clone(),
+                    self.relative.join(&name),
+                    name,
+                    top,
+                    base,
+                    Some(this.clone()),
+                ) as Arc<dyn VirtualFile>
+            })
+            .collect())
+    }
+
+    fn parent(&self) -> Option<Arc<dyn VirtualFile>> {
+        self.parent.clone()
+    }
+
+    // Anything shown from the top layer except merged directories, which only hold overrides
+    fn is_overridden(&self) -> bool {
+        self.top().is_some() && !self.is_merged()
+    }
+}
@@ -0,0 +1,208 @@
+// Where a file or asset is relative to the root of a filesystem, written the same way on every
+// machine so it can be shared: `levels/foo.bnl:aid_name`. Each `:` steps into an archive, so an
+// asset in a nested archive is `levels/foo.bnl:inner.pak:aid_name`.
+
+use std::{
+    fmt::Display,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::Error;
+use crate::file::{FileKind, VirtualFile};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetPath {
+    // Directories and the file name on disk
+    file: Vec<String>,
+    // One entry name for each archive stepped into
+    entries: Vec<String>,
+}
+
+impl AssetPath {
+    // The root itself
+    pub fn root() -> AssetPath {
+        AssetPath::default()
+    }
+
+    pub fn new(file: &Path, entries: &[&str]) -> Result<AssetPath, Error> {
+        let file = file
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => Ok(name.to_string_lossy().into_owned()),
+                _ => Err(Error::InvalidArgument(format!(
+                    "{} is not a relative path.",
+                    file.display()
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let entries = entries
+            .iter()
+            .map(|entry| entry_name(entry).map(str::to_string))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if file.is_empty() && !entries.is_empty() {
+            return Err(Error::InvalidArgument(
+                "Asset paths need a file to hold their entries.".to_string(),
+            ));
+        }
+
+        Ok(AssetPath { file, entries })
+    }
+
+    // Relative to the root
+    pub fn file(&self) -> PathBuf {
+        self.file.iter().collect()
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    // The innermost entry, None for anything on disk
+    pub fn asset(&self) -> Option<&str> {
+        self.entries.last().map(String::as_str)
+    }
+
+    // The archive holding this asset
+    pub fn archive(&self) -> Option<AssetPath> {
+        let mut archive = self.clone();
+        archive.entries.pop()?;
+
+        Some(archive)
+    }
+
+    // A child of a directory. Everything inside an archive is an entry.
+    pub fn join(&self, name: &str) -> AssetPath {
+        if self.entries.is_empty() {
+            let mut path = self.clone();
+            path.file.push(name.to_string());
+            path
+        } else {
+            self.entry(name)
+        }
+    }
+
+    // A child of an archive
+    pub fn entry(&self, name: &str) -> AssetPath {
+        let mut path = self.clone();
+        path.entries.push(name.to_string());
+        path
+    }
+
+    // Walks up from `file`, which must be below `root`
+    pub fn from_file(
+        root: &Arc<dyn VirtualFile>,
+        file: &Arc<dyn VirtualFile>,
+    ) -> Result<AssetPath, Error> {
+        let mut chain = Vec::new();
+        let mut current = file.clone();
+
+        while current.path() != root.path() {
+            let parent = current.parent().ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "{} is not below {}.",
+                    file.path().display(),
+                    root.path().display()
+                ))
+            })?;
+
+            chain.push(current);
+            current = parent;
+        }
+
+        let mut path = AssetPath::root();
+        let mut parent_kind = root.kind();
+
+        for file in chain.iter().rev() {
+            path = if parent_kind == FileKind::Archive {
+                path.entry(file.name())
+            } else {
+                path.join(file.name())
+            };
+
+            parent_kind = file.kind();
+        }
+
+        Ok(path)
+    }
+
+    pub fn resolve(&self, root: &Arc<dyn VirtualFile>) -> Result<Arc<dyn VirtualFile>, Error> {
+        let mut current = root.clone();
+
+        for name in self.file.iter().chain(&self.entries) {
+            current = current.child(name)?.ok_or_else(|| {
+                Error::InvalidArgument(format!("{} doesn't exist, from {}.", name, self))
+            })?;
+        }
+
+        Ok(current)
+    }
+}
+
+fn entry_name(entry: &str) -> Result<&str, Error> {
+    if entry.is_empty() || entry.contains(':') {
+        return Err(Error::InvalidArgument(format!(
+            "{:?} is not a valid entry name.",
+            entry
+        )));
+    }
+
+    Ok(entry)
+}
+
+impl Display for AssetPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.join("/"))?;
+
+        for entry in &self.entries {
+            write!(f, ":{}", entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Backslashes are taken as separators too, for paths copied on Windows
+impl FromStr for AssetPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<AssetPath, Error> {
+        let mut parts = s.split(':');
+        let file = parts.next().unwrap_or_default();
+
+        if file.starts_with(['/', '\\']) {
+            return Err(Error::InvalidArgument(format!(
+                "{} is not a relative path.",
+                s
+            )));
+        }
+
+        let file = file
+            .split(['/', '\\'])
+            .filter(|name| !name.is_empty())
+            .map(|name| match name {
+                "." | ".." => Err(Error::InvalidArgument(format!(
+                    "{} is not a canonical path.",
+                    s
+                ))),
+                _ => Ok(name.to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let entries = parts
+            .map(|entry| entry_name(entry).map(str::to_string))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if file.is_empty() && !entries.is_empty() {
+            return Err(Error::InvalidArgument(format!(
+                "{} has entries but no file to hold them.",
+                s
+            )));
+        }
+
+        Ok(AssetPath { file, entries })
+    }
+}
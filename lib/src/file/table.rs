@@ -0,0 +1,106 @@
+// Archives that store each entry whole at a known offset. Readers for such formats only parse the
+// entry table, reading an entry is then just slicing the archive's contents, which stay mapped.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::Error;
+use crate::file::{
+    FileBytes,
+    archive::{ArchiveReader, extension},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableEntry {
+    pub name: String,
+    // Relative to the start of the archive
+    pub range: Range<usize>,
+    // Overrides the name's extension as the key the entry is mounted by, see
+    // `ArchiveReader::entry_format`
+    pub format: Option<String>,
+}
+
+pub struct TableArchive {
+    bytes: FileBytes,
+    entries: Vec<TableEntry>,
+    index: HashMap<String, usize>,
+}
+
+impl TableArchive {
+    // Every range must lie inside `bytes`. Entries keep the given order, and with duplicate names
+    // only the first can be read.
+    pub fn new(bytes: FileBytes, entries: Vec<TableEntry>) -> Result<TableArchive, Error> {
+        let mut index = HashMap::with_capacity(entries.len());
+
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.range.start > entry.range.end {
+                return Err(Error::InvalidData(format!(
+                    "Entry {} has an invalid range {:?}.",
+                    entry.name, entry.range
+                )));
+            }
+
+            if entry.range.end > bytes.len() {
+                return Err(Error::insufficient_data(entry.range.end, bytes.len()));
+            }
+
+            index.entry(entry.name.clone()).or_insert(i);
+        }
+
+        Ok(TableArchive {
+            bytes,
+            entries,
+            index,
+        })
+    }
+
+    pub fn bytes(&self) -> &FileBytes {
+        &self.bytes
+    }
+
+    pub fn entries(&self) -> &[TableEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, name: &str) -> Result<&TableEntry, Error> {
+        self.index
+            .get(name)
+            .map(|&i| &self.entries[i])
+            .ok_or_else(|| Error::InvalidArgument(format!("No entry named {}.", name)))
+    }
+}
+
+impl ArchiveReader for TableArchive {
+    fn entry_names(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    fn read_entry(&self, name: &str) -> Result<Vec<u8>, Error> {
+        Ok(self.bytes[self.entry(name)?.range.clone()].to_vec())
+    }
+
+    fn map_entry(&self, name: &str) -> Result<FileBytes, Error> {
+        self.bytes.slice(self.entry(name)?.range.clone())
+    }
+
+    fn entry_size(&self, name: &str) -> Result<u64, Error> {
+        Ok(self.entry(name)?.range.len() as u64)
+    }
+
+    fn entry_format(&self, name: &str) -> Option<String> {
+        let entry = self.entry(name).ok()?;
+
+        entry.format.clone().or_else(|| extension(&entry.name))
+    }
+
+    fn memory_size(&self) -> usize {
+        self.bytes.len()
+            + self
+                .entries
+                .iter()
+                .map(|entry| std::mem::size_of::<TableEntry>() + entry.name.len())
+                .sum::<usize>()
+    }
+}
@@ -0,0 +1,958 @@
+// DirectDraw Surface container, used to hand textures to and from external tools. Surfaces are
+// copied as is in both directions, so a texture can make the round trip without being re-encoded.
+// DDS surfaces are always linear, swizzled textures are unswizzled on the way out.
+
+use crate::Error;
+use crate::format::image::{
+    Image, ImageFormat,
+    codec::ImageCodec,
+    cube::CubeMap,
+    mip::MipChain,
+    raw::PixelFormat,
+    swizzle::{self, Layout},
+    volume::VolumeTexture,
+};
+
+pub const MAGIC: &[u8; 4] = b"DDS ";
+
+const HEADER_SIZE: usize = 124;
+const PIXEL_FORMAT_SIZE: usize = 32;
+const DX10_HEADER_SIZE: usize = 20;
+const PALETTE_SIZE: usize = 256 * 4;
+
+// D3D10 limits, which also keep every size calculation well inside usize
+const MAX_DIMENSION: u32 = 16384;
+const MAX_DEPTH: u32 = 2048;
+
+const CUBE_FACES: u32 = 6;
+
+// Header flags
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDSD_DEPTH: u32 = 0x800000;
+
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+
+// Pixel format flags
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_ALPHA: u32 = 0x2;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_PALETTEINDEXED8: u32 = 0x20;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+// DX10 header values
+const DIMENSION_TEXTURE2D: u32 = 3;
+const DIMENSION_TEXTURE3D: u32 = 4;
+const MISC_TEXTURECUBE: u32 = 0x4;
+
+const ALPHA_MODE_MASK: u32 = 0x7;
+const ALPHA_MODE_UNKNOWN: u32 = 0;
+const ALPHA_MODE_PREMULTIPLIED: u32 = 2;
+
+const DXGI_FORMAT_R8_UNORM: u32 = 61;
+const DXGI_FORMAT_A8_UNORM: u32 = 65;
+const DXGI_FORMAT_BC1_UNORM: u32 = 71;
+const DXGI_FORMAT_BC1_UNORM_SRGB: u32 = 72;
+const DXGI_FORMAT_BC2_UNORM: u32 = 74;
+const DXGI_FORMAT_BC2_UNORM_SRGB: u32 = 75;
+const DXGI_FORMAT_BC3_UNORM: u32 = 77;
+const DXGI_FORMAT_BC3_UNORM_SRGB: u32 = 78;
+const DXGI_FORMAT_B5G6R5_UNORM: u32 = 85;
+const DXGI_FORMAT_B5G5R5A1_UNORM: u32 = 86;
+const DXGI_FORMAT_B8G8R8A8_UNORM: u32 = 87;
+const DXGI_FORMAT_B8G8R8X8_UNORM: u32 = 88;
+const DXGI_FORMAT_B8G8R8A8_UNORM_SRGB: u32 = 91;
+const DXGI_FORMAT_B8G8R8X8_UNORM_SRGB: u32 = 93;
+const DXGI_FORMAT_B4G4R4A4_UNORM: u32 = 115;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureKind {
+    #[default]
+    Texture2D,
+    // Six faces in the order +X, -X, +Y, -Y, +Z, -Z, each with its own mip chain
+    Cube,
+    // Mip levels halve in depth as well, every level holds all of its slices
+    Volume,
+}
+
+// Which header describes the pixel format. Files are written back with the header they were read
+// with, DX10 is required by some tools for sRGB and premultiplied alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderVersion {
+    #[default]
+    Legacy,
+    DX10,
+}
+
+#[derive(Debug)]
+pub struct DDS {
+    format: ImageFormat,
+    kind: TextureKind,
+    header: HeaderVersion,
+    // Only DX10 headers can say a surface holds sRGB colour, legacy files are always read without
+    // it. The surfaces are stored the same either way.
+    srgb: bool,
+
+    width: u32,
+    height: u32,
+    depth: u32,
+    level_count: u32,
+
+    // P8 only, kept in the same A8R8G8B8 layout as the rest of the crate
+    palette: Option<Vec<u8>>,
+
+    // Every surface back to back in DDS order
+    data: Vec<u8>,
+}
+
+impl DDS {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        format: ImageFormat,
+        kind: TextureKind,
+        width: u32,
+        height: u32,
+        depth: u32,
+        level_count: u32,
+        palette: Option<Vec<u8>>,
+        data: Vec<u8>,
+    ) -> Result<DDS, Error> {
+        if format.layout() != Layout::Linear {
+            return Err(Error::InvalidArgument(
+                "DDS surfaces must be linear.".to_string(),
+            ));
+        }
+
+        if matches!(format, ImageFormat::P8(_)) && palette.as_ref().is_none_or(|p| p.len() < 4) {
+            return Err(Error::InvalidArgument(
+                "P8 textures require a palette.".to_string(),
+            ));
+        }
+
+        let dds = DDS {
+            format,
+            kind,
+            header: HeaderVersion::default(),
+            srgb: false,
+            width,
+            height,
+            depth: if kind == TextureKind::Volume {
+                depth
+            } else {
+                1
+            },
+            level_count,
+            palette,
+            data,
+        };
+
+        dds.validate_dimensions()?;
+
+        let bytes_required = dds.data_size();
+
+        if dds.data.len() < bytes_required {
+            return Err(Error::insufficient_data(bytes_required, dds.data.len()));
+        }
+
+        if dds.data.len() > bytes_required {
+            return Err(Error::InvalidData(format!(
+                "Surface data is the wrong size (need {} bytes, have {}).",
+                bytes_required,
+                dds.data.len()
+            )));
+        }
+
+        Ok(dds)
+    }
+
+    pub fn from_image(image: &Image) -> Result<DDS, Error> {
+        let (width, height) = (image.width(), image.height());
+
+        let (data, palette) = match image {
+            Image::Raw(image) => (image.to_bytes(Layout::Linear)?, None),
+            Image::P8(image) => (
+                image.to_bytes(Layout::Linear)?,
+                Some(image.palette_to_bytes()),
+            ),
+            image => (image.to_surface_bytes()?, None),
+        };
+
+        DDS::new(
+            image.format().with_layout(Layout::Linear),
+            TextureKind::Texture2D,
+            width,
+            height,
+            1,
+            1,
+            palette,
+            data,
+        )
+    }
+
+    pub fn from_mip_chain(chain: &MipChain) -> Result<DDS, Error> {
+        DDS::new(
+            chain.format().with_layout(Layout::Linear),
+            TextureKind::Texture2D,
+            chain.width(),
+            chain.height(),
+            1,
+            chain.level_count(),
+            chain.palette().map(|p| p.to_vec()),
+            linear_chain(chain)?,
+        )
+    }
+
+    // Faces in the order +X, -X, +Y, -Y, +Z, -Z, which must all share a format, size and level
+    // count
+    pub fn from_cube_faces(faces: &[MipChain]) -> Result<DDS, Error> {
+        let first = match faces {
+            [first, ..] if faces.len() == CUBE_FACES as usize => first,
+            _ => {
+                return Err(Error::InvalidArgument(format!(
+                    "Cube maps need 6 faces, got {}.",
+                    faces.len()
+                )));
+            }
+        };
+
+        let mut data = Vec::new();
+
+        for face in faces {
+            if face.format() != first.format()
+                || face.width() != first.width()
+                || face.height() != first.height()
+                || face.level_count() != first.level_count()
+            {
+                return Err(Error::InvalidArgument(
+                    "Every cube face must have the same format, size and level count.".to_string(),
+                ));
+            }
+
+            data.append(&mut linear_chain(face)?);
+        }
+
+        DDS::new(
+            first.format().with_layout(Layout::Linear),
+            TextureKind::Cube,
+            first.width(),
+            first.height(),
+            1,
+            first.level_count(),
+            first.palette().map(|p| p.to_vec()),
+            data,
+        )
+    }
+
+    pub fn from_cube_map(cube: &CubeMap) -> Result<DDS, Error> {
+        DDS::from_cube_faces(cube.faces())
+    }
+
+    pub fn from_volume(volume: &VolumeTexture) -> Result<DDS, Error> {
+        let mut data = Vec::new();
+
+        // Slices are always linear, whatever layout the volume is written with
+        for level in 0..volume.level_count() {
+            let (_, _, depth) = VolumeTexture::level_dimensions(
+                volume.width(),
+                volume.height(),
+                volume.depth(),
+                level,
+            );
+
+            for slice in 0..depth {
+                data.extend_from_slice(volume.slice_bytes(slice, level).unwrap_or_default());
+            }
+        }
+
+        DDS::new(
+            volume.format().with_layout(Layout::Linear),
+            TextureKind::Volume,
+            volume.width(),
+            volume.height(),
+            volume.depth(),
+            volume.level_count(),
+            volume.palette().map(|p| p.to_vec()),
+            data,
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<DDS, Error> {
+        if bytes.len() < MAGIC.len() + HEADER_SIZE || &bytes[0..4] != MAGIC {
+            return Err(Error::InvalidData("Not a DDS file.".to_string()));
+        }
+
+        let header = &bytes[4..4 + HEADER_SIZE];
+        let field = |offset: usize| read_u32(header, offset);
+
+        if field(0) as usize != HEADER_SIZE || field(72) as usize != PIXEL_FORMAT_SIZE {
+            return Err(Error::InvalidData(
+                "The DDS header has an invalid size.".to_string(),
+            ));
+        }
+
+        let height = field(8);
+        let width = field(12);
+        let caps2 = field(108);
+
+        let pf_flags = field(76);
+        let four_cc: [u8; 4] = header[80..84].try_into().unwrap();
+
+        let mut offset = MAGIC.len() + HEADER_SIZE;
+
+        let (format, kind, srgb, header_version) =
+            if pf_flags & DDPF_FOURCC != 0 && &four_cc == b"DX10" {
+                let dx10 = bytes.get(offset..offset + DX10_HEADER_SIZE).ok_or(
+                    Error::insufficient_data(offset + DX10_HEADER_SIZE, bytes.len()),
+                )?;
+                offset += DX10_HEADER_SIZE;
+
+                let (format, kind, srgb) = read_dx10_header(dx10)?;
+                (format, kind, srgb, HeaderVersion::DX10)
+            } else {
+                let format = legacy_format(
+                    pf_flags,
+                    four_cc,
+                    field(84),
+                    [field(88), field(92), field(96), field(100)],
+                )?;
+
+                let kind = if caps2 & DDSCAPS2_CUBEMAP != 0 {
+                    if caps2 & DDSCAPS2_CUBEMAP_ALL_FACES != DDSCAPS2_CUBEMAP_ALL_FACES {
+                        return Err(Error::UnsupportedFormat(
+                            "cube maps without all 6 faces".to_string(),
+                        ));
+                    }
+
+                    TextureKind::Cube
+                } else if caps2 & DDSCAPS2_VOLUME != 0 {
+                    TextureKind::Volume
+                } else {
+                    TextureKind::Texture2D
+                };
+
+                (format, kind, false, HeaderVersion::Legacy)
+            };
+
+        let depth = if kind == TextureKind::Volume {
+            field(20).max(1)
+        } else {
+            1
+        };
+
+        // Plenty of writers leave the count at zero when there is only one level
+        let level_count = field(24).max(1);
+
+        let palette = if matches!(format, ImageFormat::P8(_)) {
+            let entries = bytes
+                .get(offset..offset + PALETTE_SIZE)
+                .ok_or(Error::insufficient_data(offset + PALETTE_SIZE, bytes.len()))?;
+            offset += PALETTE_SIZE;
+
+            // Stored as R G B A
+            Some(
+                entries
+                    .chunks_exact(4)
+                    .flat_map(|c| [c[2], c[1], c[0], c[3]])
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        let mut dds = DDS {
+            format,
+            kind,
+            header: header_version,
+            srgb,
+            width,
+            height,
+            depth,
+            level_count,
+            palette,
+            data: Vec::new(),
+        };
+
+        dds.validate_dimensions()?;
+
+        let bytes_required = dds.data_size();
+
+        // Trailing bytes are ignored
+        dds.data = bytes
+            .get(offset..offset + bytes_required)
+            .ok_or(Error::insufficient_data(
+                offset + bytes_required,
+                bytes.len(),
+            ))?
+            .to_vec();
+
+        Ok(dds)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT;
+        let mut caps = DDSCAPS_TEXTURE;
+        let mut caps2 = 0;
+
+        let pitch_or_linear_size = if self.format.is_block_compressed() {
+            flags |= DDSD_LINEARSIZE;
+            self.format.surface_size(self.width, self.height) as u32
+        } else {
+            flags |= DDSD_PITCH;
+            self.width * self.format.block_size() as u32
+        };
+
+        if self.level_count > 1 {
+            flags |= DDSD_MIPMAPCOUNT;
+            caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+        }
+
+        match self.kind {
+            TextureKind::Texture2D => {}
+            TextureKind::Cube => {
+                caps |= DDSCAPS_COMPLEX;
+                caps2 |= DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_ALL_FACES;
+            }
+            TextureKind::Volume => {
+                flags |= DDSD_DEPTH;
+                caps |= DDSCAPS_COMPLEX;
+                caps2 |= DDSCAPS2_VOLUME;
+            }
+        }
+
+        let pixel_format = match self.header {
+            HeaderVersion::Legacy => legacy_pixel_format(self.format),
+            HeaderVersion::DX10 => PixelFormatHeader {
+                flags: DDPF_FOURCC,
+                four_cc: *b"DX10",
+                ..Default::default()
+            },
+        };
+
+        let mut bytes = Vec::with_capacity(
+            MAGIC.len() + HEADER_SIZE + DX10_HEADER_SIZE + PALETTE_SIZE + self.data.len(),
+        );
+
+        bytes.extend_from_slice(MAGIC);
+
+        for value in [
+            HEADER_SIZE as u32,
+            flags,
+            self.height,
+            self.width,
+            pitch_or_linear_size,
+            if self.kind == TextureKind::Volume {
+                self.depth
+            } else {
+                0
+            },
+            self.level_count,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        // Reserved
+        bytes.resize(bytes.len() + 11 * 4, 0);
+
+        bytes.extend_from_slice(&(PIXEL_FORMAT_SIZE as u32).to_le_bytes());
+        bytes.extend_from_slice(&pixel_format.flags.to_le_bytes());
+        bytes.extend_from_slice(&pixel_format.four_cc);
+
+        for value in [pixel_format.bit_count]
+            .into_iter()
+            .chain(pixel_format.masks)
+            .chain([caps, caps2, 0, 0, 0])
+        {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        if self.header == HeaderVersion::DX10 {
+            bytes.extend_from_slice(&self.dx10_header()?);
+        }
+
+        if let Some(palette) = &self.palette {
+            let mut entries: Vec<u8> = palette
+                .chunks_exact(4)
+                .take(PALETTE_SIZE / 4)
+                .flat_map(|c| [c[2], c[1], c[0], c[3]])
+                .collect();
+
+            entries.resize(PALETTE_SIZE, 0);
+            bytes.append(&mut entries);
+        }
+
+        bytes.extend_from_slice(&self.data);
+
+        Ok(bytes)
+    }
+
+    fn dx10_header(&self) -> Result<[u8; DX10_HEADER_SIZE], Error> {
+        let dxgi_format = match (self.format, self.srgb) {
+            (ImageFormat::DXT1, false) => DXGI_FORMAT_BC1_UNORM,
+            (ImageFormat::DXT1, true) => DXGI_FORMAT_BC1_UNORM_SRGB,
+            (ImageFormat::DXT2 | ImageFormat::DXT3, false) => DXGI_FORMAT_BC2_UNORM,
+            (ImageFormat::DXT2 | ImageFormat::DXT3, true) => DXGI_FORMAT_BC2_UNORM_SRGB,
+            (ImageFormat::DXT4 | ImageFormat::DXT5, false) => DXGI_FORMAT_BC3_UNORM,
+            (ImageFormat::DXT4 | ImageFormat::DXT5, true) => DXGI_FORMAT_BC3_UNORM_SRGB,
+            (ImageFormat::Raw(PixelFormat::A8R8G8B8, _), false) => DXGI_FORMAT_B8G8R8A8_UNORM,
+            (ImageFormat::Raw(PixelFormat::A8R8G8B8, _), true) => DXGI_FORMAT_B8G8R8A8_UNORM_SRGB,
+            (ImageFormat::Raw(PixelFormat::X8R8G8B8, _), false) => DXGI_FORMAT_B8G8R8X8_UNORM,
+            (ImageFormat::Raw(PixelFormat::X8R8G8B8, _), true) => DXGI_FORMAT_B8G8R8X8_UNORM_SRGB,
+            (ImageFormat::Raw(PixelFormat::R5G6B5, _), false) => DXGI_FORMAT_B5G6R5_UNORM,
+            (ImageFormat::Raw(PixelFormat::A1R5G5B5, _), false) => DXGI_FORMAT_B5G5R5A1_UNORM,
+            (ImageFormat::Raw(PixelFormat::A4R4G4B4, _), false) => DXGI_FORMAT_B4G4R4A4_UNORM,
+            (ImageFormat::Raw(PixelFormat::A8, _), false) => DXGI_FORMAT_A8_UNORM,
+            (ImageFormat::Raw(PixelFormat::L8, _), false) => DXGI_FORMAT_R8_UNORM,
+            (format, true) => {
+                return Err(Error::UnsupportedFormat(format!(
+                    "{:?} has no sRGB DX10 equivalent",
+                    format
+                )));
+            }
+            (format, false) => {
+                return Err(Error::UnsupportedFormat(format!(
+                    "{:?} has no DX10 equivalent, use a legacy header",
+                    format
+                )));
+            }
+        };
+
+        let alpha_mode = match self.format {
+            ImageFormat::DXT2 | ImageFormat::DXT4 => ALPHA_MODE_PREMULTIPLIED,
+            _ => ALPHA_MODE_UNKNOWN,
+        };
+
+        let (dimension, misc_flags) = match self.kind {
+            TextureKind::Texture2D => (DIMENSION_TEXTURE2D, 0),
+            TextureKind::Cube => (DIMENSION_TEXTURE2D, MISC_TEXTURECUBE),
+            TextureKind::Volume => (DIMENSION_TEXTURE3D, 0),
+        };
+
+        let mut header = [0u8; DX10_HEADER_SIZE];
+
+        for (i, value) in [dxgi_format, dimension, misc_flags, 1, alpha_mode]
+            .into_iter()
+            .enumerate()
+        {
+            header[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+
+        Ok(header)
+    }
+
+    fn validate_dimensions(&self) -> Result<(), Error> {
+        if self.width == 0
+            || self.height == 0
+            || self.depth == 0
+            || self.width > MAX_DIMENSION
+            || self.height > MAX_DIMENSION
+            || self.depth > MAX_DEPTH
+        {
+            return Err(Error::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+                depth: self.depth,
+                reason: "outside the range supported by DDS",
+            });
+        }
+
+        let full_level_count = MipChain::full_level_count(self.width.max(self.depth), self.height);
+
+        if self.level_count > full_level_count {
+            return Err(Error::InvalidLevelCount {
+                level_count: self.level_count,
+                max: full_level_count,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn level_depth(&self, level: u32) -> u32 {
+        self.depth.checked_shr(level).unwrap_or(0).max(1)
+    }
+
+    fn level_size(&self, level: u32) -> usize {
+        let (width, height) = MipChain::level_dimensions(self.width, self.height, level);
+        self.format.surface_size(width, height)
+    }
+
+    // Bytes taken by one face's full mip chain
+    fn chain_size(&self) -> usize {
+        (0..self.level_count).map(|l| self.level_size(l)).sum()
+    }
+
+    fn data_size(&self) -> usize {
+        match self.kind {
+            TextureKind::Texture2D => self.chain_size(),
+            TextureKind::Cube => self.chain_size() * CUBE_FACES as usize,
+            TextureKind::Volume => (0..self.level_count)
+                .map(|l| self.level_size(l) * self.level_depth(l) as usize)
+                .sum(),
+        }
+    }
+
+    // Number of faces for cube maps, depth slices in the given level for volumes, otherwise 1
+    pub fn surface_count(&self, level: u32) -> u32 {
+        match self.kind {
+            TextureKind::Texture2D => 1,
+            TextureKind::Cube => CUBE_FACES,
+            TextureKind::Volume => self.level_depth(level),
+        }
+    }
+
+    // `index` selects the cube face or volume slice, and is 0 for plain textures
+    pub fn surface(&self, index: u32, level: u32) -> Option<&[u8]> {
+        if level >= self.level_count || index >= self.surface_count(level) {
+            return None;
+        }
+
+        let offset = match self.kind {
+            TextureKind::Texture2D | TextureKind::Cube => {
+                index as usize * self.chain_size()
+                    + (0..level).map(|l| self.level_size(l)).sum::<usize>()
+            }
+            TextureKind::Volume => {
+                (0..level)
+                    .map(|l| self.level_size(l) * self.level_depth(l) as usize)
+                    .sum::<usize>()
+                    + index as usize * self.level_size(level)
+            }
+        };
+
+        self.data.get(offset..offset + self.level_size(level))
+    }
+
+    pub fn decode(&self, index: u32, level: u32) -> Result<Image, Error> {
+        let bytes = self
+            .surface(index, level)
+            .ok_or(Error::SurfaceOutOfRange { index, level })?;
+
+        let (width, height) = MipChain::level_dimensions(self.width, self.height, level);
+
+        self.format
+            .decode(bytes, width, height, self.palette.as_deref())
+    }
+
+    // The mip chain of a plain texture or one cube face, ready to be swizzled back with
+    // `MipChain::set_layout` where needed
+    pub fn mip_chain(&self, face: u32) -> Result<MipChain, Error> {
+        if self.kind == TextureKind::Volume || face >= self.surface_count(0) {
+            return Err(Error::SurfaceOutOfRange {
+                index: face,
+                level: 0,
+            });
+        }
+
+        let chain_size = self.chain_size();
+        let offset = face as usize * chain_size;
+
+        let mut chain = MipChain::from_bytes(
+            &self.data[offset..offset + chain_size],
+            self.width,
+            self.height,
+            self.format,
+            self.level_count,
+        )?;
+
+        if let Some(palette) = &self.palette {
+            chain.set_palette(palette.clone());
+        }
+
+        Ok(chain)
+    }
+
+    pub fn cube_map(&self) -> Result<CubeMap, Error> {
+        if self.kind != TextureKind::Cube {
+            return Err(Error::InvalidArgument(
+                "This texture is not a cube map.".to_string(),
+            ));
+        }
+
+        CubeMap::from_faces(
+            (0..CUBE_FACES)
+                .map(|face| self.mip_chain(face))
+                .collect::<Result<Vec<_>, Error>>()?,
+        )
+    }
+
+    pub fn volume(&self) -> Result<VolumeTexture, Error> {
+        if self.kind != TextureKind::Volume {
+            return Err(Error::InvalidArgument(
+                "This texture is not a volume.".to_string(),
+            ));
+        }
+
+        let mut volume = VolumeTexture::from_bytes(
+            &self.data,
+            self.width,
+            self.height,
+            self.depth,
+            self.format,
+            self.level_count,
+        )?;
+
+        if let Some(palette) = &self.palette {
+            volume.set_palette(palette.clone());
+        }
+
+        Ok(volume)
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    pub fn kind(&self) -> TextureKind {
+        self.kind
+    }
+
+    pub fn header_version(&self) -> HeaderVersion {
+        self.header
+    }
+
+    pub fn set_header_version(&mut self, header: HeaderVersion) {
+        self.header = header;
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
+    // Written out with a DX10 header only, legacy headers have nowhere to keep it
+    pub fn set_srgb(&mut self, srgb: bool) {
+        self.srgb = srgb;
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn level_count(&self) -> u32 {
+        self.level_count
+    }
+
+    pub fn palette(&self) -> Option<&[u8]> {
+        self.palette.as_deref()
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[derive(Debug, Default)]
+struct PixelFormatHeader {
+    flags: u32,
+    four_cc: [u8; 4],
+    bit_count: u32,
+    // R, G, B, A
+    masks: [u32; 4],
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn unsupported(description: String) -> Error {
+    Error::UnsupportedFormat(format!("DDS pixel format ({})", description))
+}
+
+fn legacy_pixel_format(format: ImageFormat) -> PixelFormatHeader {
+    let four_cc = |four_cc: &[u8; 4]| PixelFormatHeader {
+        flags: DDPF_FOURCC,
+        four_cc: *four_cc,
+        ..Default::default()
+    };
+
+    let masked = |flags: u32, bit_count: u32, masks: [u32; 4]| PixelFormatHeader {
+        flags,
+        four_cc: [0; 4],
+        bit_count,
+        masks,
+    };
+
+    match format {
+        ImageFormat::DXT1 => four_cc(b"DXT1"),
+        ImageFormat::DXT2 => four_cc(b"DXT2"),
+        ImageFormat::DXT3 => four_cc(b"DXT3"),
+        ImageFormat::DXT4 => four_cc(b"DXT4"),
+        ImageFormat::DXT5 => four_cc(b"DXT5"),
+        ImageFormat::P8(_) => masked(DDPF_PALETTEINDEXED8, 8, [0; 4]),
+        ImageFormat::Raw(format, _) => match format {
+            PixelFormat::A8R8G8B8 => masked(
+                DDPF_RGB | DDPF_ALPHAPIXELS,
+                32,
+                [0xFF0000, 0xFF00, 0xFF, 0xFF000000],
+            ),
+            PixelFormat::X8R8G8B8 => masked(DDPF_RGB, 32, [0xFF0000, 0xFF00, 0xFF, 0]),
+            PixelFormat::R5G6B5 => masked(DDPF_RGB, 16, [0xF800, 0x7E0, 0x1F, 0]),
+            PixelFormat::X1R5G5B5 => masked(DDPF_RGB, 16, [0x7C00, 0x3E0, 0x1F, 0]),
+            PixelFormat::A1R5G5B5 => masked(
+                DDPF_RGB | DDPF_ALPHAPIXELS,
+                16,
+                [0x7C00, 0x3E0, 0x1F, 0x8000],
+            ),
+            PixelFormat::A4R4G4B4 => {
+                masked(DDPF_RGB | DDPF_ALPHAPIXELS, 16, [0xF00, 0xF0, 0xF, 0xF000])
+            }
+            PixelFormat::L8 => masked(DDPF_LUMINANCE, 8, [0xFF, 0, 0, 0]),
+            PixelFormat::A8 => masked(DDPF_ALPHA, 8, [0, 0, 0, 0xFF]),
+        },
+    }
+}
+
+fn legacy_format(
+    flags: u32,
+    four_cc: [u8; 4],
+    bit_count: u32,
+    masks: [u32; 4],
+) -> Result<ImageFormat, Error> {
+    if flags & DDPF_FOURCC != 0 {
+        return match &four_cc {
+            b"DXT1" => Ok(ImageFormat::DXT1),
+            b"DXT2" => Ok(ImageFormat::DXT2),
+            b"DXT3" => Ok(ImageFormat::DXT3),
+            b"DXT4" => Ok(ImageFormat::DXT4),
+            b"DXT5" => Ok(ImageFormat::DXT5),
+            _ => Err(unsupported(format!(
+                "FourCC {}",
+                String::from_utf8_lossy(&four_cc)
+            ))),
+        };
+    }
+
+    if flags & DDPF_PALETTEINDEXED8 != 0 {
+        return Ok(ImageFormat::P8(Layout::Linear));
+    }
+
+    // Masks of channels the flags say are absent are ignored, some writers leave junk in them
+    let has_alpha = flags & (DDPF_ALPHAPIXELS | DDPF_ALPHA) != 0;
+    let alpha_mask = if has_alpha { masks[3] } else { 0 };
+
+    let pixel_format = if flags & DDPF_RGB != 0 {
+        match (bit_count, masks[0], masks[1], masks[2], alpha_mask) {
+            (32, 0xFF0000, 0xFF00, 0xFF, 0xFF000000) => Some(PixelFormat::A8R8G8B8),
+            (32, 0xFF0000, 0xFF00, 0xFF, 0) => Some(PixelFormat::X8R8G8B8),
+            (16, 0xF800, 0x7E0, 0x1F, 0) => Some(PixelFormat::R5G6B5),
+            (16, 0x7C00, 0x3E0, 0x1F, 0) => Some(PixelFormat::X1R5G5B5),
+            (16, 0x7C00, 0x3E0, 0x1F, 0x8000) => Some(PixelFormat::A1R5G5B5),
+            (16, 0xF00, 0xF0, 0xF, 0xF000) => Some(PixelFormat::A4R4G4B4),
+            _ => None,
+        }
+    } else if flags & DDPF_LUMINANCE != 0 && bit_count == 8 && !has_alpha {
+        Some(PixelFormat::L8)
+    } else if flags & DDPF_ALPHA != 0 && bit_count == 8 {
+        Some(PixelFormat::A8)
+    } else {
+        None
+    };
+
+    pixel_format
+        .map(|format| ImageFormat::Raw(format, Layout::Linear))
+        .ok_or_else(|| {
+            unsupported(format!(
+                "flags {:#x}, {} bits, masks {:#x} {:#x} {:#x} {:#x}",
+                flags, bit_count, masks[0], masks[1], masks[2], masks[3]
+            ))
+        })
+}
+
+// Also returns whether the format is an sRGB variant
+fn read_dx10_header(header: &[u8]) -> Result<(ImageFormat, TextureKind, bool), Error> {
+    let dxgi_format = read_u32(header, 0);
+    let dimension = read_u32(header, 4);
+    let misc_flags = read_u32(header, 8);
+    let array_size = read_u32(header, 12);
+    let premultiplied = read_u32(header, 16) & ALPHA_MODE_MASK == ALPHA_MODE_PREMULTIPLIED;
+
+    if array_size > 1 {
+        return Err(Error::UnsupportedFormat("texture arrays".to_string()));
+    }
+
+    let kind = match dimension {
+        DIMENSION_TEXTURE2D if misc_flags & MISC_TEXTURECUBE != 0 => TextureKind::Cube,
+        DIMENSION_TEXTURE2D => TextureKind::Texture2D,
+        DIMENSION_TEXTURE3D => TextureKind::Volume,
+        _ => {
+            return Err(Error::UnsupportedFormat(format!(
+                "DX10 resource dimension {}",
+                dimension
+            )));
+        }
+    };
+
+    let srgb = matches!(
+        dxgi_format,
+        DXGI_FORMAT_BC1_UNORM_SRGB
+            | DXGI_FORMAT_BC2_UNORM_SRGB
+            | DXGI_FORMAT_BC3_UNORM_SRGB
+            | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB
+            | DXGI_FORMAT_B8G8R8X8_UNORM_SRGB
+    );
+
+    // sRGB variants share their storage with the linear ones
+    let format = match dxgi_format {
+        DXGI_FORMAT_BC1_UNORM | DXGI_FORMAT_BC1_UNORM_SRGB => ImageFormat::DXT1,
+        DXGI_FORMAT_BC2_UNORM | DXGI_FORMAT_BC2_UNORM_SRGB if premultiplied => ImageFormat::DXT2,
+        DXGI_FORMAT_BC2_UNORM | DXGI_FORMAT_BC2_UNORM_SRGB => ImageFormat::DXT3,
+        DXGI_FORMAT_BC3_UNORM | DXGI_FORMAT_BC3_UNORM_SRGB if premultiplied => ImageFormat::DXT4,
+        DXGI_FORMAT_BC3_UNORM | DXGI_FORMAT_BC3_UNORM_SRGB => ImageFormat::DXT5,
+        DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB => {
+            ImageFormat::Raw(PixelFormat::A8R8G8B8, Layout::Linear)
+        }
+        DXGI_FORMAT_B8G8R8X8_UNORM | DXGI_FORMAT_B8G8R8X8_UNORM_SRGB => {
+            ImageFormat::Raw(PixelFormat::X8R8G8B8, Layout::Linear)
+        }
+        DXGI_FORMAT_B5G6R5_UNORM => ImageFormat::Raw(PixelFormat::R5G6B5, Layout::Linear),
+        DXGI_FORMAT_B5G5R5A1_UNORM => ImageFormat::Raw(PixelFormat::A1R5G5B5, Layout::Linear),
+        DXGI_FORMAT_B4G4R4A4_UNORM => ImageFormat::Raw(PixelFormat::A4R4G4B4, Layout::Linear),
+        DXGI_FORMAT_A8_UNORM => ImageFormat::Raw(PixelFormat::A8, Layout::Linear),
+        // Single channel textures are the closest DX10 gets to luminance
+        DXGI_FORMAT_R8_UNORM => ImageFormat::Raw(PixelFormat::L8, Layout::Linear),
+        _ => return Err(unsupported(format!("DXGI format {}", dxgi_format))),
+    };
+
+    Ok((format, kind, srgb))
+}
+
+// Every level of the chain concatenated, unswizzled if needed
+fn linear_chain(chain: &MipChain) -> Result<Vec<u8>, Error> {
+    let format = chain.format();
+    let mut data = Vec::new();
+
+    for level in 0..chain.level_count() {
+        let (width, height) = MipChain::level_dimensions(chain.width(), chain.height(), level);
+        let bytes = chain.level_bytes(level).unwrap_or_default();
+
+        data.append(&mut swizzle::relayout(
+            bytes,
+            width,
+            height,
+            format.block_size(),
+            format.layout(),
+            Layout::Linear,
+        )?);
+    }
+
+    Ok(data)
+}
@@ -0,0 +1,200 @@
+// Alpha blocks shared between the DXT2-DXT5 formats. Each one covers a 4x4 texel block and
+// occupies the first 8 bytes of the 16 byte compressed block.
+
+use crate::Error;
+use crate::format::image::encoder;
+
+pub const ALPHA_BLOCK_SIZE: usize = 8;
+
+// How colour should be returned for formats which store it premultiplied by alpha (DXT2/DXT4)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaMode {
+    // Colour as stored in the texture
+    Premultiplied,
+    // Colour divided back out by alpha, as expected by PNG and most image viewers
+    #[default]
+    Straight,
+}
+
+// Converts premultiplied RGBA8 bytes to straight alpha in place. Fully transparent texels carry no
+// colour information, so they are left as stored.
+pub fn unpremultiply(bytes: &mut [u8]) {
+    for texel in bytes.chunks_exact_mut(4) {
+        let a = texel[3] as u32;
+
+        if a == 0 || a == u8::MAX as u32 {
+            continue;
+        }
+
+        for c in &mut texel[0..3] {
+            *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+        }
+    }
+}
+
+// DXT2/DXT3: 4 bits of alpha per texel, row by row, lowest nibble first
+#[derive(Debug, Clone, Copy)]
+pub struct ExplicitAlphaBlock {
+    data: u64,
+}
+
+impl ExplicitAlphaBlock {
+    pub fn from_bytes(bytes: &[u8]) -> Result<ExplicitAlphaBlock, Error> {
+        if bytes.len() < ALPHA_BLOCK_SIZE {
+            return Err(Error::insufficient_data(ALPHA_BLOCK_SIZE, bytes.len()));
+        }
+
+        Ok(ExplicitAlphaBlock {
+            data: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+        })
+    }
+
+    pub fn from_rgba(texels: &[[u8; 4]; 16]) -> ExplicitAlphaBlock {
+        ExplicitAlphaBlock {
+            data: encoder::encode_explicit_alpha(texels),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; ALPHA_BLOCK_SIZE] {
+        self.data.to_le_bytes()
+    }
+
+    pub fn alpha(&self, row: usize, col: usize) -> u8 {
+        let nibble = (self.data >> (4 * (row * 4 + col))) & 0b1111;
+
+        // Expand 4 bits to 8 by replicating the nibble
+        (nibble * 17) as u8
+    }
+
+    pub fn rows(&self) -> [[u8; 4]; 4] {
+        std::array::from_fn(|row| std::array::from_fn(|col| self.alpha(row, col)))
+    }
+
+    // Every texel's alpha in row order
+    pub fn values(&self) -> [u8; 16] {
+        std::array::from_fn(|i| self.alpha(i / 4, i % 4))
+    }
+}
+
+// DXT4/DXT5: two 8 bit endpoints followed by a 3 bit palette index per texel (48 bits total)
+#[derive(Debug, Clone, Copy)]
+pub struct InterpolatedAlphaBlock {
+    alpha_1: u8,
+    alpha_2: u8,
+    indices: u64,
+}
+
+impl InterpolatedAlphaBlock {
+    pub fn from_bytes(bytes: &[u8]) -> Result<InterpolatedAlphaBlock, Error> {
+        if bytes.len() < ALPHA_BLOCK_SIZE {
+            return Err(Error::insufficient_data(ALPHA_BLOCK_SIZE, bytes.len()));
+        }
+
+        let mut packed = [0u8; 8];
+        packed[..6].copy_from_slice(&bytes[2..8]);
+
+        Ok(InterpolatedAlphaBlock {
+            alpha_1: bytes[0],
+            alpha_2: bytes[1],
+            indices: u64::from_le_bytes(packed),
+        })
+    }
+
+    pub fn from_rgba(texels: &[[u8; 4]; 16]) -> InterpolatedAlphaBlock {
+        let alphas = texels.map(|t| t[3]);
+
+        let min = alphas.iter().copied().min().unwrap_or(0);
+        let max = alphas.iter().copied().max().unwrap_or(0);
+
+        // Eight alpha mode spread over the full range of the block
+        let eight = InterpolatedAlphaBlock::fit(max, min, &alphas);
+
+        // Six alpha mode, leaving fully transparent and opaque texels to the explicit entries
+        let inner = alphas.iter().copied().filter(|&a| a != 0 && a != u8::MAX);
+        let six = InterpolatedAlphaBlock::fit(
+            inner.clone().min().unwrap_or(0),
+            inner.max().unwrap_or(0),
+            &alphas,
+        );
+
+        if six.1 < eight.1 { six.0 } else { eight.0 }
+    }
+
+    // Picks the closest palette entry for every texel, returning the block and its squared error
+    fn fit(alpha_1: u8, alpha_2: u8, alphas: &[u8; 16]) -> (InterpolatedAlphaBlock, u32) {
+        let mut block = InterpolatedAlphaBlock {
+            alpha_1,
+            alpha_2,
+            indices: 0,
+        };
+
+        let palette = block.palette();
+        let mut error = 0;
+
+        for (i, &a) in alphas.iter().enumerate() {
+            let (index, distance) = palette
+                .iter()
+                .map(|&p| (p as i32 - a as i32).pow(2) as u32)
+                .enumerate()
+                .min_by_key(|(_, d)| *d)
+                .unwrap();
+
+            block.indices |= (index as u64) << (3 * i);
+            error += distance;
+        }
+
+        (block, error)
+    }
+
+    pub fn to_bytes(&self) -> [u8; ALPHA_BLOCK_SIZE] {
+        let mut bytes = [0u8; ALPHA_BLOCK_SIZE];
+
+        bytes[0] = self.alpha_1;
+        bytes[1] = self.alpha_2;
+        bytes[2..8].copy_from_slice(&self.indices.to_le_bytes()[..6]);
+
+        bytes
+    }
+
+    // Eight alpha mode is used when the first endpoint is larger, otherwise the block holds four
+    // interpolated values plus explicit fully transparent and fully opaque entries
+    pub fn is_eight_alpha_mode(&self) -> bool {
+        self.alpha_1 > self.alpha_2
+    }
+
+    pub fn palette(&self) -> [u8; 8] {
+        let a1 = self.alpha_1 as u32;
+        let a2 = self.alpha_2 as u32;
+
+        let mut palette = [self.alpha_1, self.alpha_2, 0, 0, 0, 0, 0, u8::MAX];
+
+        let steps = if self.is_eight_alpha_mode() { 7 } else { 5 };
+
+        for (weight, value) in (1..steps).zip(palette.iter_mut().skip(2)) {
+            *value = (((steps - weight) * a1 + weight * a2) / steps) as u8;
+        }
+
+        palette
+    }
+
+    pub fn index(&self, row: usize, col: usize) -> usize {
+        ((self.indices >> (3 * (row * 4 + col))) & 0b111) as usize
+    }
+
+    pub fn alpha(&self, row: usize, col: usize) -> u8 {
+        self.palette()[self.index(row, col)]
+    }
+
+    pub fn rows(&self) -> [[u8; 4]; 4] {
+        let values = self.values();
+
+        std::array::from_fn(|row| std::array::from_fn(|col| values[row * 4 + col]))
+    }
+
+    // Every texel's alpha in row order, looking the palette up only once
+    pub fn values(&self) -> [u8; 16] {
+        let palette = self.palette();
+
+        std::array::from_fn(|i| palette[((self.indices >> (3 * i)) & 0b111) as usize])
+    }
+}
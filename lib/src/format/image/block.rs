@@ -0,0 +1,105 @@
+// Decoding shared by the block compressed formats. Blocks are decoded straight into the caller's
+// RGBA8 buffer one block row at a time, which with the `rayon` feature enabled happens in parallel.
+
+use crate::Error;
+use crate::format::image::{
+    codec,
+    types::{R5G6B5Colour, RGBColour},
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+pub(crate) type Texel = [u8; 4];
+
+pub(crate) trait DecodeBlock: Sync {
+    // Every texel of the block in row order
+    fn texels(&self) -> [Texel; 16];
+}
+
+// The four colours addressable by a colour block. In three colour mode (DXT1 only) the last entry
+// is transparent black.
+pub(crate) fn colour_palette(
+    colour_1: R5G6B5Colour,
+    colour_2: R5G6B5Colour,
+    three_colour: bool,
+) -> [Texel; 4] {
+    let c1: RGBColour = colour_1.into();
+    let c2: RGBColour = colour_2.into();
+
+    let c1 = [c1.r as u32, c1.g as u32, c1.b as u32];
+    let c2 = [c2.r as u32, c2.g as u32, c2.b as u32];
+
+    let mix = |w1: u32, w2: u32| -> Texel {
+        let d = w1 + w2;
+        [
+            ((w1 * c1[0] + w2 * c2[0]) / d) as u8,
+            ((w1 * c1[1] + w2 * c2[1]) / d) as u8,
+            ((w1 * c1[2] + w2 * c2[2]) / d) as u8,
+            u8::MAX,
+        ]
+    };
+
+    if three_colour {
+        [mix(1, 0), mix(0, 1), mix(1, 1), [0, 0, 0, 0]]
+    } else {
+        [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+    }
+}
+
+// Looks up the 2 bit colour index of every texel
+pub(crate) fn colour_texels(palette: &[Texel; 4], indices: u32) -> [Texel; 16] {
+    std::array::from_fn(|i| palette[((indices >> (2 * i)) & 0b11) as usize])
+}
+
+pub(crate) fn check_output(out: &[u8], width: u32, height: u32) -> Result<(), Error> {
+    let bytes_required = codec::rgba_size(width, height)?;
+
+    if out.len() < bytes_required {
+        return Err(Error::BufferTooSmall {
+            expected: bytes_required,
+            actual: out.len(),
+        });
+    }
+
+    Ok(())
+}
+
+// `out` must have already been checked to hold width * height RGBA8 texels
+pub(crate) fn decode_blocks<B: DecodeBlock>(blocks: &[B], width: u32, height: u32, out: &mut [u8]) {
+    let blocks_x = width.div_ceil(4) as usize;
+    let row_bytes = width as usize * 4;
+
+    let out = &mut out[..row_bytes * height as usize];
+
+    let decode_row = |(block_y, rows): (usize, &mut [u8])| {
+        let row_count = rows.len() / row_bytes;
+
+        for block_x in 0..blocks_x {
+            let texels = blocks[block_y * blocks_x + block_x].texels();
+
+            // Edge blocks are padded out to 4x4, so only copy the texels inside the image
+            let x = block_x * 4;
+            let col_count = (width as usize - x).min(4);
+
+            for row in 0..row_count {
+                let offset = row * row_bytes + x * 4;
+
+                for col in 0..col_count {
+                    rows[offset + col * 4..offset + col * 4 + 4]
+                        .copy_from_slice(&texels[row * 4 + col]);
+                }
+            }
+        }
+    };
+
+    #[cfg(feature = "rayon")]
+    out.par_chunks_mut(row_bytes * 4)
+        .enumerate()
+        .for_each(decode_row);
+
+    #[cfg(not(feature = "rayon"))]
+    out.chunks_mut(row_bytes * 4)
+        .enumerate()
+        .for_each(decode_row);
+}
@@ -0,0 +1,65 @@
+use crate::Error;
+use crate::format::image::{ImageFormat, encoder::EncodeQuality};
+
+// Common interface over every texture format, so textures can be handled without matching on the
+// concrete type
+pub trait ImageCodec: Sized {
+    fn format(&self) -> ImageFormat;
+
+    fn width(&self) -> u32;
+
+    fn height(&self) -> u32;
+
+    // Straight alpha RGBA8, row by row, into a buffer of at least width * height * 4 bytes
+    fn decode_rgba_into(&self, out: &mut [u8]) -> Result<(), Error>;
+
+    fn decode_rgba(&self) -> Vec<u8> {
+        let mut rgba = vec![0u8; self.width() as usize * self.height() as usize * 4];
+
+        // The buffer is always large enough, so this can't fail
+        self.decode_rgba_into(&mut rgba).unwrap();
+        rgba
+    }
+
+    // `format` must describe the implementing type. It carries the pixel format and layout for the
+    // uncompressed types, which can't be inferred from the RGBA input.
+    fn encode_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        quality: EncodeQuality,
+    ) -> Result<Self, Error>;
+
+    // The surface in its on disk layout
+    fn to_surface_bytes(&self) -> Result<Vec<u8>, Error>;
+
+    fn block_dimensions(&self) -> (u32, u32) {
+        self.format().block_dimensions()
+    }
+
+    fn block_size(&self) -> usize {
+        self.format().block_size()
+    }
+
+    fn bytes_per_level(&self) -> usize {
+        self.format().surface_size(self.width(), self.height())
+    }
+}
+
+// Bytes taken by a width x height RGBA8 image. Sizes often come straight from game files, so this
+// fails rather than overflowing.
+pub(crate) fn rgba_size(width: u32, height: u32) -> Result<usize, Error> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|texels| texels.checked_mul(4))
+        .ok_or(Error::invalid_dimensions(
+            width,
+            height,
+            "too large to decode",
+        ))
+}
+
+pub(crate) fn format_mismatch(codec: &str, format: ImageFormat) -> Error {
+    Error::InvalidArgument(format!("Cannot encode {:?} as {}.", format, codec))
+}
@@ -0,0 +1,353 @@
+use crate::Error;
+use crate::format::image::{
+    Image, ImageFormat,
+    codec::ImageCodec,
+    encoder::{self, EncodeQuality},
+    mip::{MipChain, MipFilter},
+    p8::{MAX_PALETTE_SIZE, P8Image},
+};
+
+pub const FACE_COUNT: usize = 6;
+
+// In the order faces are stored on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; FACE_COUNT] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CubeFace::PositiveX => "+X",
+            CubeFace::NegativeX => "-X",
+            CubeFace::PositiveY => "+Y",
+            CubeFace::NegativeY => "-Y",
+            CubeFace::PositiveZ => "+Z",
+            CubeFace::NegativeZ => "-Z",
+        }
+    }
+}
+
+// How the six faces are arranged when a cube map is flattened into one image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CubeLayout {
+    // Horizontal cross, 4 faces wide and 3 high:
+    //       +Y
+    //   -X  +Z  +X  -Z
+    //       -Y
+    #[default]
+    Cross,
+    // All six faces side by side in storage order
+    Strip,
+}
+
+impl CubeLayout {
+    // Size of the flattened image in faces
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            CubeLayout::Cross => (4, 3),
+            CubeLayout::Strip => (FACE_COUNT as u32, 1),
+        }
+    }
+
+    // Position of a face in the flattened image, in faces
+    pub fn face_position(&self, face: CubeFace) -> (u32, u32) {
+        match self {
+            CubeLayout::Cross => match face {
+                CubeFace::PositiveX => (2, 1),
+                CubeFace::NegativeX => (0, 1),
+                CubeFace::PositiveY => (1, 0),
+                CubeFace::NegativeY => (1, 2),
+                CubeFace::PositiveZ => (1, 1),
+                CubeFace::NegativeZ => (3, 1),
+            },
+            CubeLayout::Strip => (face.index() as u32, 0),
+        }
+    }
+}
+
+// Six square faces stored one after another, each with its own full mip chain. P8 faces share a
+// single palette.
+#[derive(Debug)]
+pub struct CubeMap {
+    faces: Vec<MipChain>,
+}
+
+impl CubeMap {
+    pub fn from_faces(faces: Vec<MipChain>) -> Result<CubeMap, Error> {
+        let first = match faces.first() {
+            Some(first) if faces.len() == FACE_COUNT => first,
+            _ => {
+                return Err(Error::InvalidArgument(format!(
+                    "Cube maps need 6 faces, got {}.",
+                    faces.len()
+                )));
+            }
+        };
+
+        if first.width() != first.height() {
+            return Err(Error::invalid_dimensions(
+                first.width(),
+                first.height(),
+                "cube map faces must be square",
+            ));
+        }
+
+        if faces.iter().any(|face| {
+            face.format() != first.format()
+                || face.width() != first.width()
+                || face.height() != first.height()
+                || face.level_count() != first.level_count()
+                || face.palette() != first.palette()
+        }) {
+            return Err(Error::InvalidArgument(
+                "Every cube face must have the same format, size, level count and palette."
+                    .to_string(),
+            ));
+        }
+
+        Ok(CubeMap { faces })
+    }
+
+    pub fn from_bytes(
+        bytes: &[u8],
+        size: u32,
+        format: ImageFormat,
+        level_count: u32,
+    ) -> Result<CubeMap, Error> {
+        // Checked before sizing the faces, which walks every level
+        if size == 0 {
+            return Err(Error::invalid_dimensions(
+                size,
+                size,
+                "images need at least one texel",
+            ));
+        }
+
+        if level_count == 0 || level_count > MipChain::full_level_count(size, size) {
+            return Err(Error::InvalidLevelCount {
+                level_count,
+                max: MipChain::full_level_count(size, size),
+            });
+        }
+
+        let face_size = CubeMap::face_size(size, format, level_count);
+
+        if bytes.len() < face_size.saturating_mul(FACE_COUNT) {
+            return Err(Error::insufficient_data(
+                face_size.saturating_mul(FACE_COUNT),
+                bytes.len(),
+            ));
+        }
+
+        let faces = bytes
+            .chunks_exact(face_size.max(1))
+            .take(FACE_COUNT)
+            .map(|face| MipChain::from_bytes(face, size, size, format, level_count))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        CubeMap::from_faces(faces)
+    }
+
+    // `rgba` holds every face flattened with the given layout. Smaller levels are generated per face.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        layout: CubeLayout,
+        format: ImageFormat,
+        level_count: u32,
+        filter: MipFilter,
+        quality: EncodeQuality,
+    ) -> Result<CubeMap, Error> {
+        encoder::validate_rgba(rgba, width, height)?;
+
+        let (faces_x, faces_y) = layout.dimensions();
+        let size = width / faces_x;
+
+        if size == 0 || width != size * faces_x || height != size * faces_y {
+            return Err(Error::invalid_dimensions(
+                width,
+                height,
+                "the image cannot be split into square cube faces",
+            ));
+        }
+
+        let face_images: Vec<Vec<u8>> = CubeFace::ALL
+            .iter()
+            .map(|&face| {
+                let (x, y) = layout.face_position(face);
+                crop(rgba, width, x * size, y * size, size, size)
+            })
+            .collect();
+
+        // A palette chosen from all six faces at once, so that they can share it
+        let palette = match format {
+            ImageFormat::P8(_) => Some(
+                P8Image::from_rgba(
+                    &face_images.concat(),
+                    size,
+                    size * FACE_COUNT as u32,
+                    MAX_PALETTE_SIZE,
+                )?
+                .palette_to_bytes(),
+            ),
+            _ => None,
+        };
+
+        let faces = face_images
+            .iter()
+            .map(|face| match &palette {
+                Some(palette) => MipChain::from_rgba_with_palette(
+                    face,
+                    size,
+                    size,
+                    format,
+                    level_count,
+                    filter,
+                    palette.clone(),
+                ),
+                None => MipChain::from_rgba(face, size, size, format, level_count, filter, quality),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        CubeMap::from_faces(faces)
+    }
+
+    // Bytes taken by one face's mip chain
+    pub fn face_size(size: u32, format: ImageFormat, level_count: u32) -> usize {
+        (0..level_count)
+            .map(|level| {
+                let (width, height) = MipChain::level_dimensions(size, size, level);
+                format.surface_size(width, height)
+            })
+            .fold(0, usize::saturating_add)
+    }
+
+    pub fn face(&self, face: CubeFace) -> &MipChain {
+        &self.faces[face.index()]
+    }
+
+    pub fn faces(&self) -> &[MipChain] {
+        &self.faces
+    }
+
+    pub fn decode_face(&self, face: CubeFace, level: u32) -> Result<Image, Error> {
+        self.face(face).decode_level(level)
+    }
+
+    // Every face of one level decoded and flattened with the given layout. Unused areas of a cross
+    // are left transparent.
+    pub fn to_rgba(&self, level: u32, layout: CubeLayout) -> Result<(Vec<u8>, u32, u32), Error> {
+        let (size, _) = MipChain::level_dimensions(self.size(), self.size(), level);
+        let (faces_x, faces_y) = layout.dimensions();
+
+        let (width, height) = (size * faces_x, size * faces_y);
+        let mut rgba = vec![0u8; width as usize * height as usize * 4];
+
+        for face in CubeFace::ALL {
+            let image = self.decode_face(face, level)?;
+            let (x, y) = layout.face_position(face);
+
+            paste(
+                &mut rgba,
+                width,
+                &image.decode_rgba(),
+                x * size,
+                y * size,
+                size,
+                size,
+            );
+        }
+
+        Ok((rgba, width, height))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.faces.iter().flat_map(|face| face.to_bytes()).collect()
+    }
+
+    // Width and height of every face
+    pub fn size(&self) -> u32 {
+        self.faces[0].width()
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.faces[0].format()
+    }
+
+    pub fn level_count(&self) -> u32 {
+        self.faces[0].level_count()
+    }
+
+    pub fn palette(&self) -> Option<&[u8]> {
+        self.faces[0].palette()
+    }
+
+    pub fn set_palette(&mut self, palette: Vec<u8>) {
+        for face in self.faces.iter_mut() {
+            face.set_palette(palette.clone());
+        }
+    }
+}
+
+// Copies a width x height rectangle out of an RGBA8 image
+pub(crate) fn crop(
+    rgba: &[u8],
+    image_width: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Vec<u8> {
+    let row_bytes = width as usize * 4;
+
+    (y..y + height)
+        .flat_map(|row| {
+            let offset = (row as usize * image_width as usize + x as usize) * 4;
+            &rgba[offset..offset + row_bytes]
+        })
+        .copied()
+        .collect()
+}
+
+// Copies a width x height RGBA8 image into a larger one at (x, y)
+pub(crate) fn paste(
+    rgba: &mut [u8],
+    image_width: u32,
+    src: &[u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) {
+    let row_bytes = width as usize * 4;
+
+    for (row, src_row) in src
+        .chunks_exact(row_bytes)
+        .take(height as usize)
+        .enumerate()
+    {
+        let offset = ((y as usize + row) * image_width as usize + x as usize) * 4;
+        rgba[offset..offset + row_bytes].copy_from_slice(src_row);
+    }
+}
@@ -1,4 +1,11 @@
-use crate::format::image::types::{R5G6B5Colour, RGBColour};
+use crate::Error;
+use crate::format::image::{
+    ImageFormat,
+    block::{self, DecodeBlock, Texel},
+    codec::{self, ImageCodec},
+    encoder::{self, ColourBlockKind, EncodeQuality},
+    types::{R5G6B5Colour, RGBAColour, RGBColour},
+};
 
 #[derive(Debug)]
 pub struct DXT1 {
@@ -12,190 +19,226 @@ pub struct DXT1Block {
     colour_1: R5G6B5Colour,
     colour_2: R5G6B5Colour,
 
-    // On disk          // Desired
-    // 03 13 23 33      00 10 20 30
-    // 02 12 22 32      01 11 21 31
-    // 01 11 21 31      02 12 22 32
-    // 00 10 20 30      03 13 23 33
+    // 2 bits per texel, row by row, lowest bits first
     indices: u32,
 }
 
 impl DXT1Block {
-    pub fn from_bytes(bytes: &[u8]) -> Result<DXT1Block, std::io::Error> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<DXT1Block, Error> {
         if bytes.len() < size_of::<DXT1Block>() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Not enough input bytes to form a block.",
+            return Err(Error::insufficient_data(
+                size_of::<DXT1Block>(),
+                bytes.len(),
             ));
         }
 
         Ok(DXT1Block {
-            colour_1: u16::from_le_bytes(bytes[0..2].try_into().unwrap()).into(),
-            colour_2: u16::from_le_bytes(bytes[2..4].try_into().unwrap()).into(),
+            colour_1: R5G6B5Colour::from_le(bytes[0..2].try_into().unwrap()),
+            colour_2: R5G6B5Colour::from_le(bytes[2..4].try_into().unwrap()),
             indices: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
         })
     }
 
-    // Row-order block in the human order
-    pub fn row_1(&self) -> [RGBColour; 4] {
-        let clo = |col| -> RGBColour {
-            let i: u32 = (self.indices << (2 * (col + 1))) & 0b11u32;
+    // Compresses 16 texels given in row order. Texels with alpha below the punch-through threshold
+    // are encoded as transparent.
+    pub fn from_rgba(texels: &[[u8; 4]; 16], quality: EncodeQuality) -> DXT1Block {
+        let (colour_1, colour_2, indices) =
+            encoder::encode_colour_block(texels, quality, ColourBlockKind::DXT1);
+
+        DXT1Block {
+            colour_1: colour_1.into(),
+            colour_2: colour_2.into(),
+            indices,
+        }
+    }
 
-            let col_1: RGBColour = self.colour_1.into();
-            let col_2: RGBColour = self.colour_2.into();
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let mut bytes = [0u8; 8];
 
-            RGBColour {
-                r: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                g: ((i * col_1.g as u32 + (3 - i) * col_2.g as u32) / 3) as u8,
-                b: ((i * col_1.b as u32 + (3 - i) * col_2.b as u32) / 3) as u8,
-            }
-        };
+        bytes[0..2].copy_from_slice(&self.colour_1.to_le_bytes());
+        bytes[2..4].copy_from_slice(&self.colour_2.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.indices.to_le_bytes());
 
-        [clo(0), clo(1), clo(2), clo(3)]
+        bytes
     }
 
-    pub fn row_2(&self) -> [RGBColour; 4] {
-        let clo = |col| -> RGBColour {
-            let i: u32 = (self.indices >> 8 << (2 * (col + 1))) & 0b11u32;
+    // When the endpoints are stored in ascending order the block is in three colour mode, with the
+    // last palette entry being transparent black
+    pub fn has_alpha(&self) -> bool {
+        self.colour_1.raw() <= self.colour_2.raw()
+    }
 
-            let col_1: RGBColour = self.colour_1.into();
-            let col_2: RGBColour = self.colour_2.into();
+    pub fn palette(&self) -> [RGBAColour; 4] {
+        block::colour_palette(self.colour_1, self.colour_2, self.has_alpha())
+            .map(|[r, g, b, a]| RGBAColour { r, g, b, a })
+    }
 
-            RGBColour {
-                r: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                g: ((i * col_1.g as u32 + (3 - i) * col_2.g as u32) / 3) as u8,
-                b: ((i * col_1.b as u32 + (3 - i) * col_2.b as u32) / 3) as u8,
-            }
-        };
+    fn row_rgba(&self, row: usize) -> [RGBAColour; 4] {
+        let texels = self.texels();
 
-        [clo(0), clo(1), clo(2), clo(3)]
+        std::array::from_fn(|col| {
+            let [r, g, b, a] = texels[row * 4 + col];
+            RGBAColour { r, g, b, a }
+        })
     }
 
-    pub fn row_3(&self) -> [RGBColour; 4] {
-        let clo = |col| -> RGBColour {
-            let i: u32 = (self.indices >> 16 << (2 * (col + 1))) & 0b11u32;
+    fn row_rgb(&self, row: usize) -> [RGBColour; 4] {
+        self.row_rgba(row).map(|c| RGBColour {
+            r: c.r,
+            g: c.g,
+            b: c.b,
+        })
+    }
 
-            let col_1: RGBColour = self.colour_1.into();
-            let col_2: RGBColour = self.colour_2.into();
+    // Row-order block in the human order
+    pub fn row_1(&self) -> [RGBColour; 4] {
+        self.row_rgb(0)
+    }
 
-            RGBColour {
-                r: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                g: ((i * col_1.g as u32 + (3 - i) * col_2.g as u32) / 3) as u8,
-                b: ((i * col_1.b as u32 + (3 - i) * col_2.b as u32) / 3) as u8,
-            }
-        };
+    pub fn row_2(&self) -> [RGBColour; 4] {
+        self.row_rgb(1)
+    }
 
-        [clo(0), clo(1), clo(2), clo(3)]
+    pub fn row_3(&self) -> [RGBColour; 4] {
+        self.row_rgb(2)
     }
 
     pub fn row_4(&self) -> [RGBColour; 4] {
-        let clo = |col| -> RGBColour {
-            let i: u32 = (self.indices >> 24 << (2 * (col + 1))) & 0b11u32;
+        self.row_rgb(3)
+    }
 
-            let col_1: RGBColour = self.colour_1.into();
-            let col_2: RGBColour = self.colour_2.into();
+    pub fn rows(&self) -> [[RGBColour; 4]; 4] {
+        [self.row_1(), self.row_2(), self.row_3(), self.row_4()]
+    }
 
-            RGBColour {
-                r: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                g: ((i * col_1.g as u32 + (3 - i) * col_2.g as u32) / 3) as u8,
-                b: ((i * col_1.b as u32 + (3 - i) * col_2.b as u32) / 3) as u8,
-            }
-        };
+    pub fn row_1_rgba(&self) -> [RGBAColour; 4] {
+        self.row_rgba(0)
+    }
 
-        [clo(0), clo(1), clo(2), clo(3)]
+    pub fn row_2_rgba(&self) -> [RGBAColour; 4] {
+        self.row_rgba(1)
     }
 
-    pub fn rows(&self) -> [[RGBColour; 4]; 4] {
-        [self.row_1(), self.row_2(), self.row_3(), self.row_4()]
+    pub fn row_3_rgba(&self) -> [RGBAColour; 4] {
+        self.row_rgba(2)
+    }
+
+    pub fn row_4_rgba(&self) -> [RGBAColour; 4] {
+        self.row_rgba(3)
+    }
+
+    pub fn rows_rgba(&self) -> [[RGBAColour; 4]; 4] {
+        [
+            self.row_1_rgba(),
+            self.row_2_rgba(),
+            self.row_3_rgba(),
+            self.row_4_rgba(),
+        ]
+    }
+}
+
+impl DecodeBlock for DXT1Block {
+    fn texels(&self) -> [Texel; 16] {
+        let palette = block::colour_palette(self.colour_1, self.colour_2, self.has_alpha());
+        block::colour_texels(&palette, self.indices)
     }
 }
 
 impl DXT1 {
     pub fn as_rgb_bytes(&self) -> Vec<u8> {
-        self.as_rgb().iter().flat_map(|c| [c.r, c.g, c.b]).collect()
+        self.as_rgba_bytes()
+            .chunks_exact(4)
+            .flat_map(|c| [c[0], c[1], c[2]])
+            .collect()
     }
 
     pub fn as_rgba_bytes(&self) -> Vec<u8> {
-        self.as_rgb()
-            .iter()
-            .flat_map(|c| [c.r, c.g, c.b, u8::MAX])
-            .collect()
+        let mut rgba = vec![0u8; self.width as usize * self.height as usize * 4];
+        block::decode_blocks(&self.blocks, self.width, self.height, &mut rgba);
+        rgba
     }
 
     pub fn as_rgb(&self) -> Vec<RGBColour> {
-        let blocks_x = self.height.div_ceil(4) as usize;
-        let blocks_y = self.width.div_ceil(4) as usize;
-
-        let mut rgb_colours = Vec::<RGBColour>::new();
-
-        let width_stride = (self.height / 4) as usize;
-
-        let mut vec1 = Vec::<RGBColour>::new();
-        let mut vec2 = Vec::<RGBColour>::new();
-        let mut vec3 = Vec::<RGBColour>::new();
-        let mut vec4 = Vec::<RGBColour>::new();
-
-        for outer_row in 0..blocks_y {
-            vec1.clear();
-            vec2.clear();
-            vec3.clear();
-            vec4.clear();
-
-            for i in 0..blocks_x {
-                let block = &self.blocks[outer_row * width_stride + i];
+        self.as_rgba_bytes()
+            .chunks_exact(4)
+            .map(|c| RGBColour {
+                r: c[0],
+                g: c[1],
+                b: c[2],
+            })
+            .collect()
+    }
 
-                vec1.extend_from_slice(&block.row_1());
-                vec2.extend_from_slice(&block.row_2());
-                vec3.extend_from_slice(&block.row_3());
-                vec4.extend_from_slice(&block.row_4());
-            }
+    pub fn as_rgba(&self) -> Vec<RGBAColour> {
+        self.as_rgba_bytes()
+            .chunks_exact(4)
+            .map(|c| RGBAColour {
+                r: c[0],
+                g: c[1],
+                b: c[2],
+                a: c[3],
+            })
+            .collect()
+    }
 
-            rgb_colours.extend_from_slice(&vec1);
-            rgb_colours.extend_from_slice(&vec2);
-            rgb_colours.extend_from_slice(&vec3);
-            rgb_colours.extend_from_slice(&vec4);
-        }
-        rgb_colours
+    // Decodes into an existing RGBA8 buffer of at least width * height texels
+    pub fn decode_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        block::check_output(out, self.width, self.height)?;
+        block::decode_blocks(&self.blocks, self.width, self.height, out);
+        Ok(())
     }
 
-    pub fn from_bytes(bytes: &[u8], width: u32, height: u32) -> Result<DXT1, std::io::Error> {
-        if width == 0 && height == 0 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Both width and height are 0.",
+    pub fn from_bytes(bytes: &[u8], width: u32, height: u32) -> Result<DXT1, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::invalid_dimensions(
+                width,
+                height,
+                "images need at least one texel",
             ));
         }
 
-        let pixel_count = width * height;
-
-        let bytes_required = pixel_count / 2; // DXT1 is 16 pixels per 8 bytes, so /16 x8 gives
-        // number of bytes required
-
-        if bytes.len() < bytes_required as usize {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!(
-                    "The byte slice supplied is not large enough ({} x {} = {} pixels, need {}, only have {})",
-                    width,
-                    height,
-                    pixel_count,
-                    bytes_required,
-                    bytes.len()
-                ),
-            ));
-        }
+        // Rejects sizes whose decoded output couldn't be addressed
+        codec::rgba_size(width, height)?;
 
-        let block_count = bytes_required / 8;
+        // DXT1 is 16 pixels per 8 bytes, with partial blocks padded out to a full block
+        let bytes_required = ImageFormat::DXT1.surface_size(width, height);
+        let block_count = bytes_required / size_of::<DXT1Block>();
 
-        let mut blocks = Vec::new();
+        if bytes.len() < bytes_required {
+            return Err(Error::insufficient_data(bytes_required, bytes.len()));
+        }
 
-        for i in 0..block_count as usize {
+        let mut blocks = Vec::with_capacity(block_count);
+
+        for i in 0..block_count {
             blocks.push(DXT1Block::from_bytes(
                 &bytes[(i * size_of::<DXT1Block>())..],
             )?);
         }
 
-        // let blocks = ;
+        Ok(DXT1 {
+            width,
+            height,
+            blocks,
+        })
+    }
+
+    pub fn from_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        quality: EncodeQuality,
+    ) -> Result<DXT1, Error> {
+        encoder::validate_rgba(rgba, width, height)?;
+
+        let mut blocks = Vec::new();
+
+        for block_y in 0..height.div_ceil(4) {
+            for block_x in 0..width.div_ceil(4) {
+                let texels = encoder::gather_block(rgba, width, height, block_x, block_y);
+                blocks.push(DXT1Block::from_rgba(&texels, quality));
+            }
+        }
 
         Ok(DXT1 {
             width,
@@ -204,6 +247,10 @@ impl DXT1 {
         })
     }
 
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.blocks.iter().flat_map(|b| b.to_bytes()).collect()
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -212,3 +259,39 @@ impl DXT1 {
         self.height
     }
 }
+
+impl ImageCodec for DXT1 {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::DXT1
+    }
+
+    fn width(&self) -> u32 {
+        DXT1::width(self)
+    }
+
+    fn height(&self) -> u32 {
+        DXT1::height(self)
+    }
+
+    fn decode_rgba_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        self.decode_into(out)
+    }
+
+    fn encode_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        quality: EncodeQuality,
+    ) -> Result<DXT1, Error> {
+        if format != ImageFormat::DXT1 {
+            return Err(codec::format_mismatch("DXT1", format));
+        }
+
+        DXT1::from_rgba(rgba, width, height, quality)
+    }
+
+    fn to_surface_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.to_bytes())
+    }
+}
@@ -1,217 +1,121 @@
-use crate::format::image::types::{R5G6B5Colour, RGBAColour};
+use crate::Error;
+use crate::format::image::{
+    ImageFormat,
+    alpha::{AlphaMode, unpremultiply},
+    codec::{self, ImageCodec},
+    dxt3::{DXT3, DXT3Block},
+    encoder::{self, EncodeQuality},
+};
+
+// DXT2 shares its block layout with DXT3, the only difference being that the colour data has been
+// premultiplied by alpha
+pub type DXT2Block = DXT3Block;
 
 #[derive(Debug)]
 pub struct DXT2 {
-    width: u32,
-    height: u32,
-    blocks: Vec<DXT2Block>,
+    inner: DXT3,
 }
 
-#[derive(Debug)]
-pub struct DXT2Block {
-    alpha_data: [u8; 8],
-
-    colour_1: R5G6B5Colour,
-    colour_2: R5G6B5Colour,
-
-    // On disk          // Desired
-    // 03 13 23 33      00 10 20 30
-    // 02 12 22 32      01 11 21 31
-    // 01 11 21 31      02 12 22 32
-    // 00 10 20 30      03 13 23 33
-    indices: u32,
-}
-
-impl DXT2Block {
-    pub fn from_bytes(bytes: &[u8]) -> Result<DXT2Block, std::io::Error> {
-        if bytes.len() < size_of::<DXT2Block>() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Not enough input bytes to form a block.",
-            ));
-        }
-
-        Ok(DXT2Block {
-            // TODO: Remove this expect
-            alpha_data: bytes[0..8].try_into().expect("Unable to convert"),
-            colour_1: u16::from_le_bytes(bytes[8..10].try_into().unwrap()).into(),
-            colour_2: u16::from_le_bytes(bytes[10..12].try_into().unwrap()).into(),
-            indices: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
-        })
+impl DXT2 {
+    pub fn as_rgba_bytes(&self) -> Vec<u8> {
+        self.as_rgba_bytes_with(AlphaMode::default())
     }
 
-    // Row-order block in the human order
-    pub fn row_1_rgba(&self) -> [RGBAColour; 4] {
-        let clo = |col| -> RGBAColour {
-            let i: u32 = (self.indices << (2 * (col + 1))) & 0b11u32;
+    pub fn as_rgba_bytes_with(&self, alpha_mode: AlphaMode) -> Vec<u8> {
+        let mut bytes = self.inner.as_rgba_bytes();
 
-            let col_1: RGBAColour = self.colour_1.into();
-            let col_2: RGBAColour = self.colour_2.into();
-
-            RGBAColour {
-                r: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                g: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                b: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                a: u8::MAX,
-            }
-        };
+        if alpha_mode == AlphaMode::Straight {
+            unpremultiply(&mut bytes);
+        }
 
-        [clo(0), clo(1), clo(2), clo(3)]
+        bytes
     }
 
-    pub fn row_2_rgba(&self) -> [RGBAColour; 4] {
-        let clo = |col| -> RGBAColour {
-            let i: u32 = (self.indices >> 8 << (2 * (col + 1))) & 0b11u32;
+    pub fn decode_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        self.decode_into_with(out, AlphaMode::default())
+    }
 
-            let col_1: RGBAColour = self.colour_1.into();
-            let col_2: RGBAColour = self.colour_2.into();
+    pub fn decode_into_with(&self, out: &mut [u8], alpha_mode: AlphaMode) -> Result<(), Error> {
+        self.inner.decode_into(out)?;
 
-            RGBAColour {
-                r: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                g: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                b: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                a: u8::MAX,
-            }
-        };
+        if alpha_mode == AlphaMode::Straight {
+            let len = self.width() as usize * self.height() as usize * 4;
+            unpremultiply(&mut out[..len]);
+        }
 
-        [clo(0), clo(1), clo(2), clo(3)]
+        Ok(())
     }
 
-    pub fn row_3_rgba(&self) -> [RGBAColour; 4] {
-        let clo = |col| -> RGBAColour {
-            let i: u32 = (self.indices >> 16 << (2 * (col + 1))) & 0b11u32;
+    pub fn from_bytes(bytes: &[u8], width: u32, height: u32) -> Result<DXT2, Error> {
+        Ok(DXT2 {
+            inner: DXT3::from_bytes(bytes, width, height)?,
+        })
+    }
 
-            let col_1: RGBAColour = self.colour_1.into();
-            let col_2: RGBAColour = self.colour_2.into();
+    // Takes straight alpha RGBA and premultiplies it before compression
+    pub fn from_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        quality: EncodeQuality,
+    ) -> Result<DXT2, Error> {
+        encoder::validate_rgba(rgba, width, height)?;
 
-            RGBAColour {
-                r: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                g: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                b: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                a: u8::MAX,
-            }
-        };
+        let premultiplied: Vec<u8> = rgba[..width as usize * height as usize * 4]
+            .chunks_exact(4)
+            .flat_map(|texel| encoder::premultiply(texel.try_into().unwrap()))
+            .collect();
 
-        [clo(0), clo(1), clo(2), clo(3)]
+        Ok(DXT2 {
+            inner: DXT3::from_rgba(&premultiplied, width, height, quality)?,
+        })
     }
 
-    pub fn row_4_rgba(&self) -> [RGBAColour; 4] {
-        let clo = |col| -> RGBAColour {
-            let i: u32 = (self.indices >> 24 << (2 * (col + 1))) & 0b11u32;
-
-            let col_1: RGBAColour = self.colour_1.into();
-            let col_2: RGBAColour = self.colour_2.into();
-
-            RGBAColour {
-                r: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                g: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                b: ((i * col_1.r as u32 + (3 - i) * col_2.r as u32) / 3) as u8,
-                a: u8::MAX,
-            }
-        };
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
 
-        [clo(0), clo(1), clo(2), clo(3)]
+    pub fn width(&self) -> u32 {
+        self.inner.width()
     }
 
-    pub fn rows(&self) -> [[RGBAColour; 4]; 4] {
-        [
-            self.row_1_rgba(),
-            self.row_2_rgba(),
-            self.row_3_rgba(),
-            self.row_4_rgba(),
-        ]
+    pub fn height(&self) -> u32 {
+        self.inner.height()
     }
 }
 
-impl DXT2 {
-    pub fn as_rgba_bytes(&self) -> Vec<u8> {
-        let mut rgba_colours = Vec::<RGBAColour>::new();
-
-        let width_stride = (self.height / 4) as usize;
-
-        let mut vec1 = Vec::<RGBAColour>::new();
-        let mut vec2 = Vec::<RGBAColour>::new();
-        let mut vec3 = Vec::<RGBAColour>::new();
-        let mut vec4 = Vec::<RGBAColour>::new();
-
-        for outer_row in 0..(self.width / 4) as usize {
-            vec1.clear();
-            vec2.clear();
-            vec3.clear();
-            vec4.clear();
-
-            for i in 0..width_stride {
-                let block = &self.blocks[outer_row * width_stride + i];
-
-                vec1.extend_from_slice(&block.row_1_rgba());
-                vec2.extend_from_slice(&block.row_2_rgba());
-                vec3.extend_from_slice(&block.row_3_rgba());
-                vec4.extend_from_slice(&block.row_4_rgba());
-            }
-
-            rgba_colours.extend_from_slice(&vec1);
-            rgba_colours.extend_from_slice(&vec2);
-            rgba_colours.extend_from_slice(&vec3);
-            rgba_colours.extend_from_slice(&vec4);
-        }
-
-        rgba_colours
-            .iter()
-            .flat_map(|c| [c.r, c.g, c.b, c.a])
-            .collect()
+impl ImageCodec for DXT2 {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::DXT2
     }
 
-    pub fn from_bytes(bytes: &[u8], width: u32, height: u32) -> Result<DXT2, std::io::Error> {
-        if width == 0 && height == 0 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Both width and height are 0.",
-            ));
-        }
-
-        let pixel_count = width * height;
-
-        let bytes_required = pixel_count / 2; // DXT2 is 16 pixels per 8 bytes, so /16 x8 gives
-        // number of bytes required
-
-        if bytes.len() < bytes_required as usize {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!(
-                    "The byte slice supplied is not large enough ({} x {} = {} pixels, need {}, only have {})",
-                    width,
-                    height,
-                    pixel_count,
-                    bytes_required,
-                    bytes.len()
-                ),
-            ));
-        }
+    fn width(&self) -> u32 {
+        DXT2::width(self)
+    }
 
-        let block_count = bytes_required / 8;
+    fn height(&self) -> u32 {
+        DXT2::height(self)
+    }
 
-        let mut blocks = Vec::new();
+    fn decode_rgba_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        self.decode_into(out)
+    }
 
-        for i in 0..block_count as usize {
-            blocks.push(DXT2Block::from_bytes(
-                &bytes[(i * size_of::<DXT2Block>())..],
-            )?);
+    fn encode_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        quality: EncodeQuality,
+    ) -> Result<DXT2, Error> {
+        if format != ImageFormat::DXT2 {
+            return Err(codec::format_mismatch("DXT2", format));
         }
 
-        // let blocks = ;
-
-        Ok(DXT2 {
-            width,
-            height,
-            blocks,
-        })
+        DXT2::from_rgba(rgba, width, height, quality)
     }
 
-    pub fn width(&self) -> u32 {
-        self.width
-    }
-
-    pub fn height(&self) -> u32 {
-        self.height
+    fn to_surface_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.to_bytes())
     }
 }
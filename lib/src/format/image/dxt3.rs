@@ -0,0 +1,249 @@
+use crate::Error;
+use crate::format::image::{
+    ImageFormat,
+    alpha::{ALPHA_BLOCK_SIZE, ExplicitAlphaBlock},
+    block::{self, DecodeBlock, Texel},
+    codec::{self, ImageCodec},
+    encoder::{self, ColourBlockKind, EncodeQuality},
+    types::{R5G6B5Colour, RGBAColour, RGBColour},
+};
+
+const BLOCK_SIZE: usize = 16;
+
+#[derive(Debug)]
+pub struct DXT3 {
+    width: u32,
+    height: u32,
+    blocks: Vec<DXT3Block>,
+}
+
+#[derive(Debug)]
+pub struct DXT3Block {
+    alpha: ExplicitAlphaBlock,
+
+    colour_1: R5G6B5Colour,
+    colour_2: R5G6B5Colour,
+
+    // 2 bits per texel, row by row, lowest bits first
+    indices: u32,
+}
+
+impl DXT3Block {
+    pub fn from_bytes(bytes: &[u8]) -> Result<DXT3Block, Error> {
+        if bytes.len() < BLOCK_SIZE {
+            return Err(Error::insufficient_data(BLOCK_SIZE, bytes.len()));
+        }
+
+        Ok(DXT3Block {
+            alpha: ExplicitAlphaBlock::from_bytes(&bytes[0..ALPHA_BLOCK_SIZE])?,
+            colour_1: R5G6B5Colour::from_le(bytes[8..10].try_into().unwrap()),
+            colour_2: R5G6B5Colour::from_le(bytes[10..12].try_into().unwrap()),
+            indices: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+        })
+    }
+
+    // Compresses 16 texels given in row order
+    pub fn from_rgba(texels: &[[u8; 4]; 16], quality: EncodeQuality) -> DXT3Block {
+        let (colour_1, colour_2, indices) =
+            encoder::encode_colour_block(texels, quality, ColourBlockKind::FourColour);
+
+        DXT3Block {
+            alpha: ExplicitAlphaBlock::from_rgba(texels),
+            colour_1: colour_1.into(),
+            colour_2: colour_2.into(),
+            indices,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; BLOCK_SIZE] {
+        let mut bytes = [0u8; BLOCK_SIZE];
+
+        bytes[0..8].copy_from_slice(&self.alpha.to_bytes());
+        bytes[8..10].copy_from_slice(&self.colour_1.to_le_bytes());
+        bytes[10..12].copy_from_slice(&self.colour_2.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.indices.to_le_bytes());
+
+        bytes
+    }
+
+    // DXT3 colour blocks are always in four colour mode, regardless of endpoint order
+    pub fn palette(&self) -> [RGBColour; 4] {
+        block::colour_palette(self.colour_1, self.colour_2, false).map(|[r, g, b, _]| RGBColour {
+            r,
+            g,
+            b,
+        })
+    }
+
+    pub fn alpha(&self) -> &ExplicitAlphaBlock {
+        &self.alpha
+    }
+
+    fn row_rgba(&self, row: usize) -> [RGBAColour; 4] {
+        let texels = self.texels();
+
+        std::array::from_fn(|col| {
+            let [r, g, b, a] = texels[row * 4 + col];
+            RGBAColour { r, g, b, a }
+        })
+    }
+
+    // Row-order block in the human order
+    pub fn row_1_rgba(&self) -> [RGBAColour; 4] {
+        self.row_rgba(0)
+    }
+
+    pub fn row_2_rgba(&self) -> [RGBAColour; 4] {
+        self.row_rgba(1)
+    }
+
+    pub fn row_3_rgba(&self) -> [RGBAColour; 4] {
+        self.row_rgba(2)
+    }
+
+    pub fn row_4_rgba(&self) -> [RGBAColour; 4] {
+        self.row_rgba(3)
+    }
+
+    pub fn rows(&self) -> [[RGBAColour; 4]; 4] {
+        [
+            self.row_1_rgba(),
+            self.row_2_rgba(),
+            self.row_3_rgba(),
+            self.row_4_rgba(),
+        ]
+    }
+}
+
+impl DecodeBlock for DXT3Block {
+    fn texels(&self) -> [Texel; 16] {
+        let palette = block::colour_palette(self.colour_1, self.colour_2, false);
+        let mut texels = block::colour_texels(&palette, self.indices);
+
+        for (texel, alpha) in texels.iter_mut().zip(self.alpha.values()) {
+            texel[3] = alpha;
+        }
+
+        texels
+    }
+}
+
+impl DXT3 {
+    pub fn as_rgba_bytes(&self) -> Vec<u8> {
+        let mut rgba = vec![0u8; self.width as usize * self.height as usize * 4];
+        block::decode_blocks(&self.blocks, self.width, self.height, &mut rgba);
+        rgba
+    }
+
+    // Decodes into an existing RGBA8 buffer of at least width * height texels
+    pub fn decode_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        block::check_output(out, self.width, self.height)?;
+        block::decode_blocks(&self.blocks, self.width, self.height, out);
+        Ok(())
+    }
+
+    pub fn from_bytes(bytes: &[u8], width: u32, height: u32) -> Result<DXT3, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::invalid_dimensions(
+                width,
+                height,
+                "images need at least one texel",
+            ));
+        }
+
+        // Rejects sizes whose decoded output couldn't be addressed
+        codec::rgba_size(width, height)?;
+
+        // DXT3 is 16 pixels per 16 bytes, with partial blocks padded out to a full block
+        let bytes_required = ImageFormat::DXT3.surface_size(width, height);
+        let block_count = bytes_required / BLOCK_SIZE;
+
+        if bytes.len() < bytes_required {
+            return Err(Error::insufficient_data(bytes_required, bytes.len()));
+        }
+
+        let mut blocks = Vec::with_capacity(block_count);
+
+        for i in 0..block_count {
+            blocks.push(DXT3Block::from_bytes(&bytes[(i * BLOCK_SIZE)..])?);
+        }
+
+        Ok(DXT3 {
+            width,
+            height,
+            blocks,
+        })
+    }
+
+    pub fn from_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        quality: EncodeQuality,
+    ) -> Result<DXT3, Error> {
+        encoder::validate_rgba(rgba, width, height)?;
+
+        let mut blocks = Vec::new();
+
+        for block_y in 0..height.div_ceil(4) {
+            for block_x in 0..width.div_ceil(4) {
+                let texels = encoder::gather_block(rgba, width, height, block_x, block_y);
+                blocks.push(DXT3Block::from_rgba(&texels, quality));
+            }
+        }
+
+        Ok(DXT3 {
+            width,
+            height,
+            blocks,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.blocks.iter().flat_map(|b| b.to_bytes()).collect()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl ImageCodec for DXT3 {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::DXT3
+    }
+
+    fn width(&self) -> u32 {
+        DXT3::width(self)
+    }
+
+    fn height(&self) -> u32 {
+        DXT3::height(self)
+    }
+
+    fn decode_rgba_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        self.decode_into(out)
+    }
+
+    fn encode_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        quality: EncodeQuality,
+    ) -> Result<DXT3, Error> {
+        if format != ImageFormat::DXT3 {
+            return Err(codec::format_mismatch("DXT3", format));
+        }
+
+        DXT3::from_rgba(rgba, width, height, quality)
+    }
+
+    fn to_surface_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.to_bytes())
+    }
+}
@@ -0,0 +1,121 @@
+use crate::Error;
+use crate::format::image::{
+    ImageFormat,
+    alpha::{AlphaMode, unpremultiply},
+    codec::{self, ImageCodec},
+    dxt5::{DXT5, DXT5Block},
+    encoder::{self, EncodeQuality},
+};
+
+// DXT4 shares its block layout with DXT5, the only difference being that the colour data has been
+// premultiplied by alpha
+pub type DXT4Block = DXT5Block;
+
+#[derive(Debug)]
+pub struct DXT4 {
+    inner: DXT5,
+}
+
+impl DXT4 {
+    pub fn as_rgba_bytes(&self) -> Vec<u8> {
+        self.as_rgba_bytes_with(AlphaMode::default())
+    }
+
+    pub fn as_rgba_bytes_with(&self, alpha_mode: AlphaMode) -> Vec<u8> {
+        let mut bytes = self.inner.as_rgba_bytes();
+
+        if alpha_mode == AlphaMode::Straight {
+            unpremultiply(&mut bytes);
+        }
+
+        bytes
+    }
+
+    pub fn decode_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        self.decode_into_with(out, AlphaMode::default())
+    }
+
+    pub fn decode_into_with(&self, out: &mut [u8], alpha_mode: AlphaMode) -> Result<(), Error> {
+        self.inner.decode_into(out)?;
+
+        if alpha_mode == AlphaMode::Straight {
+            let len = self.width() as usize * self.height() as usize * 4;
+            unpremultiply(&mut out[..len]);
+        }
+
+        Ok(())
+    }
+
+    pub fn from_bytes(bytes: &[u8], width: u32, height: u32) -> Result<DXT4, Error> {
+        Ok(DXT4 {
+            inner: DXT5::from_bytes(bytes, width, height)?,
+        })
+    }
+
+    // Takes straight alpha RGBA and premultiplies it before compression
+    pub fn from_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        quality: EncodeQuality,
+    ) -> Result<DXT4, Error> {
+        encoder::validate_rgba(rgba, width, height)?;
+
+        let premultiplied: Vec<u8> = rgba[..width as usize * height as usize * 4]
+            .chunks_exact(4)
+            .flat_map(|texel| encoder::premultiply(texel.try_into().unwrap()))
+            .collect();
+
+        Ok(DXT4 {
+            inner: DXT5::from_rgba(&premultiplied, width, height, quality)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.inner.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.inner.height()
+    }
+}
+
+impl ImageCodec for DXT4 {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::DXT4
+    }
+
+    fn width(&self) -> u32 {
+        DXT4::width(self)
+    }
+
+    fn height(&self) -> u32 {
+        DXT4::height(self)
+    }
+
+    fn decode_rgba_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        self.decode_into(out)
+    }
+
+    fn encode_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        quality: EncodeQuality,
+    ) -> Result<DXT4, Error> {
+        if format != ImageFormat::DXT4 {
+            return Err(codec::format_mismatch("DXT4", format));
+        }
+
+        DXT4::from_rgba(rgba, width, height, quality)
+    }
+
+    fn to_surface_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.to_bytes())
+    }
+}
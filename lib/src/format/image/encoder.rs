@@ -0,0 +1,350 @@
+// Shared block compression used by the DXT encoders. Colours are fitted in floating point and then
+// snapped to the R5G6B5 grid, with the final palette indices always chosen against the quantised
+// endpoints so that the error reflects what the decoder will actually produce.
+
+use crate::Error;
+use crate::format::image::{
+    codec,
+    types::{R5G6B5Colour, RGBColour},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodeQuality {
+    // Endpoints taken from the extent of the colours along their principal axis
+    #[default]
+    RangeFit,
+    // Exhaustive search over every ordered clustering of the colours along the principal axis,
+    // solving for the least squares endpoints of each
+    ClusterFit,
+}
+
+// Alpha below this threshold is treated as transparent by the DXT1 punch-through mode
+pub const PUNCH_THROUGH_THRESHOLD: u8 = 128;
+
+type Texel = [u8; 4];
+type Vec3 = [f32; 3];
+
+pub(crate) fn validate_rgba(rgba: &[u8], width: u32, height: u32) -> Result<(), Error> {
+    if width == 0 || height == 0 {
+        return Err(Error::invalid_dimensions(
+            width,
+            height,
+            "images need at least one texel",
+        ));
+    }
+
+    let bytes_required = codec::rgba_size(width, height)?;
+
+    if rgba.len() < bytes_required {
+        return Err(Error::insufficient_data(bytes_required, rgba.len()));
+    }
+
+    Ok(())
+}
+
+// Collects the 4x4 texels for a block, repeating the edge texels of the image for blocks that hang
+// off the right or bottom edges
+pub(crate) fn gather_block(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    block_x: u32,
+    block_y: u32,
+) -> [Texel; 16] {
+    std::array::from_fn(|i| {
+        let x = (block_x * 4 + (i % 4) as u32).min(width - 1) as usize;
+        let y = (block_y * 4 + (i / 4) as u32).min(height - 1) as usize;
+
+        let offset = (y * width as usize + x) * 4;
+        rgba[offset..offset + 4].try_into().unwrap()
+    })
+}
+
+pub(crate) fn premultiply(texel: Texel) -> Texel {
+    let a = texel[3] as u32;
+    let mul = |c: u8| ((c as u32 * a + 127) / 255) as u8;
+
+    [mul(texel[0]), mul(texel[1]), mul(texel[2]), texel[3]]
+}
+
+// Returns the packed 4 bit alpha values for a DXT2/DXT3 alpha block
+pub(crate) fn encode_explicit_alpha(texels: &[Texel; 16]) -> u64 {
+    texels.iter().enumerate().fold(0u64, |acc, (i, t)| {
+        let nibble = (t[3] as u64 * 15 + 127) / 255;
+        acc | (nibble << (4 * i))
+    })
+}
+
+// DXT1 picks between three and four colour palettes based on the endpoint order, while the colour
+// blocks of DXT2-DXT5 are always decoded with four colours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColourBlockKind {
+    DXT1,
+    FourColour,
+}
+
+// Returns (colour_1, colour_2, indices) for a colour block. For DXT1 blocks, texels below the alpha
+// threshold are mapped to the transparent entry of the three colour mode.
+pub(crate) fn encode_colour_block(
+    texels: &[Texel; 16],
+    quality: EncodeQuality,
+    kind: ColourBlockKind,
+) -> (u16, u16, u32) {
+    let transparent: [bool; 16] = std::array::from_fn(|i| {
+        kind == ColourBlockKind::DXT1 && texels[i][3] < PUNCH_THROUGH_THRESHOLD
+    });
+
+    let three_colour = transparent.iter().any(|&t| t);
+
+    let points: Vec<Vec3> = texels
+        .iter()
+        .zip(transparent.iter())
+        .filter(|(_, t)| !**t)
+        .map(|(c, _)| [c[0] as f32, c[1] as f32, c[2] as f32])
+        .collect();
+
+    if points.is_empty() {
+        // Entirely transparent, equal endpoints select three colour mode
+        return (0, 0, u32::MAX);
+    }
+
+    let (start, end) = range_fit(&points);
+    let mut best = fit_indices(texels, &transparent, kind, start, end);
+
+    if quality == EncodeQuality::ClusterFit {
+        let weights: &[f32] = if three_colour {
+            &[0.0, 0.5, 1.0]
+        } else {
+            &[0.0, 1.0 / 3.0, 2.0 / 3.0, 1.0]
+        };
+
+        if let Some(candidate) = cluster_fit(&points, weights, |start, end| {
+            fit_indices(texels, &transparent, kind, start, end)
+        }) && candidate.3 < best.3
+        {
+            best = candidate;
+        }
+    }
+
+    (best.0, best.1, best.2)
+}
+
+fn range_fit(points: &[Vec3]) -> (Vec3, Vec3) {
+    let axis = principal_axis(points);
+
+    let mut min = (f32::MAX, points[0]);
+    let mut max = (f32::MIN, points[0]);
+
+    for p in points {
+        let d = dot(*p, axis);
+        if d < min.0 {
+            min = (d, *p);
+        }
+        if d > max.0 {
+            max = (d, *p);
+        }
+    }
+
+    (max.1, min.1)
+}
+
+fn cluster_fit(
+    points: &[Vec3],
+    weights: &[f32],
+    evaluate: impl Fn(Vec3, Vec3) -> (u16, u16, u32, f32),
+) -> Option<(u16, u16, u32, f32)> {
+    let axis = principal_axis(points);
+
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| dot(*a, axis).total_cmp(&dot(*b, axis)));
+
+    let n = sorted.len();
+    let mut best: Option<(u16, u16, u32, f32)> = None;
+
+    // Each split point marks where the next cluster starts, splits are non-decreasing
+    let mut splits = vec![0usize; weights.len() - 1];
+
+    loop {
+        let (mut a, mut b, mut c) = (0.0f32, 0.0f32, 0.0f32);
+        let mut x = [0.0f32; 3];
+        let mut y = [0.0f32; 3];
+
+        for (i, p) in sorted.iter().enumerate() {
+            let cluster = splits.iter().filter(|&&s| i >= s).count();
+            let t = weights[cluster];
+
+            a += (1.0 - t) * (1.0 - t);
+            b += (1.0 - t) * t;
+            c += t * t;
+
+            for ch in 0..3 {
+                x[ch] += (1.0 - t) * p[ch];
+                y[ch] += t * p[ch];
+            }
+        }
+
+        let det = a * c - b * b;
+
+        if det.abs() > f32::EPSILON {
+            let start = std::array::from_fn(|ch| ((c * x[ch] - b * y[ch]) / det).clamp(0.0, 255.0));
+            let end = std::array::from_fn(|ch| ((a * y[ch] - b * x[ch]) / det).clamp(0.0, 255.0));
+
+            let candidate = evaluate(start, end);
+
+            if best.is_none_or(|best| candidate.3 < best.3) {
+                best = Some(candidate);
+            }
+        }
+
+        // Advance to the next non-decreasing combination of split points
+        let mut level = splits.len();
+        loop {
+            if level == 0 {
+                return best;
+            }
+            level -= 1;
+
+            if splits[level] < n {
+                splits[level] += 1;
+                let value = splits[level];
+                splits[level..].iter_mut().for_each(|s| *s = value);
+                break;
+            }
+        }
+    }
+}
+
+// Quantises the endpoints and picks the closest palette entry for every texel, returning the
+// packed block and its squared error
+fn fit_indices(
+    texels: &[Texel; 16],
+    transparent: &[bool; 16],
+    kind: ColourBlockKind,
+    start: Vec3,
+    end: Vec3,
+) -> (u16, u16, u32, f32) {
+    let mut colour_1 = quantise_565(start);
+    let mut colour_2 = quantise_565(end);
+
+    let three_colour = match kind {
+        ColourBlockKind::DXT1 => {
+            let needs_transparency = transparent.iter().any(|&t| t);
+
+            // The endpoint order selects the block mode, so swap them into the order we need
+            if (needs_transparency && colour_1 > colour_2)
+                || (!needs_transparency && colour_1 < colour_2)
+            {
+                std::mem::swap(&mut colour_1, &mut colour_2);
+            }
+
+            colour_1 <= colour_2
+        }
+        ColourBlockKind::FourColour => false,
+    };
+
+    let palette = colour_palette(colour_1, colour_2, three_colour);
+    let usable = if three_colour { 3 } else { 4 };
+
+    let mut indices = 0u32;
+    let mut error = 0.0f32;
+
+    for (i, texel) in texels.iter().enumerate() {
+        let index = if transparent[i] {
+            3
+        } else {
+            let p = [texel[0] as f32, texel[1] as f32, texel[2] as f32];
+
+            let (index, distance) = palette[..usable]
+                .iter()
+                .map(|c| distance_squared(*c, p))
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+
+            error += distance;
+            index
+        };
+
+        indices |= (index as u32) << (2 * i);
+    }
+
+    (colour_1, colour_2, indices, error)
+}
+
+fn colour_palette(colour_1: u16, colour_2: u16, three_colour: bool) -> [Vec3; 4] {
+    let c1 = expand_565(colour_1);
+    let c2 = expand_565(colour_2);
+
+    let mix = |w1: f32, w2: f32, d: f32| -> Vec3 {
+        std::array::from_fn(|ch| ((w1 * c1[ch] + w2 * c2[ch]) / d).floor())
+    };
+
+    if three_colour {
+        [c1, c2, mix(1.0, 1.0, 2.0), [0.0; 3]]
+    } else {
+        [c1, c2, mix(2.0, 1.0, 3.0), mix(1.0, 2.0, 3.0)]
+    }
+}
+
+fn quantise_565(colour: Vec3) -> u16 {
+    let r = (colour[0].clamp(0.0, 255.0) * 31.0 / 255.0).round() as u16;
+    let g = (colour[1].clamp(0.0, 255.0) * 63.0 / 255.0).round() as u16;
+    let b = (colour[2].clamp(0.0, 255.0) * 31.0 / 255.0).round() as u16;
+
+    (r << 11) | (g << 5) | b
+}
+
+fn expand_565(colour: u16) -> Vec3 {
+    let colour = RGBColour::from(R5G6B5Colour::from(colour));
+
+    [colour.r as f32, colour.g as f32, colour.b as f32]
+}
+
+fn principal_axis(points: &[Vec3]) -> Vec3 {
+    let n = points.len() as f32;
+    let mean: Vec3 = std::array::from_fn(|ch| points.iter().map(|p| p[ch]).sum::<f32>() / n);
+
+    let mut covariance = [[0.0f32; 3]; 3];
+    for p in points {
+        let d: Vec3 = std::array::from_fn(|ch| p[ch] - mean[ch]);
+        for i in 0..3 {
+            for j in 0..3 {
+                covariance[i][j] += d[i] * d[j];
+            }
+        }
+    }
+
+    // Power iteration converges quickly enough for a 3x3 matrix. Starting from the column of the
+    // channel with the most variance keeps the start from being orthogonal to the axis, which
+    // would collapse every texel onto one point.
+    let channel = (0..3)
+        .max_by(|&a, &b| covariance[a][a].total_cmp(&covariance[b][b]))
+        .unwrap();
+
+    let mut axis = if covariance[channel][channel] > 0.0 {
+        covariance[channel]
+    } else {
+        [1.0f32, 1.0, 1.0]
+    };
+    for _ in 0..8 {
+        let next: Vec3 = std::array::from_fn(|i| dot(covariance[i], axis));
+        let length = dot(next, next).sqrt();
+
+        if length < f32::EPSILON {
+            break;
+        }
+
+        axis = next.map(|v| v / length);
+    }
+
+    axis
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn distance_squared(a: Vec3, b: Vec3) -> f32 {
+    let d: Vec3 = std::array::from_fn(|ch| a[ch] - b[ch]);
+    dot(d, d)
+}
@@ -0,0 +1,502 @@
+// PNG and TGA import and export, so decoded textures can be handed to ordinary image tools. Both
+// directions go through straight alpha RGBA8, which keeps alpha intact for every format.
+
+use crate::Error;
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::format::image::{
+    Image, ImageFormat,
+    codec::ImageCodec,
+    cube::{CubeLayout, CubeMap},
+    encoder::EncodeQuality,
+    mip::{MipChain, MipFilter},
+    volume::VolumeTexture,
+};
+
+const TGA_HEADER_SIZE: usize = 18;
+
+// Image types
+const TGA_TRUE_COLOUR: u8 = 2;
+const TGA_GREYSCALE: u8 = 3;
+const TGA_RLE_TRUE_COLOUR: u8 = 10;
+const TGA_RLE_GREYSCALE: u8 = 11;
+
+// Descriptor bits
+const TGA_RIGHT_TO_LEFT: u8 = 0x10;
+const TGA_TOP_TO_BOTTOM: u8 = 0x20;
+
+// How the levels of a mip chain are written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MipExport {
+    // One file per level, named with a `_mip<level>` suffix
+    #[default]
+    Separate,
+    // Every level side by side in a single file, top aligned, with the unused area transparent
+    Strip,
+}
+
+impl Image {
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), Error> {
+        write_png(writer, &self.decode_rgba(), self.width(), self.height())
+    }
+
+    pub fn write_tga<W: Write>(&self, writer: W) -> Result<(), Error> {
+        write_tga(writer, &self.decode_rgba(), self.width(), self.height())
+    }
+
+    // Reads any PNG colour type and bit depth, then encodes it to `format`
+    pub fn from_png<R: Read>(
+        reader: R,
+        format: ImageFormat,
+        quality: EncodeQuality,
+    ) -> Result<Image, Error> {
+        let (rgba, width, height) = read_png(reader)?;
+        Image::encode_rgba(&rgba, width, height, format, quality)
+    }
+
+    // Reads 8 bit greyscale and 24/32 bit true colour TGAs, compressed or not, then encodes it to
+    // `format`
+    pub fn from_tga<R: Read>(
+        reader: R,
+        format: ImageFormat,
+        quality: EncodeQuality,
+    ) -> Result<Image, Error> {
+        let (rgba, width, height) = read_tga(reader)?;
+        Image::encode_rgba(&rgba, width, height, format, quality)
+    }
+}
+
+impl MipChain {
+    pub fn write_png(&self, path: &Path, export: MipExport) -> Result<(), Error> {
+        self.write_levels(path, export, write_png)
+    }
+
+    pub fn write_tga(&self, path: &Path, export: MipExport) -> Result<(), Error> {
+        self.write_levels(path, export, write_tga)
+    }
+
+    fn write_levels(
+        &self,
+        path: &Path,
+        export: MipExport,
+        write: fn(std::fs::File, &[u8], u32, u32) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        match export {
+            MipExport::Separate => {
+                for level in 0..self.level_count() {
+                    let image = self.decode_level(level)?;
+
+                    write(
+                        std::fs::File::create(level_path(path, level))?,
+                        &image.decode_rgba(),
+                        image.width(),
+                        image.height(),
+                    )?;
+                }
+
+                Ok(())
+            }
+            MipExport::Strip => {
+                let (rgba, width, height) = self.to_rgba_strip()?;
+                write(std::fs::File::create(path)?, &rgba, width, height)
+            }
+        }
+    }
+
+    // Every level decoded and placed left to right, top aligned
+    pub fn to_rgba_strip(&self) -> Result<(Vec<u8>, u32, u32), Error> {
+        let width: u32 = (0..self.level_count())
+            .map(|level| MipChain::level_dimensions(self.width(), self.height(), level).0)
+            .sum();
+        let height = self.height();
+
+        let mut strip = vec![0u8; width as usize * height as usize * 4];
+        let mut x = 0usize;
+
+        for level in 0..self.level_count() {
+            let image = self.decode_level(level)?;
+            let rgba = image.decode_rgba();
+
+            let level_row = image.width() as usize * 4;
+
+            for (y, row) in rgba.chunks_exact(level_row).enumerate() {
+                let offset = (y * width as usize + x) * 4;
+                strip[offset..offset + level_row].copy_from_slice(row);
+            }
+
+            x += image.width() as usize;
+        }
+
+        Ok((strip, width, height))
+    }
+}
+
+impl CubeMap {
+    // One level with every face flattened into a cross or strip
+    pub fn write_png<W: Write>(
+        &self,
+        writer: W,
+        level: u32,
+        layout: CubeLayout,
+    ) -> Result<(), Error> {
+        let (rgba, width, height) = self.to_rgba(level, layout)?;
+        write_png(writer, &rgba, width, height)
+    }
+
+    pub fn write_tga<W: Write>(
+        &self,
+        writer: W,
+        level: u32,
+        layout: CubeLayout,
+    ) -> Result<(), Error> {
+        let (rgba, width, height) = self.to_rgba(level, layout)?;
+        write_tga(writer, &rgba, width, height)
+    }
+
+    pub fn from_png<R: Read>(
+        reader: R,
+        layout: CubeLayout,
+        format: ImageFormat,
+        level_count: u32,
+        filter: MipFilter,
+        quality: EncodeQuality,
+    ) -> Result<CubeMap, Error> {
+        let (rgba, width, height) = read_png(reader)?;
+        CubeMap::from_rgba(
+            &rgba,
+            width,
+            height,
+            layout,
+            format,
+            level_count,
+            filter,
+            quality,
+        )
+    }
+
+    pub fn from_tga<R: Read>(
+        reader: R,
+        layout: CubeLayout,
+        format: ImageFormat,
+        level_count: u32,
+        filter: MipFilter,
+        quality: EncodeQuality,
+    ) -> Result<CubeMap, Error> {
+        let (rgba, width, height) = read_tga(reader)?;
+        CubeMap::from_rgba(
+            &rgba,
+            width,
+            height,
+            layout,
+            format,
+            level_count,
+            filter,
+            quality,
+        )
+    }
+}
+
+impl VolumeTexture {
+    // One level with every slice stacked top to bottom
+    pub fn write_png<W: Write>(&self, writer: W, level: u32) -> Result<(), Error> {
+        let (rgba, width, height) = self.to_rgba(level)?;
+        write_png(writer, &rgba, width, height)
+    }
+
+    pub fn write_tga<W: Write>(&self, writer: W, level: u32) -> Result<(), Error> {
+        let (rgba, width, height) = self.to_rgba(level)?;
+        write_tga(writer, &rgba, width, height)
+    }
+
+    // The image must be `depth` equally sized slices stacked top to bottom
+    pub fn from_png<R: Read>(
+        reader: R,
+        depth: u32,
+        format: ImageFormat,
+        level_count: u32,
+        filter: MipFilter,
+        quality: EncodeQuality,
+    ) -> Result<VolumeTexture, Error> {
+        let (rgba, width, height) = read_png(reader)?;
+        volume_from_strip(
+            &rgba,
+            width,
+            height,
+            depth,
+            format,
+            level_count,
+            filter,
+            quality,
+        )
+    }
+
+    pub fn from_tga<R: Read>(
+        reader: R,
+        depth: u32,
+        format: ImageFormat,
+        level_count: u32,
+        filter: MipFilter,
+        quality: EncodeQuality,
+    ) -> Result<VolumeTexture, Error> {
+        let (rgba, width, height) = read_tga(reader)?;
+        volume_from_strip(
+            &rgba,
+            width,
+            height,
+            depth,
+            format,
+            level_count,
+            filter,
+            quality,
+        )
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn volume_from_strip(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    depth: u32,
+    format: ImageFormat,
+    level_count: u32,
+    filter: MipFilter,
+    quality: EncodeQuality,
+) -> Result<VolumeTexture, Error> {
+    if depth == 0 || !height.is_multiple_of(depth) {
+        return Err(Error::InvalidDimensions {
+            width,
+            height,
+            depth,
+            reason: "the image cannot be split into equal slices",
+        });
+    }
+
+    VolumeTexture::from_rgba(
+        rgba,
+        width,
+        height / depth,
+        depth,
+        format,
+        level_count,
+        filter,
+        quality,
+    )
+}
+
+// `texture.png` becomes `texture_mip2.png` for level 2
+fn level_path(path: &Path, level: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let name = match path.extension() {
+        Some(extension) => format!("{}_mip{}.{}", stem, level, extension.to_string_lossy()),
+        None => format!("{}_mip{}", stem, level),
+    };
+
+    path.with_file_name(name)
+}
+
+pub fn write_png<W: Write>(writer: W, rgba: &[u8], width: u32, height: u32) -> Result<(), Error> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rgba[..width as usize * height as usize * 4])?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+pub fn read_png<R: Read>(reader: R) -> Result<(Vec<u8>, u32, u32), Error> {
+    let mut decoder = png::Decoder::new(reader);
+
+    // Palettes, low bit depths and 16 bit channels all come out as 8 bits per channel
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0u8; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+
+    let pixels = &buffer[..info.buffer_size()];
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|c| [c[0], c[1], c[2], u8::MAX])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|c| [c[0], c[0], c[0], c[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&l| [l, l, l, u8::MAX]).collect(),
+        png::ColorType::Indexed => {
+            return Err(Error::InvalidData(
+                "Indexed PNG was not expanded.".to_string(),
+            ));
+        }
+    };
+
+    Ok((rgba, info.width, info.height))
+}
+
+// Uncompressed 32 bit, top to bottom
+pub fn write_tga<W: Write>(
+    mut writer: W,
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), Error> {
+    let (Ok(tga_width), Ok(tga_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(Error::invalid_dimensions(
+            width,
+            height,
+            "too large for a TGA",
+        ));
+    };
+
+    let mut header = [0u8; TGA_HEADER_SIZE];
+    header[2] = TGA_TRUE_COLOUR;
+    header[12..14].copy_from_slice(&tga_width.to_le_bytes());
+    header[14..16].copy_from_slice(&tga_height.to_le_bytes());
+    header[16] = 32;
+    header[17] = TGA_TOP_TO_BOTTOM | 8;
+
+    let pixels: Vec<u8> = rgba[..width as usize * height as usize * 4]
+        .chunks_exact(4)
+        .flat_map(|c| [c[2], c[1], c[0], c[3]])
+        .collect();
+
+    writer.write_all(&header)?;
+    writer.write_all(&pixels)?;
+
+    Ok(())
+}
+
+pub fn read_tga<R: Read>(mut reader: R) -> Result<(Vec<u8>, u32, u32), Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    let header = bytes
+        .get(..TGA_HEADER_SIZE)
+        .ok_or(Error::insufficient_data(TGA_HEADER_SIZE, bytes.len()))?;
+
+    let id_length = header[0] as usize;
+    let has_colour_map = header[1] != 0;
+    let image_type = header[2];
+    let colour_map_length = u16::from_le_bytes([header[5], header[6]]) as usize;
+    let colour_map_bits = header[7] as usize;
+    let width = u16::from_le_bytes([header[12], header[13]]) as u32;
+    let height = u16::from_le_bytes([header[14], header[15]]) as u32;
+    let bits_per_pixel = header[16];
+    let descriptor = header[17];
+
+    let bytes_per_pixel = match (image_type, bits_per_pixel) {
+        (TGA_TRUE_COLOUR | TGA_RLE_TRUE_COLOUR, 24) => 3,
+        (TGA_TRUE_COLOUR | TGA_RLE_TRUE_COLOUR, 32) => 4,
+        (TGA_GREYSCALE | TGA_RLE_GREYSCALE, 8) => 1,
+        _ => {
+            return Err(Error::UnsupportedFormat(format!(
+                "TGA type {} at {} bits per pixel",
+                image_type, bits_per_pixel
+            )));
+        }
+    };
+
+    if width == 0 || height == 0 {
+        return Err(Error::invalid_dimensions(
+            width,
+            height,
+            "images need at least one texel",
+        ));
+    }
+
+    // Colour maps aren't used by true colour or greyscale images, but still have to be skipped
+    let colour_map_size = if has_colour_map {
+        colour_map_length * colour_map_bits.div_ceil(8)
+    } else {
+        0
+    };
+
+    let data_offset = TGA_HEADER_SIZE + id_length + colour_map_size;
+    let data = bytes
+        .get(data_offset..)
+        .ok_or(Error::insufficient_data(data_offset, bytes.len()))?;
+
+    let pixel_count = width as usize * height as usize;
+
+    let pixels = if matches!(image_type, TGA_RLE_TRUE_COLOUR | TGA_RLE_GREYSCALE) {
+        decode_tga_rle(data, pixel_count, bytes_per_pixel)
+            .ok_or(Error::InvalidData("The TGA is truncated.".to_string()))?
+    } else {
+        data.get(..pixel_count * bytes_per_pixel)
+            .ok_or(Error::insufficient_data(
+                data_offset + pixel_count * bytes_per_pixel,
+                bytes.len(),
+            ))?
+            .to_vec()
+    };
+
+    let mut rgba = vec![0u8; pixel_count * 4];
+
+    for (i, pixel) in pixels.chunks_exact(bytes_per_pixel).enumerate() {
+        let (mut x, mut y) = (i % width as usize, i / width as usize);
+
+        if descriptor & TGA_RIGHT_TO_LEFT != 0 {
+            x = width as usize - 1 - x;
+        }
+
+        if descriptor & TGA_TOP_TO_BOTTOM == 0 {
+            y = height as usize - 1 - y;
+        }
+
+        let offset = (y * width as usize + x) * 4;
+
+        rgba[offset..offset + 4].copy_from_slice(&match pixel {
+            [b, g, r, a] => [*r, *g, *b, *a],
+            [b, g, r] => [*r, *g, *b, u8::MAX],
+            [l] => [*l, *l, *l, u8::MAX],
+            _ => unreachable!(),
+        });
+    }
+
+    Ok((rgba, width, height))
+}
+
+// Packets are a count byte followed by either one pixel repeated (high bit set) or that many raw
+// pixels
+fn decode_tga_rle(data: &[u8], pixel_count: usize, bytes_per_pixel: usize) -> Option<Vec<u8>> {
+    // A packet expands to at most 128 pixels, so don't trust the header beyond what the data can
+    // hold
+    let mut pixels = Vec::with_capacity((pixel_count * bytes_per_pixel).min(data.len() * 128));
+    let mut offset = 0;
+
+    while pixels.len() < pixel_count * bytes_per_pixel {
+        let packet = *data.get(offset)?;
+        let count = (packet & 0x7F) as usize + 1;
+        offset += 1;
+
+        if packet & 0x80 != 0 {
+            let pixel = data.get(offset..offset + bytes_per_pixel)?;
+            offset += bytes_per_pixel;
+
+            for _ in 0..count {
+                pixels.extend_from_slice(pixel);
+            }
+        } else {
+            let run = data.get(offset..offset + count * bytes_per_pixel)?;
+            offset += count * bytes_per_pixel;
+
+            pixels.extend_from_slice(run);
+        }
+    }
+
+    // A packet may run past the last pixel
+    pixels.truncate(pixel_count * bytes_per_pixel);
+
+    Some(pixels)
+}
@@ -0,0 +1,192 @@
+// Quality metrics between two RGBA8 images of the same size, usually a source image and the result
+// of encoding and decoding it. SSIM uses 8x8 windows stepped 4 texels at a time, with the usual
+// constants for 8 bit data.
+
+use crate::Error;
+use crate::format::image::encoder;
+
+const WINDOW: u32 = 8;
+const STRIDE: u32 = 4;
+
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Weighting {
+    #[default]
+    Uniform,
+    // Colour errors scaled by the reference alpha, so texels that end up invisible don't count.
+    // Alpha itself is always weighted uniformly.
+    Alpha,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelMetrics {
+    pub rmse: f64,
+    // Infinite when the channels are identical
+    pub psnr: f64,
+    pub ssim: f64,
+}
+
+impl ChannelMetrics {
+    fn from_mse(mse: f64, ssim: f64) -> ChannelMetrics {
+        ChannelMetrics {
+            rmse: mse.sqrt(),
+            psnr: if mse > 0.0 {
+                10.0 * (255.0 * 255.0 / mse).log10()
+            } else {
+                f64::INFINITY
+            },
+            ssim,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    // In RGBA order
+    pub channels: [ChannelMetrics; 4],
+    // Mean squared error and SSIM averaged over all four channels
+    pub overall: ChannelMetrics,
+}
+
+impl Metrics {
+    pub fn from_rgba(
+        reference: &[u8],
+        test: &[u8],
+        width: u32,
+        height: u32,
+        weighting: Weighting,
+    ) -> Result<Metrics, Error> {
+        encoder::validate_rgba(reference, width, height)?;
+        encoder::validate_rgba(test, width, height)?;
+
+        let texels = width as usize * height as usize;
+
+        let uniform = vec![1.0; texels];
+        let alpha: Vec<f64> = match weighting {
+            Weighting::Uniform => Vec::new(),
+            Weighting::Alpha => reference[..texels * 4]
+                .chunks_exact(4)
+                .map(|t| t[3] as f64 / 255.0)
+                .collect(),
+        };
+
+        let mut mse = [0.0; 4];
+        let mut ssim = [0.0; 4];
+
+        for channel in 0..4 {
+            let weights = if weighting == Weighting::Alpha && channel < 3 {
+                &alpha
+            } else {
+                &uniform
+            };
+
+            let samples = |rgba: &[u8]| -> Vec<f64> {
+                rgba[..texels * 4]
+                    .chunks_exact(4)
+                    .map(|t| t[channel] as f64)
+                    .collect()
+            };
+
+            let x = samples(reference);
+            let y = samples(test);
+
+            mse[channel] = weighted_mse(&x, &y, weights);
+            ssim[channel] = mean_ssim(&x, &y, weights, width, height);
+        }
+
+        Ok(Metrics {
+            channels: std::array::from_fn(|i| ChannelMetrics::from_mse(mse[i], ssim[i])),
+            overall: ChannelMetrics::from_mse(
+                mse.iter().sum::<f64>() / 4.0,
+                ssim.iter().sum::<f64>() / 4.0,
+            ),
+        })
+    }
+}
+
+fn weighted_mse(x: &[f64], y: &[f64], weights: &[f64]) -> f64 {
+    let total: f64 = weights.iter().sum();
+
+    if total == 0.0 {
+        return 0.0;
+    }
+
+    x.iter()
+        .zip(y)
+        .zip(weights)
+        .map(|((x, y), w)| w * (x - y) * (x - y))
+        .sum::<f64>()
+        / total
+}
+
+// Windows are averaged by their total weight, so with alpha weighting a mostly transparent window
+// counts for little
+fn mean_ssim(x: &[f64], y: &[f64], weights: &[f64], width: u32, height: u32) -> f64 {
+    let (window_width, window_height) = (WINDOW.min(width), WINDOW.min(height));
+
+    let mut total = 0.0;
+    let mut sum = 0.0;
+
+    for top in window_starts(height, window_height) {
+        for left in window_starts(width, window_width) {
+            let indices = (top..top + window_height).flat_map(|row| {
+                (left..left + window_width).map(move |col| (row * width + col) as usize)
+            });
+
+            if let Some((weight, ssim)) = window_ssim(x, y, weights, indices) {
+                total += weight;
+                sum += weight * ssim;
+            }
+        }
+    }
+
+    if total == 0.0 { 1.0 } else { sum / total }
+}
+
+// Returns the window's total weight along with its SSIM, or None if nothing in it is weighted
+fn window_ssim(
+    x: &[f64],
+    y: &[f64],
+    weights: &[f64],
+    indices: impl Iterator<Item = usize> + Clone,
+) -> Option<(f64, f64)> {
+    let total: f64 = indices.clone().map(|i| weights[i]).sum();
+
+    if total == 0.0 {
+        return None;
+    }
+
+    let mean = |v: &[f64]| indices.clone().map(|i| weights[i] * v[i]).sum::<f64>() / total;
+    let (mean_x, mean_y) = (mean(x), mean(y));
+
+    let (mut var_x, mut var_y, mut cov) = (0.0, 0.0, 0.0);
+
+    for i in indices {
+        let (dx, dy) = (x[i] - mean_x, y[i] - mean_y);
+
+        var_x += weights[i] * dx * dx;
+        var_y += weights[i] * dy * dy;
+        cov += weights[i] * dx * dy;
+    }
+
+    let (var_x, var_y, cov) = (var_x / total, var_y / total, cov / total);
+
+    let ssim = ((2.0 * mean_x * mean_y + C1) * (2.0 * cov + C2))
+        / ((mean_x * mean_x + mean_y * mean_y + C1) * (var_x + var_y + C2));
+
+    Some((total, ssim))
+}
+
+// Window offsets along one axis, with the last window always touching the far edge
+fn window_starts(length: u32, window: u32) -> Vec<u32> {
+    let last = length - window;
+    let mut starts: Vec<u32> = (0..=last).step_by(STRIDE as usize).collect();
+
+    if starts.last() != Some(&last) {
+        starts.push(last);
+    }
+
+    starts
+}
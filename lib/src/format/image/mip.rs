@@ -0,0 +1,424 @@
+use crate::Error;
+use crate::format::image::{
+    Image, ImageFormat,
+    codec::ImageCodec,
+    encoder::{self, EncodeQuality},
+    p8::{MAX_PALETTE_SIZE, P8Image},
+    swizzle::{self, Layout},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MipFilter {
+    // Plain 2x2 average
+    Box,
+    // Windowed sinc, keeps lower levels sharper than a box filter
+    #[default]
+    Kaiser,
+}
+
+// A texture together with its chain of progressively halved levels, stored back to back on disk
+// starting from the full size level
+#[derive(Debug)]
+pub struct MipChain {
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+
+    // Only used by P8 textures, where every level shares one palette
+    palette: Option<Vec<u8>>,
+
+    levels: Vec<Vec<u8>>,
+}
+
+impl MipChain {
+    // Number of levels in a complete chain, down to and including 1x1
+    pub fn full_level_count(width: u32, height: u32) -> u32 {
+        32 - width.max(height).max(1).leading_zeros()
+    }
+
+    pub fn level_dimensions(width: u32, height: u32, level: u32) -> (u32, u32) {
+        (
+            width.checked_shr(level).unwrap_or(0).max(1),
+            height.checked_shr(level).unwrap_or(0).max(1),
+        )
+    }
+
+    pub fn from_bytes(
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        level_count: u32,
+    ) -> Result<MipChain, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::invalid_dimensions(
+                width,
+                height,
+                "images need at least one texel",
+            ));
+        }
+
+        if level_count == 0 || level_count > MipChain::full_level_count(width, height) {
+            return Err(Error::InvalidLevelCount {
+                level_count,
+                max: MipChain::full_level_count(width, height),
+            });
+        }
+
+        let mut levels = Vec::with_capacity(level_count as usize);
+        let mut offset = 0usize;
+
+        for level in 0..level_count {
+            let (level_width, level_height) = MipChain::level_dimensions(width, height, level);
+            let size = format.surface_size(level_width, level_height);
+
+            let level_bytes =
+                bytes
+                    .get(offset..offset.saturating_add(size))
+                    .ok_or(Error::insufficient_data(
+                        offset.saturating_add(size),
+                        bytes.len(),
+                    ))?;
+
+            levels.push(level_bytes.to_vec());
+            offset += size;
+        }
+
+        Ok(MipChain {
+            format,
+            width,
+            height,
+            palette: None,
+            levels,
+        })
+    }
+
+    // Builds a chain from a full size RGBA image, generating every smaller level with the given
+    // filter before encoding each one
+    pub fn from_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        level_count: u32,
+        filter: MipFilter,
+        quality: EncodeQuality,
+    ) -> Result<MipChain, Error> {
+        let images = MipChain::generate_levels(rgba, width, height, level_count, filter)?;
+        MipChain::encode_levels(images, width, height, format, quality, None)
+    }
+
+    // P8 only. Every level is mapped onto the given A8R8G8B8 palette instead of choosing a new one,
+    // so several chains can share a palette.
+    pub fn from_rgba_with_palette(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        level_count: u32,
+        filter: MipFilter,
+        palette: Vec<u8>,
+    ) -> Result<MipChain, Error> {
+        let images = MipChain::generate_levels(rgba, width, height, level_count, filter)?;
+        MipChain::encode_levels(
+            images,
+            width,
+            height,
+            format,
+            EncodeQuality::default(),
+            Some(palette),
+        )
+    }
+
+    // The full size image followed by each smaller level, as RGBA8
+    fn generate_levels(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        level_count: u32,
+        filter: MipFilter,
+    ) -> Result<Vec<(Vec<u8>, u32, u32)>, Error> {
+        encoder::validate_rgba(rgba, width, height)?;
+
+        if level_count == 0 || level_count > MipChain::full_level_count(width, height) {
+            return Err(Error::InvalidLevelCount {
+                level_count,
+                max: MipChain::full_level_count(width, height),
+            });
+        }
+
+        let mut images = vec![(
+            rgba[..width as usize * height as usize * 4].to_vec(),
+            width,
+            height,
+        )];
+
+        for level in 1..level_count {
+            let (src, src_width, src_height) = &images[level as usize - 1];
+            let (dst_width, dst_height) = MipChain::level_dimensions(width, height, level);
+
+            let dst = downsample(src, *src_width, *src_height, dst_width, dst_height, filter);
+            images.push((dst, dst_width, dst_height));
+        }
+
+        Ok(images)
+    }
+
+    fn encode_levels(
+        images: Vec<(Vec<u8>, u32, u32)>,
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        quality: EncodeQuality,
+        mut palette: Option<Vec<u8>>,
+    ) -> Result<MipChain, Error> {
+        // Unless one was given, the palette is chosen from the full size level and shared with the
+        // rest of the chain
+        let levels = images
+            .iter()
+            .map(|(rgba, level_width, level_height)| match format {
+                ImageFormat::P8(layout) => {
+                    let image = match &palette {
+                        None => {
+                            P8Image::from_rgba(rgba, *level_width, *level_height, MAX_PALETTE_SIZE)?
+                        }
+                        Some(palette) => P8Image::from_rgba_with_palette(
+                            rgba,
+                            *level_width,
+                            *level_height,
+                            P8Image::palette_from_bytes(palette),
+                        )?,
+                    };
+
+                    if palette.is_none() {
+                        palette = Some(image.palette_to_bytes());
+                    }
+
+                    image.to_bytes(layout)
+                }
+                _ => Image::encode_rgba(rgba, *level_width, *level_height, format, quality)?
+                    .to_surface_bytes(),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(MipChain {
+            format,
+            width,
+            height,
+            palette: if matches!(format, ImageFormat::P8(_)) {
+                palette
+            } else {
+                None
+            },
+            levels,
+        })
+    }
+
+    pub fn decode_level(&self, level: u32) -> Result<Image, Error> {
+        let bytes = self
+            .levels
+            .get(level as usize)
+            .ok_or(Error::SurfaceOutOfRange { index: 0, level })?;
+
+        let (width, height) = MipChain::level_dimensions(self.width, self.height, level);
+
+        self.format
+            .decode(bytes, width, height, self.palette.as_deref())
+    }
+
+    // Swizzles or unswizzles every level in place. Block compressed chains are left untouched.
+    pub fn set_layout(&mut self, layout: Layout) -> Result<(), Error> {
+        let from = self.format.layout();
+        let bytes_per_pixel = self.format.block_size();
+
+        if self.format.is_block_compressed() || from == layout {
+            return Ok(());
+        }
+
+        for (level, bytes) in self.levels.iter_mut().enumerate() {
+            let (width, height) = MipChain::level_dimensions(self.width, self.height, level as u32);
+            *bytes = swizzle::relayout(bytes, width, height, bytes_per_pixel, from, layout)?;
+        }
+
+        self.format = self.format.with_layout(layout);
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.levels.concat()
+    }
+
+    pub fn level_bytes(&self, level: u32) -> Option<&[u8]> {
+        self.levels.get(level as usize).map(|l| l.as_slice())
+    }
+
+    pub fn level_count(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    // Palette in its on disk A8R8G8B8 layout, required before P8 levels can be decoded
+    pub fn palette(&self) -> Option<&[u8]> {
+        self.palette.as_deref()
+    }
+
+    pub fn set_palette(&mut self, palette: Vec<u8>) {
+        self.palette = Some(palette);
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+// Resamples an RGBA image with a separable filter. Weights are computed in destination texel units
+// so the same kernel works for both halving and the 1 texel wide tail of non-square chains.
+pub fn downsample(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    dst_width: u32,
+    dst_height: u32,
+    filter: MipFilter,
+) -> Vec<u8> {
+    let horizontal = filter_weights(width, dst_width, filter);
+    let vertical = filter_weights(height, dst_height, filter);
+
+    let mut intermediate = vec![0f32; dst_width as usize * height as usize * 4];
+
+    for y in 0..height as usize {
+        for (x, weights) in horizontal.iter().enumerate() {
+            let mut sum = [0f32; 4];
+
+            for (src_x, weight) in weights {
+                let offset = (y * width as usize + src_x) * 4;
+                for (ch, s) in sum.iter_mut().enumerate() {
+                    *s += rgba[offset + ch] as f32 * weight;
+                }
+            }
+
+            let offset = (y * dst_width as usize + x) * 4;
+            intermediate[offset..offset + 4].copy_from_slice(&sum);
+        }
+    }
+
+    let mut out = vec![0u8; dst_width as usize * dst_height as usize * 4];
+
+    for (y, weights) in vertical.iter().enumerate() {
+        for x in 0..dst_width as usize {
+            let mut sum = [0f32; 4];
+
+            for (src_y, weight) in weights {
+                let offset = (src_y * dst_width as usize + x) * 4;
+                for (ch, s) in sum.iter_mut().enumerate() {
+                    *s += intermediate[offset + ch] * weight;
+                }
+            }
+
+            let offset = (y * dst_width as usize + x) * 4;
+            for (ch, s) in sum.iter().enumerate() {
+                out[offset + ch] = s.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    out
+}
+
+const KAISER_WIDTH: f32 = 3.0;
+const KAISER_ALPHA: f32 = 4.0;
+
+// For every destination texel, the contributing source texels and their normalised weights
+fn filter_weights(src: u32, dst: u32, filter: MipFilter) -> Vec<Vec<(usize, f32)>> {
+    let scale = src as f32 / dst as f32;
+
+    let radius = match filter {
+        MipFilter::Box => 0.5,
+        MipFilter::Kaiser => KAISER_WIDTH,
+    };
+
+    (0..dst)
+        .map(|x| {
+            let centre = (x as f32 + 0.5) * scale;
+
+            let start = (centre - radius * scale).floor().max(0.0) as u32;
+            let end = ((centre + radius * scale).ceil() as u32).min(src);
+
+            let mut weights: Vec<(usize, f32)> = (start..end)
+                .map(|s| {
+                    let t = (s as f32 + 0.5 - centre) / scale;
+                    (s as usize, filter_value(t, filter))
+                })
+                .filter(|(_, w)| *w != 0.0)
+                .collect();
+
+            let total: f32 = weights.iter().map(|(_, w)| w).sum();
+
+            if total.abs() < f32::EPSILON {
+                // Fall back to the nearest texel
+                let nearest = (centre as u32).min(src - 1) as usize;
+                return vec![(nearest, 1.0)];
+            }
+
+            weights.iter_mut().for_each(|(_, w)| *w /= total);
+            weights
+        })
+        .collect()
+}
+
+fn filter_value(t: f32, filter: MipFilter) -> f32 {
+    match filter {
+        MipFilter::Box => {
+            if t.abs() <= 0.5 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        MipFilter::Kaiser => {
+            if t.abs() >= KAISER_WIDTH {
+                return 0.0;
+            }
+
+            let x = t / KAISER_WIDTH;
+            let window = bessel_i0(KAISER_ALPHA * (1.0 - x * x).sqrt()) / bessel_i0(KAISER_ALPHA);
+
+            sinc(t) * window
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-4 {
+        1.0
+    } else {
+        let px = std::f32::consts::PI * x;
+        px.sin() / px
+    }
+}
+
+// Zeroth order modified Bessel function of the first kind, by its power series
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0f32;
+    let mut term = 1.0f32;
+    let half_sq = x * x / 4.0;
+
+    for k in 1..32 {
+        term *= half_sq / (k * k) as f32;
+        sum += term;
+
+        if term < sum * 1e-8 {
+            break;
+        }
+    }
+
+    sum
+}
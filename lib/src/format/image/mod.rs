@@ -1,10 +1,236 @@
-use crate::format::image::{dxt1::DXT1, dxt2::DXT2};
+use crate::Error;
+use crate::format::image::{
+    codec::ImageCodec,
+    dxt1::DXT1,
+    dxt2::DXT2,
+    dxt3::DXT3,
+    dxt4::DXT4,
+    dxt5::DXT5,
+    encoder::EncodeQuality,
+    p8::P8Image,
+    raw::{PixelFormat, RawImage},
+    swizzle::Layout,
+};
 
+pub mod alpha;
+mod block;
+pub mod codec;
+pub mod cube;
 pub mod dxt1;
 pub mod dxt2;
+pub mod dxt3;
+pub mod dxt4;
+pub mod dxt5;
+pub mod encoder;
+#[cfg(feature = "interchange")]
+pub mod interchange;
+pub mod metrics;
+pub mod mip;
+pub mod p8;
+pub mod raw;
+pub mod shape;
+pub mod swizzle;
 pub mod types;
+pub mod volume;
 
 pub enum Image {
     DXT1(DXT1),
     DXT2(DXT2),
+    DXT3(DXT3),
+    DXT4(DXT4),
+    DXT5(DXT5),
+    Raw(RawImage),
+    P8(P8Image),
+}
+
+// Describes how a single surface is stored, without holding any data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    DXT1,
+    DXT2,
+    DXT3,
+    DXT4,
+    DXT5,
+    Raw(PixelFormat, Layout),
+    P8(Layout),
+}
+
+impl ImageFormat {
+    pub fn is_block_compressed(&self) -> bool {
+        matches!(
+            self,
+            ImageFormat::DXT1
+                | ImageFormat::DXT2
+                | ImageFormat::DXT3
+                | ImageFormat::DXT4
+                | ImageFormat::DXT5
+        )
+    }
+
+    // Texel dimensions of the smallest addressable unit, (1, 1) for uncompressed formats
+    pub fn block_dimensions(&self) -> (u32, u32) {
+        if self.is_block_compressed() {
+            (4, 4)
+        } else {
+            (1, 1)
+        }
+    }
+
+    // Block compressed formats are never swizzled
+    pub fn layout(&self) -> Layout {
+        match self {
+            ImageFormat::Raw(_, layout) | ImageFormat::P8(layout) => *layout,
+            _ => Layout::Linear,
+        }
+    }
+
+    pub fn with_layout(&self, layout: Layout) -> ImageFormat {
+        match self {
+            ImageFormat::Raw(format, _) => ImageFormat::Raw(*format, layout),
+            ImageFormat::P8(_) => ImageFormat::P8(layout),
+            format => *format,
+        }
+    }
+
+    // Number of bytes per block, or per pixel for uncompressed formats
+    pub fn block_size(&self) -> usize {
+        match self {
+            ImageFormat::DXT1 => 8,
+            ImageFormat::DXT2 | ImageFormat::DXT3 | ImageFormat::DXT4 | ImageFormat::DXT5 => 16,
+            ImageFormat::Raw(format, _) => format.bytes_per_pixel(),
+            ImageFormat::P8(_) => 1,
+        }
+    }
+
+    // Number of bytes a surface of the given size occupies. Block compressed surfaces are padded
+    // out to whole 4x4 blocks. Saturates instead of overflowing, so a surface too large to address
+    // simply fails any length check against real data.
+    pub fn surface_size(&self, width: u32, height: u32) -> usize {
+        let (block_width, block_height) = self.block_dimensions();
+
+        (width.div_ceil(block_width) as usize)
+            .saturating_mul(height.div_ceil(block_height) as usize)
+            .saturating_mul(self.block_size())
+    }
+
+    // P8 surfaces need the palette to decode, every other format ignores it
+    pub fn decode(
+        &self,
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+        palette: Option<&[u8]>,
+    ) -> Result<Image, Error> {
+        Ok(match self {
+            ImageFormat::DXT1 => Image::DXT1(DXT1::from_bytes(bytes, width, height)?),
+            ImageFormat::DXT2 => Image::DXT2(DXT2::from_bytes(bytes, width, height)?),
+            ImageFormat::DXT3 => Image::DXT3(DXT3::from_bytes(bytes, width, height)?),
+            ImageFormat::DXT4 => Image::DXT4(DXT4::from_bytes(bytes, width, height)?),
+            ImageFormat::DXT5 => Image::DXT5(DXT5::from_bytes(bytes, width, height)?),
+            ImageFormat::Raw(format, layout) => Image::Raw(RawImage::from_bytes(
+                bytes, width, height, *format, *layout,
+            )?),
+            ImageFormat::P8(layout) => {
+                let palette = palette.ok_or(Error::InvalidArgument(
+                    "P8 images require a palette to decode.".to_string(),
+                ))?;
+
+                Image::P8(P8Image::from_bytes(bytes, palette, width, height, *layout)?)
+            }
+        })
+    }
+}
+
+impl ImageCodec for Image {
+    fn format(&self) -> ImageFormat {
+        match self {
+            Image::DXT1(image) => image.format(),
+            Image::DXT2(image) => image.format(),
+            Image::DXT3(image) => image.format(),
+            Image::DXT4(image) => image.format(),
+            Image::DXT5(image) => image.format(),
+            Image::Raw(image) => image.format(),
+            Image::P8(image) => image.format(),
+        }
+    }
+
+    fn width(&self) -> u32 {
+        match self {
+            Image::DXT1(image) => image.width(),
+            Image::DXT2(image) => image.width(),
+            Image::DXT3(image) => image.width(),
+            Image::DXT4(image) => image.width(),
+            Image::DXT5(image) => image.width(),
+            Image::Raw(image) => image.width(),
+            Image::P8(image) => image.width(),
+        }
+    }
+
+    fn height(&self) -> u32 {
+        match self {
+            Image::DXT1(image) => image.height(),
+            Image::DXT2(image) => image.height(),
+            Image::DXT3(image) => image.height(),
+            Image::DXT4(image) => image.height(),
+            Image::DXT5(image) => image.height(),
+            Image::Raw(image) => image.height(),
+            Image::P8(image) => image.height(),
+        }
+    }
+
+    fn decode_rgba_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        match self {
+            Image::DXT1(image) => image.decode_rgba_into(out),
+            Image::DXT2(image) => image.decode_rgba_into(out),
+            Image::DXT3(image) => image.decode_rgba_into(out),
+            Image::DXT4(image) => image.decode_rgba_into(out),
+            Image::DXT5(image) => image.decode_rgba_into(out),
+            Image::Raw(image) => image.decode_rgba_into(out),
+            Image::P8(image) => image.decode_rgba_into(out),
+        }
+    }
+
+    fn encode_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        quality: EncodeQuality,
+    ) -> Result<Image, Error> {
+        Ok(match format {
+            ImageFormat::DXT1 => {
+                Image::DXT1(DXT1::encode_rgba(rgba, width, height, format, quality)?)
+            }
+            ImageFormat::DXT2 => {
+                Image::DXT2(DXT2::encode_rgba(rgba, width, height, format, quality)?)
+            }
+            ImageFormat::DXT3 => {
+                Image::DXT3(DXT3::encode_rgba(rgba, width, height, format, quality)?)
+            }
+            ImageFormat::DXT4 => {
+                Image::DXT4(DXT4::encode_rgba(rgba, width, height, format, quality)?)
+            }
+            ImageFormat::DXT5 => {
+                Image::DXT5(DXT5::encode_rgba(rgba, width, height, format, quality)?)
+            }
+            ImageFormat::Raw(..) => {
+                Image::Raw(RawImage::encode_rgba(rgba, width, height, format, quality)?)
+            }
+            ImageFormat::P8(_) => {
+                Image::P8(P8Image::encode_rgba(rgba, width, height, format, quality)?)
+            }
+        })
+    }
+
+    fn to_surface_bytes(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            Image::DXT1(image) => image.to_surface_bytes(),
+            Image::DXT2(image) => image.to_surface_bytes(),
+            Image::DXT3(image) => image.to_surface_bytes(),
+            Image::DXT4(image) => image.to_surface_bytes(),
+            Image::DXT5(image) => image.to_surface_bytes(),
+            Image::Raw(image) => image.to_surface_bytes(),
+            Image::P8(image) => image.to_surface_bytes(),
+        }
+    }
 }
@@ -0,0 +1,332 @@
+use crate::Error;
+use crate::format::image::{
+    ImageFormat, block,
+    codec::{self, ImageCodec},
+    encoder::{self, EncodeQuality},
+    swizzle::{self, Layout},
+};
+
+pub const MAX_PALETTE_SIZE: usize = 256;
+
+// 8 bit indexed texture. The palette is kept as RGBA8 entries, on disk the Xbox stores palettes as
+// A8R8G8B8 (B G R A in memory).
+#[derive(Debug)]
+pub struct P8Image {
+    width: u32,
+    height: u32,
+
+    palette: Vec<[u8; 4]>,
+
+    // Layout the image was loaded from and will be written back out with
+    layout: Layout,
+
+    // Always kept in linear order, swizzled input is unswizzled on load
+    indices: Vec<u8>,
+}
+
+impl P8Image {
+    pub fn as_rgba_bytes(&self) -> Vec<u8> {
+        self.indices
+            .iter()
+            .flat_map(|&i| self.palette.get(i as usize).copied().unwrap_or_default())
+            .collect()
+    }
+
+    // Decodes into an existing RGBA8 buffer of at least width * height texels
+    pub fn decode_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        block::check_output(out, self.width, self.height)?;
+
+        for (texel, &i) in out.chunks_exact_mut(4).zip(&self.indices) {
+            texel.copy_from_slice(&self.palette.get(i as usize).copied().unwrap_or_default());
+        }
+
+        Ok(())
+    }
+
+    pub fn from_bytes(
+        bytes: &[u8],
+        palette_bytes: &[u8],
+        width: u32,
+        height: u32,
+        layout: Layout,
+    ) -> Result<P8Image, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::invalid_dimensions(
+                width,
+                height,
+                "images need at least one texel",
+            ));
+        }
+
+        codec::rgba_size(width, height)?;
+
+        let bytes_required = ImageFormat::P8(layout).surface_size(width, height);
+
+        if bytes.len() < bytes_required {
+            return Err(Error::insufficient_data(bytes_required, bytes.len()));
+        }
+
+        if palette_bytes.is_empty() || !palette_bytes.len().is_multiple_of(4) {
+            return Err(Error::InvalidData(format!(
+                "Palette must be a non-empty list of 4 byte entries (got {} bytes).",
+                palette_bytes.len()
+            )));
+        }
+
+        let palette = P8Image::palette_from_bytes(palette_bytes);
+
+        let indices = match layout {
+            Layout::Linear => bytes[..bytes_required].to_vec(),
+            Layout::Swizzled => swizzle::unswizzle(bytes, width, height, 1)?,
+        };
+
+        Ok(P8Image {
+            width,
+            height,
+            palette,
+            layout,
+            indices,
+        })
+    }
+
+    // Re-quantises an RGBA image down to at most `palette_size` colours
+    pub fn from_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        palette_size: usize,
+    ) -> Result<P8Image, Error> {
+        encoder::validate_rgba(rgba, width, height)?;
+
+        if palette_size == 0 || palette_size > MAX_PALETTE_SIZE {
+            return Err(Error::InvalidArgument(format!(
+                "Palette size must be between 1 and {} (got {}).",
+                MAX_PALETTE_SIZE, palette_size
+            )));
+        }
+
+        let texels: Vec<[u8; 4]> = rgba[..width as usize * height as usize * 4]
+            .chunks_exact(4)
+            .map(|c| c.try_into().unwrap())
+            .collect();
+
+        let palette = median_cut(&texels, palette_size);
+
+        let mut image = P8Image {
+            width,
+            height,
+            palette,
+            layout: Layout::Linear,
+            indices: Vec::new(),
+        };
+        image.indices = texels.iter().map(|t| image.nearest_index(*t)).collect();
+
+        Ok(image)
+    }
+
+    // Remaps the image onto a caller supplied palette, e.g. one shared with other textures
+    pub fn from_rgba_with_palette(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        palette: Vec<[u8; 4]>,
+    ) -> Result<P8Image, Error> {
+        encoder::validate_rgba(rgba, width, height)?;
+
+        if palette.is_empty() || palette.len() > MAX_PALETTE_SIZE {
+            return Err(Error::InvalidArgument(format!(
+                "Palette size must be between 1 and {} (got {}).",
+                MAX_PALETTE_SIZE,
+                palette.len()
+            )));
+        }
+
+        let mut image = P8Image {
+            width,
+            height,
+            palette,
+            layout: Layout::Linear,
+            indices: Vec::new(),
+        };
+        image.indices = rgba[..width as usize * height as usize * 4]
+            .chunks_exact(4)
+            .map(|t| image.nearest_index(t.try_into().unwrap()))
+            .collect();
+
+        Ok(image)
+    }
+
+    fn nearest_index(&self, texel: [u8; 4]) -> u8 {
+        self.palette
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, entry)| distance_squared(**entry, texel))
+            .map(|(i, _)| i as u8)
+            .unwrap_or_default()
+    }
+
+    pub fn to_bytes(&self, layout: Layout) -> Result<Vec<u8>, Error> {
+        match layout {
+            Layout::Linear => Ok(self.indices.clone()),
+            Layout::Swizzled => swizzle::swizzle(&self.indices, self.width, self.height, 1),
+        }
+    }
+
+    // Converts an on disk A8R8G8B8 palette to RGBA8 entries
+    pub fn palette_from_bytes(palette_bytes: &[u8]) -> Vec<[u8; 4]> {
+        palette_bytes
+            .chunks_exact(4)
+            .take(MAX_PALETTE_SIZE)
+            .map(|c| [c[2], c[1], c[0], c[3]])
+            .collect()
+    }
+
+    pub fn palette_to_bytes(&self) -> Vec<u8> {
+        self.palette
+            .iter()
+            .flat_map(|c| [c[2], c[1], c[0], c[3]])
+            .collect()
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    pub fn palette(&self) -> &[[u8; 4]] {
+        &self.palette
+    }
+
+    // Editing entries in place recolours every texel using them. The number of entries is fixed,
+    // use `from_rgba_with_palette` to remap onto a different palette.
+    pub fn palette_mut(&mut self) -> &mut [[u8; 4]] {
+        &mut self.palette
+    }
+
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+fn distance_squared(a: [u8; 4], b: [u8; 4]) -> u32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(&x, &y)| (x as i32 - y as i32).pow(2) as u32)
+        .sum()
+}
+
+// Median cut quantisation. The box with the widest channel range is repeatedly split at its median
+// until there are enough boxes, and each box contributes the average of its texels.
+fn median_cut(texels: &[[u8; 4]], palette_size: usize) -> Vec<[u8; 4]> {
+    let mut unique = texels.to_vec();
+    unique.sort_unstable();
+    unique.dedup();
+
+    if unique.len() <= palette_size {
+        return unique;
+    }
+
+    let mut boxes: Vec<Vec<[u8; 4]>> = vec![texels.to_vec()];
+
+    while boxes.len() < palette_size {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| (i, widest_channel(b)))
+            .max_by_key(|(_, (_, range))| *range);
+
+        let Some((index, (channel, range))) = widest else {
+            break;
+        };
+
+        if range == 0 {
+            break;
+        }
+
+        let mut texels = boxes.swap_remove(index);
+        texels.sort_unstable_by_key(|t| t[channel]);
+
+        let upper = texels.split_off(texels.len() / 2);
+        boxes.push(texels);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|b| {
+            let mut sum = [0u32; 4];
+            for t in b {
+                for ch in 0..4 {
+                    sum[ch] += t[ch] as u32;
+                }
+            }
+
+            let n = b.len() as u32;
+            sum.map(|s| ((s + n / 2) / n) as u8)
+        })
+        .collect()
+}
+
+// Returns the channel with the largest spread and the size of that spread
+fn widest_channel(texels: &[[u8; 4]]) -> (usize, u8) {
+    (0..4)
+        .map(|ch| {
+            let min = texels.iter().map(|t| t[ch]).min().unwrap_or(0);
+            let max = texels.iter().map(|t| t[ch]).max().unwrap_or(0);
+            (ch, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+// The palette is not part of the surface, see `palette_to_bytes`
+impl ImageCodec for P8Image {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::P8(self.layout)
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn decode_rgba_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        self.decode_into(out)
+    }
+
+    fn encode_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        _quality: EncodeQuality,
+    ) -> Result<P8Image, Error> {
+        let ImageFormat::P8(layout) = format else {
+            return Err(codec::format_mismatch("P8Image", format));
+        };
+
+        let mut image = P8Image::from_rgba(rgba, width, height, MAX_PALETTE_SIZE)?;
+        image.layout = layout;
+
+        Ok(image)
+    }
+
+    fn to_surface_bytes(&self) -> Result<Vec<u8>, Error> {
+        self.to_bytes(self.layout)
+    }
+}
@@ -0,0 +1,305 @@
+use crate::Error;
+use crate::format::image::{
+    ImageFormat, block,
+    codec::{self, ImageCodec},
+    encoder::{self, EncodeQuality},
+    swizzle::{self, Layout},
+};
+
+// Uncompressed D3D8 texture formats. Multi-byte formats are little endian, so A8R8G8B8 is stored
+// B G R A in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    A8R8G8B8,
+    X8R8G8B8,
+    R5G6B5,
+    X1R5G5B5,
+    A1R5G5B5,
+    A4R4G4B4,
+    // Luminance only, decoded as an opaque grey
+    L8,
+    // Alpha only, decoded with black colour
+    A8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::A8R8G8B8 | PixelFormat::X8R8G8B8 => 4,
+            PixelFormat::R5G6B5
+            | PixelFormat::X1R5G5B5
+            | PixelFormat::A1R5G5B5
+            | PixelFormat::A4R4G4B4 => 2,
+            PixelFormat::L8 | PixelFormat::A8 => 1,
+        }
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        matches!(
+            self,
+            PixelFormat::A8R8G8B8 | PixelFormat::A1R5G5B5 | PixelFormat::A4R4G4B4 | PixelFormat::A8
+        )
+    }
+
+    // Decodes a single pixel, `bytes` must hold at least `bytes_per_pixel` bytes
+    pub fn decode_pixel(&self, bytes: &[u8]) -> [u8; 4] {
+        match self {
+            PixelFormat::A8R8G8B8 => [bytes[2], bytes[1], bytes[0], bytes[3]],
+            PixelFormat::X8R8G8B8 => [bytes[2], bytes[1], bytes[0], u8::MAX],
+            PixelFormat::R5G6B5 => {
+                let v = u16::from_le_bytes([bytes[0], bytes[1]]);
+                [expand(v >> 11, 5), expand(v >> 5, 6), expand(v, 5), u8::MAX]
+            }
+            PixelFormat::X1R5G5B5 | PixelFormat::A1R5G5B5 => {
+                let v = u16::from_le_bytes([bytes[0], bytes[1]]);
+                let a = if *self == PixelFormat::A1R5G5B5 {
+                    expand(v >> 15, 1)
+                } else {
+                    u8::MAX
+                };
+
+                [expand(v >> 10, 5), expand(v >> 5, 5), expand(v, 5), a]
+            }
+            PixelFormat::A4R4G4B4 => {
+                let v = u16::from_le_bytes([bytes[0], bytes[1]]);
+                [
+                    expand(v >> 8, 4),
+                    expand(v >> 4, 4),
+                    expand(v, 4),
+                    expand(v >> 12, 4),
+                ]
+            }
+            PixelFormat::L8 => [bytes[0], bytes[0], bytes[0], u8::MAX],
+            PixelFormat::A8 => [0, 0, 0, bytes[0]],
+        }
+    }
+
+    // Encodes a single pixel into `out`, which must hold at least `bytes_per_pixel` bytes
+    pub fn encode_pixel(&self, rgba: [u8; 4], out: &mut [u8]) {
+        let [r, g, b, a] = rgba;
+
+        match self {
+            PixelFormat::A8R8G8B8 => out[..4].copy_from_slice(&[b, g, r, a]),
+            PixelFormat::X8R8G8B8 => out[..4].copy_from_slice(&[b, g, r, u8::MAX]),
+            PixelFormat::R5G6B5 => {
+                let v = (quantise(r, 5) << 11) | (quantise(g, 6) << 5) | quantise(b, 5);
+                out[..2].copy_from_slice(&v.to_le_bytes());
+            }
+            PixelFormat::X1R5G5B5 | PixelFormat::A1R5G5B5 => {
+                let a = if *self == PixelFormat::A1R5G5B5 {
+                    quantise(a, 1)
+                } else {
+                    1
+                };
+
+                let v = (a << 15) | (quantise(r, 5) << 10) | (quantise(g, 5) << 5) | quantise(b, 5);
+                out[..2].copy_from_slice(&v.to_le_bytes());
+            }
+            PixelFormat::A4R4G4B4 => {
+                let v = (quantise(a, 4) << 12)
+                    | (quantise(r, 4) << 8)
+                    | (quantise(g, 4) << 4)
+                    | quantise(b, 4);
+                out[..2].copy_from_slice(&v.to_le_bytes());
+            }
+            PixelFormat::L8 => {
+                // Rec. 601 luma weights
+                out[0] = ((77 * r as u32 + 150 * g as u32 + 29 * b as u32 + 128) >> 8) as u8;
+            }
+            PixelFormat::A8 => out[0] = a,
+        }
+    }
+}
+
+// Expands the low `bits` bits of value to 8 bits by replicating the high bits into the gap
+fn expand(value: u16, bits: u32) -> u8 {
+    let v = (value & ((1 << bits) - 1)) as u32;
+
+    let mut result = 0u32;
+    let mut filled = 0;
+
+    while filled < 8 {
+        result = (result << bits) | v;
+        filled += bits;
+    }
+
+    (result >> (filled - 8)) as u8
+}
+
+fn quantise(value: u8, bits: u32) -> u16 {
+    let max = (1u32 << bits) - 1;
+    ((value as u32 * max + 127) / 255) as u16
+}
+
+#[derive(Debug)]
+pub struct RawImage {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+
+    // Layout the image was loaded from and will be written back out with
+    layout: Layout,
+
+    // Always kept in linear order, swizzled input is unswizzled on load
+    data: Vec<u8>,
+}
+
+impl RawImage {
+    pub fn as_rgba_bytes(&self) -> Vec<u8> {
+        self.data
+            .chunks_exact(self.format.bytes_per_pixel())
+            .flat_map(|c| self.format.decode_pixel(c))
+            .collect()
+    }
+
+    // Decodes into an existing RGBA8 buffer of at least width * height texels
+    pub fn decode_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        block::check_output(out, self.width, self.height)?;
+
+        let pixels = self.data.chunks_exact(self.format.bytes_per_pixel());
+
+        for (texel, pixel) in out.chunks_exact_mut(4).zip(pixels) {
+            texel.copy_from_slice(&self.format.decode_pixel(pixel));
+        }
+
+        Ok(())
+    }
+
+    pub fn from_bytes(
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        layout: Layout,
+    ) -> Result<RawImage, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::invalid_dimensions(
+                width,
+                height,
+                "images need at least one texel",
+            ));
+        }
+
+        codec::rgba_size(width, height)?;
+
+        let bytes_per_pixel = format.bytes_per_pixel();
+        let bytes_required = ImageFormat::Raw(format, layout).surface_size(width, height);
+
+        if bytes.len() < bytes_required {
+            return Err(Error::insufficient_data(bytes_required, bytes.len()));
+        }
+
+        let data = match layout {
+            Layout::Linear => bytes[..bytes_required].to_vec(),
+            Layout::Swizzled => swizzle::unswizzle(bytes, width, height, bytes_per_pixel)?,
+        };
+
+        Ok(RawImage {
+            width,
+            height,
+            format,
+            layout,
+            data,
+        })
+    }
+
+    pub fn from_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+    ) -> Result<RawImage, Error> {
+        encoder::validate_rgba(rgba, width, height)?;
+
+        let pixel_count = width as usize * height as usize;
+
+        let bytes_per_pixel = format.bytes_per_pixel();
+        let mut data = vec![0u8; pixel_count * bytes_per_pixel];
+
+        for (texel, out) in rgba
+            .chunks_exact(4)
+            .zip(data.chunks_exact_mut(bytes_per_pixel))
+        {
+            format.encode_pixel(texel.try_into().unwrap(), out);
+        }
+
+        Ok(RawImage {
+            width,
+            height,
+            format,
+            layout: Layout::Linear,
+            data,
+        })
+    }
+
+    pub fn to_bytes(&self, layout: Layout) -> Result<Vec<u8>, Error> {
+        match layout {
+            Layout::Linear => Ok(self.data.clone()),
+            Layout::Swizzled => swizzle::swizzle(
+                &self.data,
+                self.width,
+                self.height,
+                self.format.bytes_per_pixel(),
+            ),
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl ImageCodec for RawImage {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::Raw(self.format, self.layout)
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn decode_rgba_into(&self, out: &mut [u8]) -> Result<(), Error> {
+        self.decode_into(out)
+    }
+
+    fn encode_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        _quality: EncodeQuality,
+    ) -> Result<RawImage, Error> {
+        let ImageFormat::Raw(pixel_format, layout) = format else {
+            return Err(codec::format_mismatch("RawImage", format));
+        };
+
+        let mut image = RawImage::from_rgba(rgba, width, height, pixel_format)?;
+        image.layout = layout;
+
+        Ok(image)
+    }
+
+    fn to_surface_bytes(&self) -> Result<Vec<u8>, Error> {
+        self.to_bytes(self.layout)
+    }
+}
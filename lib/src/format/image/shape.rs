@@ -0,0 +1,81 @@
+// Works out what kind of texture a block of surfaces holds when all that is known is its format,
+// the size of the top level and how many bytes there are. Containers that don't record whether a
+// texture is a cube map or volume can still be viewed whole this way.
+
+use crate::format::image::{ImageFormat, cube, mip::MipChain, volume::VolumeTexture};
+
+// Largest depth looked for, as in D3D
+const MAX_DEPTH: u32 = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureShape {
+    Plain { level_count: u32 },
+    Cube { level_count: u32 },
+    Volume { depth: u32, level_count: u32 },
+}
+
+impl TextureShape {
+    // Only exact matches count. Plain textures win over the other shapes, and volumes need power
+    // of two sides like every Xbox volume has, so a texture of an unexpected size isn't mistaken
+    // for a stack of slices. None if nothing fits.
+    pub fn from_size(
+        format: ImageFormat,
+        width: u32,
+        height: u32,
+        size: usize,
+    ) -> Option<TextureShape> {
+        if width == 0 || height == 0 || size == 0 {
+            return None;
+        }
+
+        let chain_size = |level_count: u32| -> usize {
+            (0..level_count)
+                .map(|level| {
+                    let (level_width, level_height) =
+                        MipChain::level_dimensions(width, height, level);
+                    format.surface_size(level_width, level_height)
+                })
+                .sum()
+        };
+
+        let level_counts = 1..=MipChain::full_level_count(width, height);
+
+        if let Some(level_count) = level_counts
+            .clone()
+            .find(|&level_count| chain_size(level_count) == size)
+        {
+            return Some(TextureShape::Plain { level_count });
+        }
+
+        if width == height
+            && let Some(level_count) = level_counts
+                .clone()
+                .find(|&level_count| chain_size(level_count) * cube::FACE_COUNT == size)
+        {
+            return Some(TextureShape::Cube { level_count });
+        }
+
+        if !width.is_power_of_two() || !height.is_power_of_two() {
+            return None;
+        }
+
+        let volume_size = |depth: u32, level_count: u32| -> usize {
+            (0..level_count)
+                .map(|level| {
+                    let (level_width, level_height, level_depth) =
+                        VolumeTexture::level_dimensions(width, height, depth, level);
+                    format.surface_size(level_width, level_height) * level_depth as usize
+                })
+                .sum()
+        };
+
+        (1..=MAX_DEPTH.ilog2())
+            .map(|shift| 1 << shift)
+            .take_while(|&depth| format.surface_size(width, height) * depth as usize <= size)
+            .find_map(|depth| {
+                (1..=VolumeTexture::full_level_count(width, height, depth))
+                    .find(|&level_count| volume_size(depth, level_count) == size)
+                    .map(|level_count| TextureShape::Volume { depth, level_count })
+            })
+    }
+}
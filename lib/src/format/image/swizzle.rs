@@ -0,0 +1,266 @@
+// Xbox textures in linear (uncompressed) formats are stored swizzled, with texels laid out in
+// Morton (Z-order) so that neighbouring texels in both directions are close in memory. The bits of
+// the x and y coordinates are interleaved, x first, until the smaller dimension runs out of bits,
+// after which the remaining bits of the larger dimension are used as is. Volume textures are
+// swizzled the same way in three dimensions, with the z bits interleaved after x and y.
+
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    #[default]
+    Linear,
+    Swizzled,
+}
+
+// Returns the bit masks that the x and y coordinates are deposited into
+fn masks(width: u32, height: u32) -> (u32, u32) {
+    let (mask_x, mask_y, _) = masks_3d(width, height, 1);
+
+    (mask_x, mask_y)
+}
+
+// As `masks`, with a third mask for the z coordinate
+fn masks_3d(width: u32, height: u32, depth: u32) -> (u32, u32, u32) {
+    let mut mask_x = 0u32;
+    let mut mask_y = 0u32;
+    let mut mask_z = 0u32;
+
+    let mut bit = 1u32;
+    let mut mask_bit = 1u32;
+
+    while bit < width || bit < height || bit < depth {
+        if bit < width {
+            mask_x |= mask_bit;
+            mask_bit <<= 1;
+        }
+
+        if bit < height {
+            mask_y |= mask_bit;
+            mask_bit <<= 1;
+        }
+
+        if bit < depth {
+            mask_z |= mask_bit;
+            mask_bit <<= 1;
+        }
+
+        bit <<= 1;
+    }
+
+    (mask_x, mask_y, mask_z)
+}
+
+// Spreads the low bits of value out over the set bits of mask
+fn deposit(mut value: u32, mask: u32) -> u32 {
+    let mut result = 0u32;
+    let mut remaining = mask;
+
+    while remaining != 0 {
+        let lowest = remaining & remaining.wrapping_neg();
+
+        if value & 1 != 0 {
+            result |= lowest;
+        }
+
+        value >>= 1;
+        remaining &= remaining - 1;
+    }
+
+    result
+}
+
+fn validate(bytes: &[u8], width: u32, height: u32, bytes_per_pixel: usize) -> Result<usize, Error> {
+    validate_3d(bytes, width, height, 1, bytes_per_pixel)
+}
+
+fn validate_3d(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    depth: u32,
+    bytes_per_pixel: usize,
+) -> Result<usize, Error> {
+    if !width.is_power_of_two() || !height.is_power_of_two() || !depth.is_power_of_two() {
+        return Err(Error::InvalidDimensions {
+            width,
+            height,
+            depth,
+            reason: "swizzled textures must have power of two dimensions",
+        });
+    }
+
+    let bytes_required = (width as usize)
+        .saturating_mul(height as usize)
+        .saturating_mul(depth as usize)
+        .saturating_mul(bytes_per_pixel);
+
+    if bytes.len() < bytes_required {
+        return Err(Error::insufficient_data(bytes_required, bytes.len()));
+    }
+
+    Ok(bytes_required)
+}
+
+// Index of the texel at (x, y) within a swizzled texture
+pub fn swizzled_index(x: u32, y: u32, width: u32, height: u32) -> usize {
+    let (mask_x, mask_y) = masks(width, height);
+
+    (deposit(x, mask_x) | deposit(y, mask_y)) as usize
+}
+
+// Index of the texel at (x, y, z) within a swizzled volume
+pub fn swizzled_index_3d(x: u32, y: u32, z: u32, width: u32, height: u32, depth: u32) -> usize {
+    let (mask_x, mask_y, mask_z) = masks_3d(width, height, depth);
+
+    (deposit(x, mask_x) | deposit(y, mask_y) | deposit(z, mask_z)) as usize
+}
+
+pub fn swizzle(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    bytes_per_pixel: usize,
+) -> Result<Vec<u8>, Error> {
+    let size = validate(linear, width, height, bytes_per_pixel)?;
+    let (mask_x, mask_y) = masks(width, height);
+
+    let mut swizzled = vec![0u8; size];
+
+    for y in 0..height {
+        let offset_y = deposit(y, mask_y);
+
+        for x in 0..width {
+            let src = (y as usize * width as usize + x as usize) * bytes_per_pixel;
+            let dst = (deposit(x, mask_x) | offset_y) as usize * bytes_per_pixel;
+
+            swizzled[dst..dst + bytes_per_pixel]
+                .copy_from_slice(&linear[src..src + bytes_per_pixel]);
+        }
+    }
+
+    Ok(swizzled)
+}
+
+pub fn unswizzle(
+    swizzled: &[u8],
+    width: u32,
+    height: u32,
+    bytes_per_pixel: usize,
+) -> Result<Vec<u8>, Error> {
+    let size = validate(swizzled, width, height, bytes_per_pixel)?;
+    let (mask_x, mask_y) = masks(width, height);
+
+    let mut linear = vec![0u8; size];
+
+    for y in 0..height {
+        let offset_y = deposit(y, mask_y);
+
+        for x in 0..width {
+            let src = (deposit(x, mask_x) | offset_y) as usize * bytes_per_pixel;
+            let dst = (y as usize * width as usize + x as usize) * bytes_per_pixel;
+
+            linear[dst..dst + bytes_per_pixel]
+                .copy_from_slice(&swizzled[src..src + bytes_per_pixel]);
+        }
+    }
+
+    Ok(linear)
+}
+
+// Converts a surface between layouts, copying it unchanged when they already match
+pub fn relayout(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    bytes_per_pixel: usize,
+    from: Layout,
+    to: Layout,
+) -> Result<Vec<u8>, Error> {
+    match (from, to) {
+        (Layout::Linear, Layout::Swizzled) => swizzle(bytes, width, height, bytes_per_pixel),
+        (Layout::Swizzled, Layout::Linear) => unswizzle(bytes, width, height, bytes_per_pixel),
+        _ => Ok(bytes.to_vec()),
+    }
+}
+
+// Linear volumes hold their slices back to back, each in row order
+pub fn swizzle_3d(
+    linear: &[u8],
+    width: u32,
+    height: u32,
+    depth: u32,
+    bytes_per_pixel: usize,
+) -> Result<Vec<u8>, Error> {
+    let size = validate_3d(linear, width, height, depth, bytes_per_pixel)?;
+    let mut swizzled = vec![0u8; size];
+
+    for_each_texel_3d(width, height, depth, |linear_index, swizzled_index| {
+        let src = linear_index * bytes_per_pixel;
+        let dst = swizzled_index * bytes_per_pixel;
+
+        swizzled[dst..dst + bytes_per_pixel].copy_from_slice(&linear[src..src + bytes_per_pixel]);
+    });
+
+    Ok(swizzled)
+}
+
+pub fn unswizzle_3d(
+    swizzled: &[u8],
+    width: u32,
+    height: u32,
+    depth: u32,
+    bytes_per_pixel: usize,
+) -> Result<Vec<u8>, Error> {
+    let size = validate_3d(swizzled, width, height, depth, bytes_per_pixel)?;
+    let mut linear = vec![0u8; size];
+
+    for_each_texel_3d(width, height, depth, |linear_index, swizzled_index| {
+        let src = swizzled_index * bytes_per_pixel;
+        let dst = linear_index * bytes_per_pixel;
+
+        linear[dst..dst + bytes_per_pixel].copy_from_slice(&swizzled[src..src + bytes_per_pixel]);
+    });
+
+    Ok(linear)
+}
+
+// Calls `f` with the linear and swizzled index of every texel
+fn for_each_texel_3d(width: u32, height: u32, depth: u32, mut f: impl FnMut(usize, usize)) {
+    let (mask_x, mask_y, mask_z) = masks_3d(width, height, depth);
+    let mut linear_index = 0;
+
+    for z in 0..depth {
+        let offset_z = deposit(z, mask_z);
+
+        for y in 0..height {
+            let offset_yz = deposit(y, mask_y) | offset_z;
+
+            for x in 0..width {
+                f(linear_index, (deposit(x, mask_x) | offset_yz) as usize);
+                linear_index += 1;
+            }
+        }
+    }
+}
+
+// `relayout` for a whole volume
+pub fn relayout_3d(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    depth: u32,
+    bytes_per_pixel: usize,
+    from: Layout,
+    to: Layout,
+) -> Result<Vec<u8>, Error> {
+    match (from, to) {
+        (Layout::Linear, Layout::Swizzled) => {
+            swizzle_3d(bytes, width, height, depth, bytes_per_pixel)
+        }
+        (Layout::Swizzled, Layout::Linear) => {
+            unswizzle_3d(bytes, width, height, depth, bytes_per_pixel)
+        }
+        _ => Ok(bytes.to_vec()),
+    }
+}
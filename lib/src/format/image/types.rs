@@ -1,16 +1,16 @@
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct R5G6B5Colour {
     data: u16,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct RGBColour {
     pub r: u8,
     pub g: u8,
     pub b: u8,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct RGBAColour {
     pub r: u8,
     pub g: u8,
@@ -18,6 +18,43 @@ pub struct RGBAColour {
     pub a: u8,
 }
 
+// Packed as RRRRRGGG GGGBBBBB, red in the high bits
+impl R5G6B5Colour {
+    // DXT blocks and D3D surfaces store colours little endian
+    pub fn from_le(bytes: [u8; 2]) -> R5G6B5Colour {
+        u16::from_le_bytes(bytes).into()
+    }
+
+    pub fn from_be(bytes: [u8; 2]) -> R5G6B5Colour {
+        u16::from_be_bytes(bytes).into()
+    }
+
+    pub fn to_le_bytes(&self) -> [u8; 2] {
+        self.data.to_le_bytes()
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 2] {
+        self.data.to_be_bytes()
+    }
+
+    pub fn raw(&self) -> u16 {
+        self.data
+    }
+
+    // Channels at their stored precision
+    pub fn r(&self) -> u8 {
+        ((self.data >> 11) & 0b11111) as u8
+    }
+
+    pub fn g(&self) -> u8 {
+        ((self.data >> 5) & 0b111111) as u8
+    }
+
+    pub fn b(&self) -> u8 {
+        (self.data & 0b11111) as u8
+    }
+}
+
 impl From<u16> for R5G6B5Colour {
     fn from(value: u16) -> Self {
         R5G6B5Colour { data: value }
@@ -26,20 +63,31 @@ impl From<u16> for R5G6B5Colour {
 
 impl From<R5G6B5Colour> for RGBColour {
     fn from(colour: R5G6B5Colour) -> Self {
-        // In its proper format,
-        // RRRRRGGG GGGBBBBB
-        //
-        // In little endian on disk as a u16,
-        // GGGBBBBB RRRRRGGG
-
-        let r_raw = (colour.data >> 3) & 0b11111;
-        let g_raw = (colour.data << 3) & 0b111111;
-        let b_raw = (colour.data >> 8) & 0b11111;
+        // Replicating the top bits into the bottom maps 0 to 0 and the maximum to 255, the same
+        // expansion D3D uses
+        let r = colour.r();
+        let g = colour.g();
+        let b = colour.b();
 
         RGBColour {
-            r: (r_raw * 255 / 31) as u8,
-            g: (g_raw * 255 / 31) as u8,
-            b: (b_raw * 255 / 31) as u8,
+            r: (r << 3) | (r >> 2),
+            g: (g << 2) | (g >> 4),
+            b: (b << 3) | (b >> 2),
+        }
+    }
+}
+
+// Rounds each channel to the nearest representable value
+impl From<RGBColour> for R5G6B5Colour {
+    fn from(colour: RGBColour) -> Self {
+        let quantise = |value: u8, max: u16| (value as u16 * max + 127) / 255;
+
+        let r = quantise(colour.r, 31);
+        let g = quantise(colour.g, 63);
+        let b = quantise(colour.b, 31);
+
+        R5G6B5Colour {
+            data: (r << 11) | (g << 5) | b,
         }
     }
 }
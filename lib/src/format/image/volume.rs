@@ -0,0 +1,372 @@
+use crate::Error;
+use crate::format::image::{
+    Image, ImageFormat,
+    codec::ImageCodec,
+    encoder::{self, EncodeQuality},
+    mip::{self, MipChain, MipFilter},
+    p8::{MAX_PALETTE_SIZE, P8Image},
+    swizzle::{self, Layout},
+};
+
+// A stack of equally sized slices. Every mip level halves the depth as well as the width and
+// height, and holds all of its slices back to back. The Xbox swizzles volumes in three dimensions
+// at once, so a swizzled slice isn't contiguous. Levels are unswizzled on load and swizzled again
+// by `to_bytes`.
+#[derive(Debug)]
+pub struct VolumeTexture {
+    // Including the layout the volume was loaded from and will be written back out with
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    depth: u32,
+
+    // Only used by P8 textures, where every slice of every level shares one palette
+    palette: Option<Vec<u8>>,
+
+    // Always kept in linear order
+    levels: Vec<Vec<u8>>,
+}
+
+impl VolumeTexture {
+    pub fn full_level_count(width: u32, height: u32, depth: u32) -> u32 {
+        MipChain::full_level_count(width.max(depth), height)
+    }
+
+    pub fn level_dimensions(width: u32, height: u32, depth: u32, level: u32) -> (u32, u32, u32) {
+        let (level_width, level_height) = MipChain::level_dimensions(width, height, level);
+
+        (
+            level_width,
+            level_height,
+            depth.checked_shr(level).unwrap_or(0).max(1),
+        )
+    }
+
+    pub fn from_bytes(
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: ImageFormat,
+        level_count: u32,
+    ) -> Result<VolumeTexture, Error> {
+        if width == 0 || height == 0 || depth == 0 {
+            return Err(Error::InvalidDimensions {
+                width,
+                height,
+                depth,
+                reason: "volumes need at least one texel",
+            });
+        }
+
+        VolumeTexture::validate_level_count(width, height, depth, level_count)?;
+
+        let mut levels = Vec::with_capacity(level_count as usize);
+        let mut offset = 0usize;
+
+        for level in 0..level_count {
+            let (level_width, level_height, level_depth) =
+                VolumeTexture::level_dimensions(width, height, depth, level);
+            let size = format
+                .surface_size(level_width, level_height)
+                .saturating_mul(level_depth as usize);
+
+            let level_bytes =
+                bytes
+                    .get(offset..offset.saturating_add(size))
+                    .ok_or(Error::insufficient_data(
+                        offset.saturating_add(size),
+                        bytes.len(),
+                    ))?;
+
+            levels.push(swizzle::relayout_3d(
+                level_bytes,
+                level_width,
+                level_height,
+                level_depth,
+                format.block_size(),
+                format.layout(),
+                Layout::Linear,
+            )?);
+            offset += size;
+        }
+
+        Ok(VolumeTexture {
+            format,
+            width,
+            height,
+            depth,
+            palette: None,
+            levels,
+        })
+    }
+
+    // `rgba` holds every slice of the full size level stacked top to bottom, so it is `width` wide
+    // and `height * depth` high. Smaller levels are filtered within each slice, then neighbouring
+    // slices are averaged.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_rgba(
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: ImageFormat,
+        level_count: u32,
+        filter: MipFilter,
+        quality: EncodeQuality,
+    ) -> Result<VolumeTexture, Error> {
+        if depth == 0 {
+            return Err(Error::InvalidDimensions {
+                width,
+                height,
+                depth,
+                reason: "volumes need at least one slice",
+            });
+        }
+
+        let stacked_height = height.checked_mul(depth).ok_or(Error::InvalidDimensions {
+            width,
+            height,
+            depth,
+            reason: "too many slices to stack",
+        })?;
+
+        encoder::validate_rgba(rgba, width, stacked_height)?;
+        VolumeTexture::validate_level_count(width, height, depth, level_count)?;
+
+        let slice_bytes = width as usize * height as usize * 4;
+
+        let mut images: Vec<Vec<Vec<u8>>> = vec![
+            rgba[..slice_bytes * depth as usize]
+                .chunks_exact(slice_bytes)
+                .map(|slice| slice.to_vec())
+                .collect(),
+        ];
+
+        for level in 1..level_count {
+            let (src_width, src_height, _) =
+                VolumeTexture::level_dimensions(width, height, depth, level - 1);
+            let (dst_width, dst_height, dst_depth) =
+                VolumeTexture::level_dimensions(width, height, depth, level);
+
+            let resized: Vec<Vec<u8>> = images[level as usize - 1]
+                .iter()
+                .map(|slice| {
+                    mip::downsample(slice, src_width, src_height, dst_width, dst_height, filter)
+                })
+                .collect();
+
+            // Box filter across slices, an odd last slice is averaged with itself
+            let slices = (0..dst_depth as usize)
+                .map(|i| {
+                    let a = &resized[(2 * i).min(resized.len() - 1)];
+                    let b = &resized[(2 * i + 1).min(resized.len() - 1)];
+
+                    a.iter()
+                        .zip(b)
+                        .map(|(&a, &b)| (a as u32 + b as u32).div_ceil(2) as u8)
+                        .collect()
+                })
+                .collect();
+
+            images.push(slices);
+        }
+
+        // A palette chosen from every slice of the full size level
+        let palette = match format {
+            ImageFormat::P8(_) => Some(
+                P8Image::from_rgba(rgba, width, stacked_height, MAX_PALETTE_SIZE)?
+                    .palette_to_bytes(),
+            ),
+            _ => None,
+        };
+
+        let linear_format = format.with_layout(Layout::Linear);
+        let mut levels = Vec::with_capacity(level_count as usize);
+
+        for (level, slices) in images.iter().enumerate() {
+            let (level_width, level_height, _) =
+                VolumeTexture::level_dimensions(width, height, depth, level as u32);
+
+            let mut bytes = Vec::new();
+
+            for slice in slices {
+                let mut slice_bytes = match (format, &palette) {
+                    (ImageFormat::P8(_), Some(palette)) => P8Image::from_rgba_with_palette(
+                        slice,
+                        level_width,
+                        level_height,
+                        P8Image::palette_from_bytes(palette),
+                    )?
+                    .to_bytes(Layout::Linear)?,
+                    _ => Image::encode_rgba(
+                        slice,
+                        level_width,
+                        level_height,
+                        linear_format,
+                        quality,
+                    )?
+                    .to_surface_bytes()?,
+                };
+
+                bytes.append(&mut slice_bytes);
+            }
+
+            levels.push(bytes);
+        }
+
+        let volume = VolumeTexture {
+            format: linear_format,
+            width,
+            height,
+            depth,
+            palette,
+            levels,
+        };
+
+        // Checks the dimensions can be swizzled
+        volume.with_layout(format.layout())
+    }
+
+    fn with_layout(mut self, layout: Layout) -> Result<VolumeTexture, Error> {
+        self.set_layout(layout)?;
+        Ok(self)
+    }
+
+    fn validate_level_count(
+        width: u32,
+        height: u32,
+        depth: u32,
+        level_count: u32,
+    ) -> Result<(), Error> {
+        if level_count == 0 || level_count > VolumeTexture::full_level_count(width, height, depth) {
+            return Err(Error::InvalidLevelCount {
+                level_count,
+                max: VolumeTexture::full_level_count(width, height, depth),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn slice_bytes(&self, slice: u32, level: u32) -> Option<&[u8]> {
+        let (width, height, depth) =
+            VolumeTexture::level_dimensions(self.width, self.height, self.depth, level);
+
+        if slice >= depth {
+            return None;
+        }
+
+        let size = self.format.surface_size(width, height);
+        let offset = slice as usize * size;
+
+        self.levels
+            .get(level as usize)
+            .and_then(|bytes| bytes.get(offset..offset + size))
+    }
+
+    pub fn decode_slice(&self, slice: u32, level: u32) -> Result<Image, Error> {
+        let bytes = self
+            .slice_bytes(slice, level)
+            .ok_or(Error::SurfaceOutOfRange {
+                index: slice,
+                level,
+            })?;
+
+        let (width, height, _) =
+            VolumeTexture::level_dimensions(self.width, self.height, self.depth, level);
+
+        self.format
+            .decode(bytes, width, height, self.palette.as_deref())
+    }
+
+    // Every slice of one level decoded and stacked top to bottom
+    pub fn to_rgba(&self, level: u32) -> Result<(Vec<u8>, u32, u32), Error> {
+        let (width, height, depth) =
+            VolumeTexture::level_dimensions(self.width, self.height, self.depth, level);
+
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * depth as usize * 4);
+
+        for slice in 0..depth {
+            rgba.append(&mut self.decode_slice(slice, level)?.decode_rgba());
+        }
+
+        Ok((rgba, width, height * depth))
+    }
+
+    // Only changes the layout `to_bytes` writes, since levels are always kept linear. Block
+    // compressed volumes are never swizzled.
+    pub fn set_layout(&mut self, layout: Layout) -> Result<(), Error> {
+        if self.format.is_block_compressed() || self.format.layout() == layout {
+            return Ok(());
+        }
+
+        // Every smaller level has power of two sides too
+        if layout == Layout::Swizzled
+            && !(self.width.is_power_of_two()
+                && self.height.is_power_of_two()
+                && self.depth.is_power_of_two())
+        {
+            return Err(Error::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+                depth: self.depth,
+                reason: "swizzled textures must have power of two dimensions",
+            });
+        }
+
+        self.format = self.format.with_layout(layout);
+
+        Ok(())
+    }
+
+    // Every level in the volume's layout
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+
+        for (level, level_bytes) in self.levels.iter().enumerate() {
+            let (width, height, depth) =
+                VolumeTexture::level_dimensions(self.width, self.height, self.depth, level as u32);
+
+            bytes.append(&mut swizzle::relayout_3d(
+                level_bytes,
+                width,
+                height,
+                depth,
+                self.format.block_size(),
+                Layout::Linear,
+                self.format.layout(),
+            )?);
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn level_count(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    pub fn palette(&self) -> Option<&[u8]> {
+        self.palette.as_deref()
+    }
+
+    pub fn set_palette(&mut self, palette: Vec<u8>) {
+        self.palette = Some(palette);
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+}
@@ -1,2 +1,5 @@
+mod error;
 pub mod file;
 pub mod format;
+
+pub use error::Error;
@@ -0,0 +1,204 @@
+// Helpers shared by the integration tests. Not every test uses every helper.
+#![allow(dead_code)]
+
+use anyxplore::format::image::ImageFormat;
+
+// A straightforward decoder written from the format descriptions, kept independent of the
+// library so the two can be checked against each other. Returns straight alpha RGBA8.
+pub fn reference_decode(format: ImageFormat, bytes: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let block_size = match format {
+        ImageFormat::DXT1 => 8,
+        _ => 16,
+    };
+
+    let blocks_x = width.div_ceil(4) as usize;
+    let mut rgba = vec![0u8; width as usize * height as usize * 4];
+
+    for (i, block) in bytes.chunks_exact(block_size).enumerate() {
+        let (block_x, block_y) = (i % blocks_x, i / blocks_x);
+
+        let texels = match format {
+            ImageFormat::DXT1 => reference_colour(block, true),
+            _ => {
+                let mut texels = reference_colour(&block[8..16], false);
+
+                let alpha = match format {
+                    ImageFormat::DXT2 | ImageFormat::DXT3 => reference_explicit_alpha(block),
+                    _ => reference_interpolated_alpha(block),
+                };
+
+                for (texel, alpha) in texels.iter_mut().zip(alpha) {
+                    texel[3] = alpha;
+                }
+
+                if matches!(format, ImageFormat::DXT2 | ImageFormat::DXT4) {
+                    for texel in texels.iter_mut() {
+                        *texel = reference_unpremultiply(*texel);
+                    }
+                }
+
+                texels
+            }
+        };
+
+        for (j, texel) in texels.iter().enumerate() {
+            let x = block_x * 4 + j % 4;
+            let y = block_y * 4 + j / 4;
+
+            if x < width as usize && y < height as usize {
+                let offset = (y * width as usize + x) * 4;
+                rgba[offset..offset + 4].copy_from_slice(texel);
+            }
+        }
+    }
+
+    rgba
+}
+
+fn expand(value: u16, bits: u32) -> u8 {
+    let value = value as u32;
+    ((value << (8 - bits)) | (value >> (2 * bits - 8))) as u8
+}
+
+fn reference_colour(block: &[u8], dxt1: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+
+    let rgb = |c: u16| -> [u32; 3] {
+        [
+            expand(c >> 11, 5) as u32,
+            expand((c >> 5) & 0x3F, 6) as u32,
+            expand(c & 0x1F, 5) as u32,
+        ]
+    };
+
+    let (a, b) = (rgb(c0), rgb(c1));
+
+    let palette: [[u8; 4]; 4] = if !dxt1 || c0 > c1 {
+        [
+            [a[0] as u8, a[1] as u8, a[2] as u8, 255],
+            [b[0] as u8, b[1] as u8, b[2] as u8, 255],
+            [
+                ((2 * a[0] + b[0]) / 3) as u8,
+                ((2 * a[1] + b[1]) / 3) as u8,
+                ((2 * a[2] + b[2]) / 3) as u8,
+                255,
+            ],
+            [
+                ((a[0] + 2 * b[0]) / 3) as u8,
+                ((a[1] + 2 * b[1]) / 3) as u8,
+                ((a[2] + 2 * b[2]) / 3) as u8,
+                255,
+            ],
+        ]
+    } else {
+        [
+            [a[0] as u8, a[1] as u8, a[2] as u8, 255],
+            [b[0] as u8, b[1] as u8, b[2] as u8, 255],
+            [
+                ((a[0] + b[0]) / 2) as u8,
+                ((a[1] + b[1]) / 2) as u8,
+                ((a[2] + b[2]) / 2) as u8,
+                255,
+            ],
+            [0, 0, 0, 0],
+        ]
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    std::array::from_fn(|i| palette[((indices >> (2 * i)) & 3) as usize])
+}
+
+fn reference_explicit_alpha(block: &[u8]) -> [u8; 16] {
+    std::array::from_fn(|i| {
+        let byte = block[i / 2];
+        let nibble = if i % 2 == 0 { byte & 0xF } else { byte >> 4 };
+        nibble * 17
+    })
+}
+
+fn reference_interpolated_alpha(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+
+    let palette: [u8; 8] = if a0 > a1 {
+        std::array::from_fn(|i| match i {
+            0 => a0 as u8,
+            1 => a1 as u8,
+            _ => (((8 - i as u32) * a0 + (i as u32 - 1) * a1) / 7) as u8,
+        })
+    } else {
+        std::array::from_fn(|i| match i {
+            0 => a0 as u8,
+            1 => a1 as u8,
+            6 => 0,
+            7 => 255,
+            _ => (((6 - i as u32) * a0 + (i as u32 - 1) * a1) / 5) as u8,
+        })
+    };
+
+    let mut indices = 0u64;
+    for (i, &byte) in block[2..8].iter().enumerate() {
+        indices |= (byte as u64) << (8 * i);
+    }
+
+    std::array::from_fn(|i| palette[((indices >> (3 * i)) & 7) as usize])
+}
+
+fn reference_unpremultiply(texel: [u8; 4]) -> [u8; 4] {
+    let a = texel[3] as u32;
+
+    if a == 0 || a == 255 {
+        return texel;
+    }
+
+    let c = |c: u8| ((c as u32 * 255 + a / 2) / a).min(255) as u8;
+
+    [c(texel[0]), c(texel[1]), c(texel[2]), texel[3]]
+}
+
+// Small deterministic generator so failures reproduce without any extra dependencies
+pub struct XorShift(u32);
+
+impl XorShift {
+    pub fn new(seed: u32) -> XorShift {
+        XorShift(seed.max(1))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Vec<u8> {
+        (0..count).map(|_| self.next_u32() as u8).collect()
+    }
+}
+
+// Largest difference between any two corresponding bytes
+pub fn max_error(a: &[u8], b: &[u8]) -> u8 {
+    assert_eq!(a.len(), b.len());
+
+    a.iter()
+        .zip(b)
+        .map(|(&a, &b)| a.abs_diff(b))
+        .max()
+        .unwrap_or(0)
+}
+
+// Largest difference in each channel, over every texel
+pub fn max_channel_error(a: &[u8], b: &[u8]) -> [u8; 4] {
+    assert_eq!(a.len(), b.len());
+
+    let mut error = [0u8; 4];
+
+    for (a, b) in a.chunks_exact(4).zip(b.chunks_exact(4)) {
+        for ch in 0..4 {
+            error[ch] = error[ch].max(a[ch].abs_diff(b[ch]));
+        }
+    }
+
+    error
+}
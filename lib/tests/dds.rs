@@ -0,0 +1,170 @@
+use anyxplore::{
+    Error,
+    format::{
+        dds::{DDS, HeaderVersion, TextureKind},
+        image::{ImageFormat, mip::MipChain, raw::PixelFormat, swizzle::Layout},
+    },
+};
+
+// Offset of the DXGI format, right after the magic and the legacy header
+const DXGI_FORMAT_OFFSET: usize = 4 + 124;
+
+// A texture whose surfaces are filled with a counting pattern, so any byte moved or dropped shows
+fn texture(format: ImageFormat, kind: TextureKind, width: u32, height: u32, depth: u32) -> DDS {
+    let level_count = 2;
+
+    let size: usize = (0..level_count)
+        .map(|level| {
+            let (level_width, level_height) = MipChain::level_dimensions(width, height, level);
+            let surfaces = match kind {
+                TextureKind::Texture2D => 1,
+                TextureKind::Cube => 6,
+                TextureKind::Volume => depth.checked_shr(level).unwrap_or(0).max(1) as usize,
+            };
+
+            format.surface_size(level_width, level_height) * surfaces
+        })
+        .sum();
+
+    let palette =
+        matches!(format, ImageFormat::P8(_)).then(|| (0..1024).map(|i| (i * 3) as u8).collect());
+
+    DDS::new(
+        format,
+        kind,
+        width,
+        height,
+        depth,
+        level_count,
+        palette,
+        (0..size).map(|i| (i * 7 + i / 256) as u8).collect(),
+    )
+    .unwrap()
+}
+
+// Reading a file and writing it straight back must give the same bytes
+fn assert_round_trip(bytes: &[u8]) -> DDS {
+    let dds = DDS::from_bytes(bytes).unwrap();
+    assert_eq!(dds.to_bytes().unwrap(), bytes, "{:?}", dds.format());
+
+    dds
+}
+
+fn textures() -> Vec<DDS> {
+    vec![
+        texture(ImageFormat::DXT1, TextureKind::Texture2D, 8, 8, 1),
+        texture(ImageFormat::DXT2, TextureKind::Texture2D, 12, 4, 1),
+        texture(ImageFormat::DXT3, TextureKind::Texture2D, 4, 8, 1),
+        texture(ImageFormat::DXT4, TextureKind::Texture2D, 8, 4, 1),
+        texture(ImageFormat::DXT5, TextureKind::Cube, 8, 8, 1),
+        texture(
+            ImageFormat::Raw(PixelFormat::A8R8G8B8, Layout::Linear),
+            TextureKind::Texture2D,
+            5,
+            3,
+            1,
+        ),
+        texture(
+            ImageFormat::Raw(PixelFormat::R5G6B5, Layout::Linear),
+            TextureKind::Cube,
+            4,
+            4,
+            1,
+        ),
+        texture(
+            ImageFormat::Raw(PixelFormat::L8, Layout::Linear),
+            TextureKind::Volume,
+            4,
+            4,
+            4,
+        ),
+    ]
+}
+
+#[test]
+fn legacy_round_trip() {
+    let mut textures = textures();
+    textures.push(texture(
+        ImageFormat::P8(Layout::Linear),
+        TextureKind::Texture2D,
+        4,
+        4,
+        1,
+    ));
+
+    for original in textures {
+        let dds = assert_round_trip(&original.to_bytes().unwrap());
+
+        assert_eq!(dds.header_version(), HeaderVersion::Legacy);
+        assert!(!dds.is_srgb());
+        assert_eq!(dds.format(), original.format());
+        assert_eq!(dds.kind(), original.kind());
+        assert_eq!(dds.palette(), original.palette());
+        assert_eq!(dds.data(), original.data());
+    }
+}
+
+#[test]
+fn dx10_round_trip() {
+    for mut original in textures() {
+        original.set_header_version(HeaderVersion::DX10);
+
+        let dds = assert_round_trip(&original.to_bytes().unwrap());
+
+        assert_eq!(dds.header_version(), HeaderVersion::DX10);
+        assert!(!dds.is_srgb());
+        assert_eq!(dds.format(), original.format());
+        assert_eq!(dds.kind(), original.kind());
+        assert_eq!(dds.data(), original.data());
+    }
+}
+
+#[test]
+fn srgb_round_trip() {
+    let cases = [
+        (ImageFormat::DXT1, 71, 72),
+        (ImageFormat::DXT3, 74, 75),
+        (ImageFormat::DXT5, 77, 78),
+        (
+            ImageFormat::Raw(PixelFormat::A8R8G8B8, Layout::Linear),
+            87,
+            91,
+        ),
+        (
+            ImageFormat::Raw(PixelFormat::X8R8G8B8, Layout::Linear),
+            88,
+            93,
+        ),
+    ];
+
+    for (format, unorm, srgb) in cases {
+        let mut original = texture(format, TextureKind::Texture2D, 8, 8, 1);
+        original.set_header_version(HeaderVersion::DX10);
+
+        let mut bytes = original.to_bytes().unwrap();
+        assert_eq!(bytes[DXGI_FORMAT_OFFSET], unorm);
+
+        // A file from another tool, which differs from ours only in the format
+        bytes[DXGI_FORMAT_OFFSET] = srgb;
+
+        let mut dds = assert_round_trip(&bytes);
+        assert!(dds.is_srgb(), "{:?}", format);
+        assert_eq!(dds.format(), format);
+
+        dds.set_srgb(false);
+        assert_eq!(dds.to_bytes().unwrap()[DXGI_FORMAT_OFFSET], unorm);
+    }
+
+    // Formats without an sRGB variant can't be written as one
+    let mut dds = texture(
+        ImageFormat::Raw(PixelFormat::R5G6B5, Layout::Linear),
+        TextureKind::Texture2D,
+        4,
+        4,
+        1,
+    );
+    dds.set_header_version(HeaderVersion::DX10);
+    dds.set_srgb(true);
+
+    assert!(matches!(dds.to_bytes(), Err(Error::UnsupportedFormat(_))));
+}
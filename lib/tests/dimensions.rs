@@ -0,0 +1,139 @@
+use anyxplore::format::image::{ImageFormat, codec::ImageCodec};
+
+const SIZES: &[(u32, u32)] = &[
+    (1, 1),
+    (2, 2),
+    (3, 5),
+    (4, 4),
+    (4, 8),
+    (8, 4),
+    (6, 10),
+    (10, 6),
+    (13, 7),
+    (16, 16),
+    (64, 8),
+];
+
+const FORMATS: &[ImageFormat] = &[
+    ImageFormat::DXT1,
+    ImageFormat::DXT2,
+    ImageFormat::DXT3,
+    ImageFormat::DXT4,
+    ImageFormat::DXT5,
+];
+
+// A block where every texel is colour_1 and fully opaque, with colour_1 derived from the block
+// index so that every block in the image decodes to a different colour
+fn solid_block(format: ImageFormat, index: usize) -> Vec<u8> {
+    let colour = (0x8000 + index as u16 * 0x0421).to_le_bytes();
+
+    let mut colour_block = vec![colour[0], colour[1], 0, 0, 0, 0, 0, 0];
+
+    match format {
+        ImageFormat::DXT1 => colour_block,
+        ImageFormat::DXT2 | ImageFormat::DXT3 => {
+            let mut block = vec![0xFF; 8];
+            block.append(&mut colour_block);
+            block
+        }
+        _ => {
+            let mut block = vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 0];
+            block.append(&mut colour_block);
+            block
+        }
+    }
+}
+
+fn block_colour(format: ImageFormat, index: usize) -> Vec<u8> {
+    let block = format
+        .decode(&solid_block(format, index), 4, 4, None)
+        .unwrap();
+
+    block.decode_rgba()[0..4].to_vec()
+}
+
+#[test]
+fn decoded_size_matches_dimensions() {
+    for &format in FORMATS {
+        for &(width, height) in SIZES {
+            let blocks = (width.div_ceil(4) * height.div_ceil(4)) as usize;
+            let bytes: Vec<u8> = (0..blocks).flat_map(|i| solid_block(format, i)).collect();
+
+            assert_eq!(bytes.len(), format.surface_size(width, height));
+
+            let image = format.decode(&bytes, width, height, None).unwrap();
+
+            assert_eq!(image.width(), width);
+            assert_eq!(image.height(), height);
+            assert_eq!(
+                image.decode_rgba().len(),
+                (width * height * 4) as usize,
+                "{:?} {}x{}",
+                format,
+                width,
+                height
+            );
+        }
+    }
+}
+
+#[test]
+fn blocks_are_placed_in_row_order() {
+    for &format in FORMATS {
+        for &(width, height) in SIZES {
+            let blocks_x = width.div_ceil(4) as usize;
+            let blocks = blocks_x * height.div_ceil(4) as usize;
+
+            let bytes: Vec<u8> = (0..blocks).flat_map(|i| solid_block(format, i)).collect();
+            let rgba = format
+                .decode(&bytes, width, height, None)
+                .unwrap()
+                .decode_rgba();
+
+            for y in 0..height as usize {
+                for x in 0..width as usize {
+                    let offset = (y * width as usize + x) * 4;
+                    let expected = block_colour(format, (y / 4) * blocks_x + x / 4);
+
+                    assert_eq!(
+                        &rgba[offset..offset + 4],
+                        expected.as_slice(),
+                        "{:?} {}x{} at ({}, {})",
+                        format,
+                        width,
+                        height,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn short_input_is_rejected() {
+    for &format in FORMATS {
+        for &(width, height) in SIZES {
+            let size = format.surface_size(width, height);
+            let bytes = vec![0u8; size - 1];
+
+            assert!(
+                format.decode(&bytes, width, height, None).is_err(),
+                "{:?} {}x{}",
+                format,
+                width,
+                height
+            );
+        }
+    }
+}
+
+#[test]
+fn zero_dimensions_are_rejected() {
+    for &format in FORMATS {
+        for (width, height) in [(0, 0), (0, 4), (4, 0)] {
+            assert!(format.decode(&[0u8; 64], width, height, None).is_err());
+        }
+    }
+}
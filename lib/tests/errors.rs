@@ -0,0 +1,159 @@
+use anyxplore::{
+    Error,
+    format::{
+        dds::DDS,
+        image::{
+            ImageFormat, codec::ImageCodec, dxt1::DXT1, encoder::EncodeQuality, mip::MipChain,
+            raw::PixelFormat, swizzle::Layout,
+        },
+    },
+};
+
+#[test]
+fn insufficient_data() {
+    // 5 x 5 needs four 8 byte blocks
+    let error = DXT1::from_bytes(&[0u8; 24], 5, 5).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InsufficientData {
+                expected: 32,
+                actual: 24
+            }
+        ),
+        "{:?}",
+        error
+    );
+
+    let format = ImageFormat::Raw(PixelFormat::R5G6B5, Layout::Linear);
+    let error = format.decode(&[0u8; 10], 3, 2, None).err().unwrap();
+    assert!(
+        matches!(
+            error,
+            Error::InsufficientData {
+                expected: 12,
+                actual: 10
+            }
+        ),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn buffer_too_small() {
+    let image = DXT1::from_bytes(&[0u8; 8], 4, 4).unwrap();
+    let mut out = [0u8; 32];
+
+    let error = image.decode_rgba_into(&mut out).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::BufferTooSmall {
+                expected: 64,
+                actual: 32
+            }
+        ),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn invalid_dimensions() {
+    for (width, height) in [(0, 4), (4, 0)] {
+        let error = ImageFormat::DXT5
+            .decode(&[0u8; 64], width, height, None)
+            .err()
+            .unwrap();
+        assert!(
+            matches!(error, Error::InvalidDimensions { width: w, height: h, depth: 1, .. } if w == width && h == height),
+            "{:?}",
+            error
+        );
+    }
+
+    let format = ImageFormat::Raw(PixelFormat::A8R8G8B8, Layout::Swizzled);
+    let error = format.decode(&[0u8; 64], 3, 4, None).err().unwrap();
+    assert!(
+        matches!(
+            error,
+            Error::InvalidDimensions {
+                width: 3,
+                height: 4,
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn invalid_level_count() {
+    let error = MipChain::from_bytes(&[0u8; 64], 8, 8, ImageFormat::DXT1, 5).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InvalidLevelCount {
+                level_count: 5,
+                max: 4
+            }
+        ),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn surface_out_of_range() {
+    let rgba = [255u8; 8 * 8 * 4];
+    let chain = MipChain::from_rgba(
+        &rgba,
+        8,
+        8,
+        ImageFormat::DXT1,
+        2,
+        Default::default(),
+        EncodeQuality::default(),
+    )
+    .unwrap();
+
+    let error = chain.decode_level(2).err().unwrap();
+    assert!(
+        matches!(error, Error::SurfaceOutOfRange { index: 0, level: 2 }),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn unsupported_format() {
+    let image = ImageFormat::DXT1.decode(&[0u8; 8], 4, 4, None).unwrap();
+    let mut bytes = DDS::from_image(&image).unwrap().to_bytes().unwrap();
+
+    // Swap the FourCC for one nobody writes
+    bytes[84..88].copy_from_slice(b"ABCD");
+
+    let error = DDS::from_bytes(&bytes).unwrap_err();
+    assert!(matches!(error, Error::UnsupportedFormat(_)), "{:?}", error);
+}
+
+#[test]
+fn not_a_dds_file() {
+    let error = DDS::from_bytes(&[0u8; 256]).unwrap_err();
+    assert!(matches!(error, Error::InvalidData(_)), "{:?}", error);
+}
+
+// Callers that still deal in io::Error keep a sensible kind
+#[test]
+fn into_io_error() {
+    let error = DXT1::from_bytes(&[0u8; 4], 4, 4).unwrap_err();
+    assert_eq!(
+        std::io::Error::from(error).kind(),
+        std::io::ErrorKind::UnexpectedEof
+    );
+
+    let error = std::io::Error::from(Error::UnsupportedFormat("test".to_string()));
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+}
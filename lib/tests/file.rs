@@ -0,0 +1,820 @@
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anyxplore::{
+    Error,
+    file::{
+        AssetPath, FileBytes, FileKind, VirtualFile,
+        archive::{ArchiveBudget, ArchiveOpener, ArchiveReader},
+        budget::MemoryBudget,
+        os::OsFileSystem,
+        overlay::OverlayFileSystem,
+        table::{TableArchive, TableEntry},
+    },
+};
+
+// One `name=contents` entry per line. Only the names are parsed up front, contents stay in the
+// archive's bytes until read.
+struct LineArchive {
+    bytes: FileBytes,
+    entries: Vec<(String, Range<usize>)>,
+}
+
+impl LineArchive {
+    fn range(&self, name: &str) -> Result<Range<usize>, Error> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| entry == name)
+            .map(|(_, range)| range.clone())
+            .ok_or_else(|| Error::InvalidArgument(format!("No entry named {}.", name)))
+    }
+}
+
+impl ArchiveReader for LineArchive {
+    fn entry_names(&self) -> Vec<String> {
+        self.entries.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn read_entry(&self, name: &str) -> Result<Vec<u8>, Error> {
+        Ok(self.bytes[self.range(name)?].to_vec())
+    }
+
+    fn map_entry(&self, name: &str) -> Result<FileBytes, Error> {
+        self.bytes.slice(self.range(name)?)
+    }
+
+    // The names are left out, to keep the sizes in tests simple
+    fn memory_size(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+fn line_archives(opened: Arc<AtomicUsize>) -> ArchiveOpener {
+    Arc::new(move |bytes| {
+        opened.fetch_add(1, Ordering::SeqCst);
+
+        let text = std::str::from_utf8(&bytes).map_err(|e| Error::InvalidData(e.to_string()))?;
+
+        let mut entries = Vec::new();
+        let mut start = 0;
+
+        for line in text.split('\n') {
+            let (name, contents) = line
+                .split_once('=')
+                .ok_or_else(|| Error::InvalidData(format!("Bad line {:?}.", line)))?;
+
+            let contents_start = start + name.len() + 1;
+            entries.push((
+                name.to_string(),
+                contents_start..contents_start + contents.len(),
+            ));
+
+            start += line.len() + 1;
+        }
+
+        Ok(Box::new(LineArchive { bytes, entries }))
+    })
+}
+
+// root/
+//   a.txt
+//   levels/
+//     one.pak (x, y)
+//     two.PAK (z)
+//   broken.pak
+fn scratch_dir(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("anyxplore-{}-{}", name, std::process::id()));
+
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("levels")).unwrap();
+
+    fs::write(root.join("a.txt"), b"hello").unwrap();
+    fs::write(root.join("levels/one.pak"), b"x=first\ny=second").unwrap();
+    fs::write(root.join("levels/two.PAK"), b"z=third").unwrap();
+    fs::write(root.join("broken.pak"), b"no separator").unwrap();
+
+    root
+}
+
+fn names(files: &[Arc<dyn VirtualFile>]) -> Vec<&str> {
+    files.iter().map(|file| file.name()).collect()
+}
+
+#[test]
+fn os_directories() {
+    let dir = scratch_dir("os");
+    let root = OsFileSystem::new(&dir).open().unwrap();
+
+    assert_eq!(root.kind(), FileKind::Directory);
+    assert!(root.parent().is_none());
+
+    let children = root.children().unwrap();
+    assert_eq!(names(&children), ["a.txt", "broken.pak", "levels"]);
+
+    // Without a registered format archives are plain files
+    let text = &children[0];
+    assert_eq!(text.kind(), FileKind::File);
+    assert_eq!(text.size().unwrap(), 5);
+    assert_eq!(text.read().unwrap(), b"hello");
+    assert_eq!(text.open().unwrap().read_to_bytes().unwrap(), b"hello");
+    assert!(text.children().unwrap().is_empty());
+    assert_eq!(text.path(), dir.join("a.txt"));
+
+    let levels = root.child("levels").unwrap().unwrap();
+    assert!(levels.is_dirlike());
+    assert_eq!(names(&levels.children().unwrap()), ["one.pak", "two.PAK"]);
+    assert!(levels.read().is_err());
+
+    let parent = levels.parent().unwrap();
+    assert_eq!(parent.path(), dir);
+    assert!(parent.parent().is_none());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn archives() {
+    let dir = scratch_dir("archives");
+    let opened = Arc::new(AtomicUsize::new(0));
+
+    let root = OsFileSystem::new(&dir)
+        .with_archive_format("pak", line_archives(opened.clone()))
+        .open()
+        .unwrap();
+
+    let levels = root.child("levels").unwrap().unwrap();
+    let archives = levels.children().unwrap();
+
+    assert!(
+        archives
+            .iter()
+            .all(|archive| archive.kind() == FileKind::Archive)
+    );
+
+    // Listing doesn't parse anything
+    assert_eq!(opened.load(Ordering::SeqCst), 0);
+
+    let entries = archives[0].children().unwrap();
+    assert_eq!(names(&entries), ["x", "y"]);
+    assert_eq!(entries[1].read().unwrap(), b"second");
+    assert_eq!(entries[1].size().unwrap(), 6);
+    assert_eq!(entries[1].path(), dir.join("levels/one.pak/y"));
+
+    let archive = entries[1].parent().unwrap();
+    assert_eq!(archive.kind(), FileKind::Archive);
+    assert_eq!(archive.parent().unwrap().path(), dir.join("levels"));
+
+    // Mounted archives are reused by later listings, without parsing again
+    let again = levels.child("one.pak").unwrap().unwrap();
+    assert_eq!(names(&again.children().unwrap()), ["x", "y"]);
+    assert_eq!(opened.load(Ordering::SeqCst), 1);
+
+    // Extensions match regardless of case
+    let two = &archives[1].children().unwrap()[0];
+    assert_eq!(two.read().unwrap(), b"third");
+
+    // The raw archive is still readable
+    assert_eq!(archives[1].read().unwrap(), b"z=third");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn broken_archives() {
+    let dir = scratch_dir("broken");
+
+    let root = OsFileSystem::new(&dir)
+        .with_archive_format("pak", line_archives(Arc::new(AtomicUsize::new(0))))
+        .open()
+        .unwrap();
+
+    let broken = root.child("broken.pak").unwrap().unwrap();
+
+    assert_eq!(broken.kind(), FileKind::Archive);
+    assert!(matches!(broken.children(), Err(Error::InvalidData(_))));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn nested_archives() {
+    let dir = scratch_dir("nested");
+    let opened = Arc::new(AtomicUsize::new(0));
+
+    // Everything after the first `=` is the entry, so each level peels off one name
+    fs::write(
+        dir.join("outer.pak"),
+        b"inner.pak=deep.pak=leaf=bottom\nplain=text",
+    )
+    .unwrap();
+
+    let root = OsFileSystem::new(&dir)
+        .with_archive_format("pak", line_archives(opened.clone()))
+        .open()
+        .unwrap();
+
+    let outer = root.child("outer.pak").unwrap().unwrap();
+    let entries = outer.children().unwrap();
+    assert_eq!(names(&entries), ["inner.pak", "plain"]);
+    assert_eq!(entries[1].kind(), FileKind::File);
+
+    // Nested archives are only parsed once they are expanded
+    let inner = &entries[0];
+    assert_eq!(inner.kind(), FileKind::Archive);
+    assert_eq!(opened.load(Ordering::SeqCst), 1);
+
+    let deep = inner.child("deep.pak").unwrap().unwrap();
+    assert_eq!(deep.kind(), FileKind::Archive);
+    assert_eq!(deep.path(), dir.join("outer.pak/inner.pak/deep.pak"));
+    assert_eq!(deep.read().unwrap(), b"leaf=bottom");
+    assert_eq!(opened.load(Ordering::SeqCst), 2);
+
+    let leaf = &deep.children().unwrap()[0];
+    assert_eq!(leaf.read().unwrap(), b"bottom");
+    assert_eq!(leaf.path(), dir.join("outer.pak/inner.pak/deep.pak/leaf"));
+    assert_eq!(opened.load(Ordering::SeqCst), 3);
+
+    // Parents walk back out through every level
+    let parent = leaf.parent().unwrap().parent().unwrap();
+    assert_eq!(parent.path(), dir.join("outer.pak/inner.pak"));
+    assert_eq!(parent.parent().unwrap().path(), dir.join("outer.pak"));
+
+    // Mounted nested archives are reused
+    outer.children().unwrap()[0].children().unwrap();
+    assert_eq!(opened.load(Ordering::SeqCst), 3);
+
+    // Entries can't be written, even when they are archives
+    assert!(deep.write(b"leaf=changed").is_err());
+
+    // Nested archives hold part of their parent's contents, so they are closed along with it
+    assert_eq!(leaf.read().unwrap(), b"bottom");
+    assert!(deep.is_open());
+    outer.write(b"plain=text").unwrap();
+    assert!(!outer.is_open() && !inner.is_open() && !deep.is_open());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+// A 4 byte header followed by the contents of each entry, at offsets only the opener knows
+fn table_archives() -> ArchiveOpener {
+    Arc::new(|bytes| {
+        let entry = |name: &str, range: Range<usize>, format: Option<&str>| TableEntry {
+            name: name.to_string(),
+            range,
+            format: format.map(str::to_string),
+        };
+
+        Ok(Box::new(TableArchive::new(
+            bytes,
+            vec![
+                entry("first", 4..9, None),
+                entry("packed", 9..17, Some("pak")),
+                entry("empty.pak", 17..17, Some("none")),
+            ],
+        )?))
+    })
+}
+
+#[test]
+fn table_archive_entries() {
+    let dir = scratch_dir("table");
+    fs::write(dir.join("data.tbl"), b"HEADhellop=nested").unwrap();
+
+    let root = OsFileSystem::new(&dir)
+        .with_archive_format("tbl", table_archives())
+        .with_archive_format("pak", line_archives(Arc::new(AtomicUsize::new(0))))
+        .open()
+        .unwrap();
+
+    let table = root.child("data.tbl").unwrap().unwrap();
+    let entries = table.children().unwrap();
+    assert_eq!(names(&entries), ["first", "packed", "empty.pak"]);
+
+    // Entries are slices of the mapped archive
+    let first = entries[0].map().unwrap();
+    assert!(first.is_mapped());
+    assert_eq!(&*first, b"hello");
+    assert_eq!(entries[0].read().unwrap(), b"hello");
+    assert_eq!(entries[0].size().unwrap(), 5);
+
+    // The table's format wins over the name's extension
+    assert_eq!(entries[1].kind(), FileKind::Archive);
+    assert_eq!(entries[1].children().unwrap()[0].read().unwrap(), b"nested");
+    assert_eq!(entries[2].kind(), FileKind::File);
+    assert!(entries[2].read().unwrap().is_empty());
+
+    // Tables pointing past the end are rejected up front
+    let short = FileBytes::from(b"HEAD".to_vec());
+    let entry = TableEntry {
+        name: "past".to_string(),
+        range: 2..8,
+        format: None,
+    };
+    assert!(matches!(
+        TableArchive::new(short.clone(), vec![entry]),
+        Err(Error::InsufficientData {
+            expected: 8,
+            actual: 4
+        })
+    ));
+
+    let archive = TableArchive::new(short, Vec::new()).unwrap();
+    assert!(matches!(
+        archive.read_entry("missing"),
+        Err(Error::InvalidArgument(_))
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn asset_path_strings() {
+    let path: AssetPath = "levels/one.pak:y".parse().unwrap();
+
+    assert_eq!(path.file(), PathBuf::from("levels/one.pak"));
+    assert_eq!(path.asset(), Some("y"));
+    assert_eq!(path.to_string(), "levels/one.pak:y");
+    assert_eq!(path.archive().unwrap().to_string(), "levels/one.pak");
+    assert_eq!(
+        path,
+        AssetPath::new(std::path::Path::new("levels/one.pak"), &["y"]).unwrap()
+    );
+
+    // Equal however they were written
+    let nested: AssetPath = "levels\\one.pak:inner.pak:leaf".parse().unwrap();
+    assert_eq!(nested.to_string(), "levels/one.pak:inner.pak:leaf");
+    assert_eq!(nested.entries(), ["inner.pak", "leaf"]);
+    assert_eq!(
+        nested,
+        AssetPath::root()
+            .join("levels")
+            .join("one.pak")
+            .entry("inner.pak")
+            .join("leaf")
+    );
+
+    let directory: AssetPath = "levels/".parse().unwrap();
+    assert_eq!(directory.to_string(), "levels");
+    assert_eq!(directory.asset(), None);
+    assert!(directory.archive().is_none());
+
+    for bad in [
+        "/levels/one.pak",
+        "../one.pak",
+        "levels/./one.pak",
+        "one.pak::y",
+        ":y",
+    ] {
+        assert!(
+            matches!(bad.parse::<AssetPath>(), Err(Error::InvalidArgument(_))),
+            "{}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn asset_path_files() {
+    let dir = scratch_dir("asset-paths");
+    fs::write(dir.join("levels/nested.pak"), b"inner.pak=leaf=bottom").unwrap();
+
+    let root = OsFileSystem::new(&dir)
+        .with_archive_format("pak", line_archives(Arc::new(AtomicUsize::new(0))))
+        .open()
+        .unwrap();
+
+    let y = "levels/one.pak:y".parse::<AssetPath>().unwrap();
+    let file = y.resolve(&root).unwrap();
+    assert_eq!(file.read().unwrap(), b"second");
+    assert_eq!(AssetPath::from_file(&root, &file).unwrap(), y);
+
+    let leaf = "levels/nested.pak:inner.pak:leaf"
+        .parse::<AssetPath>()
+        .unwrap();
+    let file = leaf.resolve(&root).unwrap();
+    assert_eq!(file.read().unwrap(), b"bottom");
+    assert_eq!(AssetPath::from_file(&root, &file).unwrap(), leaf);
+
+    let levels = root.child("levels").unwrap().unwrap();
+    assert_eq!(
+        AssetPath::from_file(&root, &levels).unwrap().to_string(),
+        "levels"
+    );
+    assert_eq!(
+        AssetPath::from_file(&root, &root).unwrap(),
+        AssetPath::root()
+    );
+    assert_eq!(
+        AssetPath::root().resolve(&root).unwrap().path(),
+        root.path()
+    );
+
+    // Missing assets and files outside the root
+    assert!(matches!(
+        "levels/one.pak:missing"
+            .parse::<AssetPath>()
+            .unwrap()
+            .resolve(&root),
+        Err(Error::InvalidArgument(_))
+    ));
+    assert!(AssetPath::from_file(&levels, &root).is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn mapped_files() {
+    let dir = scratch_dir("mapped");
+
+    let root = OsFileSystem::new(&dir)
+        .with_archive_format("pak", line_archives(Arc::new(AtomicUsize::new(0))))
+        .open()
+        .unwrap();
+
+    let text = root.child("a.txt").unwrap().unwrap();
+    let mapped = text.map().unwrap();
+    assert!(mapped.is_mapped());
+    assert_eq!(&*mapped, b"hello");
+    assert_eq!(&*mapped.slice(1..3).unwrap(), b"el");
+    assert!(matches!(
+        mapped.slice(3..9),
+        Err(Error::InsufficientData { .. })
+    ));
+
+    // Writes replace the file, so on Unix the old mapping keeps its contents. Windows can't replace
+    // a file that is still mapped.
+    #[cfg(windows)]
+    drop(mapped);
+    text.write(b"a longer replacement").unwrap();
+    #[cfg(unix)]
+    assert_eq!(&*mapped, b"hello");
+    assert_eq!(&*text.map().unwrap(), b"a longer replacement");
+
+    // Entries of mapped archives are slices of the archive
+    let y = "levels/one.pak:y".parse::<AssetPath>().unwrap();
+    let entry = y.resolve(&root).unwrap().map().unwrap();
+    assert!(entry.is_mapped());
+    assert_eq!(&*entry, b"second");
+
+    // Empty files can't always be mapped, but still have contents
+    fs::write(dir.join("empty.txt"), b"").unwrap();
+    let empty = root.child("empty.txt").unwrap().unwrap().map().unwrap();
+    assert!(empty.is_empty());
+
+    assert!(!FileBytes::from(b"owned".to_vec()).is_mapped());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn replaced_files() {
+    let dir = scratch_dir("replaced");
+    fs::write(dir.join("a.txt.tmp"), b"unrelated").unwrap();
+
+    let root = OsFileSystem::new(&dir)
+        .with_archive_format("pak", line_archives(Arc::new(AtomicUsize::new(0))))
+        .open()
+        .unwrap();
+
+    let listing = || {
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        names
+    };
+    let before = listing();
+
+    // Temporary files never take the name of an existing file, and don't outlive the write
+    root.child("a.txt").unwrap().unwrap().write(b"new").unwrap();
+    assert_eq!(fs::read(dir.join("a.txt.tmp")).unwrap(), b"unrelated");
+    assert_eq!(listing(), before);
+
+    // Not even when the write fails, here because a directory can't be replaced by a file
+    assert!(root.write_child(Path::new("levels"), b"nope").is_err());
+    assert!(dir.join("levels").is_dir());
+    assert_eq!(listing(), before);
+
+    // Archives are closed before their file is replaced, however it is written
+    let one = root
+        .child("levels")
+        .unwrap()
+        .unwrap()
+        .child("one.pak")
+        .unwrap()
+        .unwrap();
+    one.children().unwrap();
+    root.write_child(Path::new("levels/one.pak"), b"x=changed")
+        .unwrap();
+    assert!(!one.is_open());
+    assert_eq!(one.children().unwrap()[0].read().unwrap(), b"changed");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn memory_budgets() {
+    let budget = MemoryBudget::new(10);
+
+    assert!(budget.insert("a", 4).is_empty());
+    assert!(budget.insert("b", 4).is_empty());
+    budget.touch(&"a");
+
+    // Least recently used goes first, and only as much as needed
+    assert_eq!(budget.insert("c", 4), ["b"]);
+    assert_eq!(budget.used(), 8);
+    assert!(budget.contains(&"a") && !budget.contains(&"b"));
+
+    // Reinserting replaces the old size
+    assert_eq!(budget.insert("a", 7), ["c"]);
+    assert_eq!(budget.used(), 7);
+
+    // Nothing else to evict, so a single item may go over
+    assert!(budget.insert("a", 20).is_empty());
+    assert_eq!(budget.used(), 20);
+
+    budget.remove(&"a");
+    assert_eq!(budget.used(), 0);
+}
+
+#[test]
+fn archive_eviction() {
+    let dir = scratch_dir("eviction");
+    let opened = Arc::new(AtomicUsize::new(0));
+
+    // one.pak and two.PAK don't fit together
+    let budget = Arc::new(ArchiveBudget::new(20));
+
+    let root = OsFileSystem::new(&dir)
+        .with_archive_format("pak", line_archives(opened.clone()))
+        .with_memory_budget(budget.clone())
+        .open()
+        .unwrap();
+
+    let levels = root.child("levels").unwrap().unwrap();
+    let one = levels.child("one.pak").unwrap().unwrap();
+    let two = levels.child("two.PAK").unwrap().unwrap();
+
+    let x = one.children().unwrap()[0].clone();
+    assert_eq!(budget.used(), 16);
+
+    two.children().unwrap();
+    assert_eq!(opened.load(Ordering::SeqCst), 2);
+    assert_eq!(budget.used(), 7);
+
+    // Entries listed before an eviction parse their archive again, which evicts the other one
+    assert_eq!(x.read().unwrap(), b"first");
+    assert_eq!(opened.load(Ordering::SeqCst), 3);
+    assert_eq!(budget.used(), 16);
+
+    one.children().unwrap();
+    assert_eq!(opened.load(Ordering::SeqCst), 3);
+    two.children().unwrap();
+    assert_eq!(opened.load(Ordering::SeqCst), 4);
+    assert!(!one.is_open() && two.is_open());
+
+    // Archives that are still open are reused
+    two.children().unwrap();
+    assert_eq!(opened.load(Ordering::SeqCst), 4);
+
+    // Dropping archives gives back their memory
+    drop((root, levels, one, two, x));
+    assert_eq!(budget.used(), 0);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+// Readers that copy out what they need and drop the archive's contents are only charged for that
+#[test]
+fn archive_charges() {
+    struct Names(Vec<String>);
+
+    impl ArchiveReader for Names {
+        fn entry_names(&self) -> Vec<String> {
+            self.0.clone()
+        }
+
+        fn read_entry(&self, name: &str) -> Result<Vec<u8>, Error> {
+            Ok(name.as_bytes().to_vec())
+        }
+
+        fn memory_size(&self) -> usize {
+            self.0.iter().map(String::len).sum()
+        }
+    }
+
+    let dir = scratch_dir("charges");
+    let budget = Arc::new(ArchiveBudget::new(100));
+
+    let root = OsFileSystem::new(&dir)
+        .with_archive_format(
+            "pak",
+            Arc::new(|bytes| {
+                let names = bytes
+                    .split(|&b| b == b'\n')
+                    .map(|line| String::from_utf8_lossy(&line[..1]).into_owned())
+                    .collect();
+
+                Ok(Box::new(Names(names)))
+            }),
+        )
+        .with_memory_budget(budget.clone())
+        .open()
+        .unwrap();
+
+    let one = "levels/one.pak".parse::<AssetPath>().unwrap();
+    let one = one.resolve(&root).unwrap();
+
+    assert!(!one.is_open());
+    assert_eq!(names(&one.children().unwrap()), ["x", "y"]);
+    assert!(one.is_open());
+    assert_eq!(budget.used(), 2);
+
+    // Format specific readers can be reached from the archive
+    let reader = one.archive_reader().unwrap().unwrap();
+    let names_reader = (reader as Arc<dyn std::any::Any + Send + Sync>)
+        .downcast::<Names>()
+        .ok()
+        .unwrap();
+    assert_eq!(names_reader.0, ["x", "y"]);
+
+    let text = root.child("a.txt").unwrap().unwrap();
+    assert!(text.archive_reader().unwrap().is_none());
+    assert!(!text.is_open());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_root() {
+    let dir = std::env::temp_dir().join(format!("anyxplore-missing-{}", std::process::id()));
+
+    assert!(matches!(OsFileSystem::new(&dir).open(), Err(Error::Io(_))));
+}
+
+// A mod folder over the scratch dir, replacing a.txt and one.pak and adding extra.txt
+fn overlay(name: &str) -> (PathBuf, PathBuf, Arc<dyn VirtualFile>) {
+    let base = scratch_dir(name);
+    let top = base.with_extension("mod");
+
+    let _ = fs::remove_dir_all(&top);
+    fs::create_dir_all(top.join("levels")).unwrap();
+
+    fs::write(top.join("a.txt"), b"modded").unwrap();
+    fs::write(top.join("extra.txt"), b"new").unwrap();
+    fs::write(top.join("levels/one.pak"), b"w=replaced").unwrap();
+
+    let opener = line_archives(Arc::new(AtomicUsize::new(0)));
+    let root = OverlayFileSystem::new(
+        OsFileSystem::new(&base)
+            .read_only()
+            .with_archive_format("pak", opener.clone())
+            .open()
+            .unwrap(),
+        OsFileSystem::new(&top)
+            .with_archive_format("pak", opener)
+            .open()
+            .unwrap(),
+    )
+    .open()
+    .unwrap();
+
+    (base, top, root)
+}
+
+#[test]
+fn overlay_merging() {
+    let (base, top, root) = overlay("overlay-merging");
+
+    assert_eq!(root.kind(), FileKind::Directory);
+    assert_eq!(root.path(), base);
+    assert!(!root.is_overridden());
+
+    let children = root.children().unwrap();
+    assert_eq!(
+        names(&children),
+        ["a.txt", "broken.pak", "extra.txt", "levels"]
+    );
+
+    // The top layer wins
+    assert_eq!(children[0].read().unwrap(), b"modded");
+    assert!(children[0].is_overridden());
+    assert_eq!(children[0].path(), base.join("a.txt"));
+    assert_eq!(children[2].read().unwrap(), b"new");
+    assert!(!children[1].is_overridden());
+
+    // Directories in both layers are merged rather than overridden
+    let levels = &children[3];
+    assert!(!levels.is_overridden());
+    assert_eq!(names(&levels.children().unwrap()), ["one.pak", "two.PAK"]);
+
+    // Overridden archives hide every entry of the base one
+    let one = levels.child("one.pak").unwrap().unwrap();
+    assert_eq!(one.kind(), FileKind::Archive);
+    assert!(one.is_overridden());
+    assert!(one.archive_reader().unwrap().is_some() && one.is_open());
+
+    let entries = one.children().unwrap();
+    assert_eq!(names(&entries), ["w"]);
+    assert!(entries[0].is_overridden());
+    assert_eq!(entries[0].path(), base.join("levels/one.pak/w"));
+
+    let two = levels.child("two.PAK").unwrap().unwrap();
+    assert_eq!(two.children().unwrap()[0].read().unwrap(), b"third");
+    assert!(!two.children().unwrap()[0].is_overridden());
+
+    // Parents stay inside the merged view
+    let parent = entries[0].parent().unwrap().parent().unwrap();
+    assert_eq!(parent.path(), base.join("levels"));
+    assert_eq!(names(&parent.children().unwrap()), ["one.pak", "two.PAK"]);
+    assert!(parent.parent().unwrap().parent().is_none());
+
+    fs::remove_dir_all(base).unwrap();
+    fs::remove_dir_all(top).unwrap();
+}
+
+#[test]
+fn overlay_writes() {
+    let (base, top, root) = overlay("overlay-writes");
+
+    let levels = root.child("levels").unwrap().unwrap();
+    let two = levels.child("two.PAK").unwrap().unwrap();
+    assert!(!two.is_overridden());
+
+    // Writing a base file copies it into the top layer, at the same path
+    two.write(b"z=changed").unwrap();
+    assert_eq!(fs::read(base.join("levels/two.PAK")).unwrap(), b"z=third");
+    assert_eq!(fs::read(top.join("levels/two.PAK")).unwrap(), b"z=changed");
+
+    // The node that was written shows the write straight away, as do ones listed later
+    assert!(two.is_overridden());
+    assert_eq!(two.read().unwrap(), b"z=changed");
+    assert_eq!(two.children().unwrap()[0].read().unwrap(), b"changed");
+
+    let two = levels.child("two.PAK").unwrap().unwrap();
+    assert!(two.is_overridden());
+    assert_eq!(two.children().unwrap()[0].read().unwrap(), b"changed");
+
+    // Writing again replaces the archive that was already in the top layer
+    two.write(b"z=again").unwrap();
+    assert_eq!(two.children().unwrap()[0].read().unwrap(), b"again");
+
+    // New files and the directories leading to them
+    root.write_child(std::path::Path::new("new/deep.txt"), b"deep")
+        .unwrap();
+    assert_eq!(fs::read(top.join("new/deep.txt")).unwrap(), b"deep");
+    assert!(!base.join("new").exists());
+
+    let new = root.child("new").unwrap().unwrap();
+    assert!(new.is_overridden());
+    assert_eq!(new.children().unwrap()[0].read().unwrap(), b"deep");
+
+    // Entries are only written through their archive, and directories not at all
+    let entry = &two.children().unwrap()[0];
+    assert!(matches!(
+        entry.write(b"nope"),
+        Err(Error::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        levels.write(b"nope"),
+        Err(Error::InvalidArgument(_))
+    ));
+
+    fs::remove_dir_all(base).unwrap();
+    fs::remove_dir_all(top).unwrap();
+}
+
+#[test]
+fn read_only_layers() {
+    let dir = scratch_dir("read-only");
+    let root = OsFileSystem::new(&dir).read_only().open().unwrap();
+
+    let text = root.child("a.txt").unwrap().unwrap();
+    assert!(matches!(
+        text.write(b"nope"),
+        Err(Error::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        root.write_child(std::path::Path::new("b.txt"), b"nope"),
+        Err(Error::InvalidArgument(_))
+    ));
+    assert_eq!(fs::read(dir.join("a.txt")).unwrap(), b"hello");
+    assert!(!dir.join("b.txt").exists());
+
+    // Writable filesystems keep writes below their root
+    let root = OsFileSystem::new(&dir).open().unwrap();
+    assert!(
+        root.write_child(std::path::Path::new("../escaped.txt"), b"nope")
+            .is_err()
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "interchange")]
+
+mod common;
+
+use anyxplore::format::image::{
+    Image, ImageFormat,
+    codec::ImageCodec,
+    encoder::EncodeQuality,
+    interchange::{read_png, read_tga, write_png, write_tga},
+    raw::PixelFormat,
+    swizzle::Layout,
+};
+use common::XorShift;
+
+// Random colours over every kind of alpha: opaque, fully transparent and partial
+fn rgba(width: u32, height: u32) -> Vec<u8> {
+    let mut rgba = XorShift::new(17).bytes(width as usize * height as usize * 4);
+
+    for (i, texel) in rgba.chunks_exact_mut(4).enumerate() {
+        texel[3] = match i % 3 {
+            0 => 255,
+            1 => 0,
+            _ => texel[3],
+        };
+    }
+
+    rgba
+}
+
+#[test]
+fn png_round_trip() {
+    for (width, height) in [(1, 1), (7, 5), (16, 3)] {
+        let rgba = rgba(width, height);
+
+        let mut png = Vec::new();
+        write_png(&mut png, &rgba, width, height).unwrap();
+
+        assert_eq!(read_png(&png[..]).unwrap(), (rgba, width, height));
+    }
+}
+
+#[test]
+fn tga_round_trip() {
+    for (width, height) in [(1, 1), (7, 5), (16, 3)] {
+        let rgba = rgba(width, height);
+
+        let mut tga = Vec::new();
+        write_tga(&mut tga, &rgba, width, height).unwrap();
+
+        assert_eq!(read_tga(&tga[..]).unwrap(), (rgba, width, height));
+    }
+}
+
+// A run length encoded, bottom to top TGA, as most other tools write them
+#[test]
+fn tga_rle_bottom_up() {
+    let tga = [
+        0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, // type 10, no colour map
+        2, 0, 2, 0, 32, 8, // 2x2, 32 bits, bottom to top
+        0x81, 10, 20, 30, 40, // bottom row, one pixel repeated twice (B G R A)
+        0x01, 1, 2, 3, 0, 4, 5, 6, 128, // top row, two raw pixels
+    ];
+
+    let (rgba, width, height) = read_tga(&tga[..]).unwrap();
+
+    assert_eq!((width, height), (2, 2));
+    assert_eq!(
+        rgba,
+        [
+            3, 2, 1, 0, 6, 5, 4, 128, //
+            30, 20, 10, 40, 30, 20, 10, 40,
+        ]
+    );
+}
+
+// Lossless formats come back exactly, including texels that are fully transparent
+#[test]
+fn textures_round_trip() {
+    let rgba = rgba(8, 4);
+    let format = ImageFormat::Raw(PixelFormat::A8R8G8B8, Layout::Linear);
+
+    let image = Image::encode_rgba(&rgba, 8, 4, format, EncodeQuality::default()).unwrap();
+
+    let mut png = Vec::new();
+    image.write_png(&mut png).unwrap();
+    let from_png = Image::from_png(&png[..], format, EncodeQuality::default()).unwrap();
+    assert_eq!(from_png.decode_rgba(), rgba);
+
+    let mut tga = Vec::new();
+    image.write_tga(&mut tga).unwrap();
+    let from_tga = Image::from_tga(&tga[..], format, EncodeQuality::default()).unwrap();
+    assert_eq!(from_tga.decode_rgba(), rgba);
+
+    // Block compressed textures export what they decode to
+    let dxt5 =
+        Image::encode_rgba(&rgba, 8, 4, ImageFormat::DXT5, EncodeQuality::default()).unwrap();
+
+    let mut png = Vec::new();
+    dxt5.write_png(&mut png).unwrap();
+    assert_eq!(read_png(&png[..]).unwrap().0, dxt5.decode_rgba());
+}
@@ -0,0 +1,94 @@
+use anyxplore::format::image::{
+    ImageFormat, cube::CubeMap, mip::MipChain, raw::PixelFormat, swizzle::Layout,
+    volume::VolumeTexture,
+};
+
+const FORMATS: &[ImageFormat] = &[
+    ImageFormat::DXT1,
+    ImageFormat::DXT2,
+    ImageFormat::DXT3,
+    ImageFormat::DXT4,
+    ImageFormat::DXT5,
+    ImageFormat::Raw(PixelFormat::A8R8G8B8, Layout::Linear),
+    ImageFormat::Raw(PixelFormat::R5G6B5, Layout::Swizzled),
+    ImageFormat::P8(Layout::Linear),
+    ImageFormat::P8(Layout::Swizzled),
+];
+
+// Sizes whose byte counts overflow a u32 or usize
+const HUGE: &[(u32, u32)] = &[
+    (u32::MAX, u32::MAX),
+    (u32::MAX, 1),
+    (1 << 31, 1 << 31),
+    (65536, 65536),
+];
+
+#[test]
+fn huge_dimensions() {
+    let bytes = [0u8; 64];
+    let palette = [0u8; 1024];
+
+    for &format in FORMATS {
+        for &(width, height) in HUGE {
+            assert!(
+                format
+                    .decode(&bytes, width, height, Some(&palette))
+                    .is_err(),
+                "{:?} {} x {}",
+                format,
+                width,
+                height
+            );
+
+            assert!(MipChain::from_bytes(&bytes, width, height, format, 1).is_err());
+            assert!(VolumeTexture::from_bytes(&bytes, width, height, 2, format, 1).is_err());
+        }
+
+        assert!(CubeMap::from_bytes(&bytes, u32::MAX, format, 1).is_err());
+        assert!(VolumeTexture::from_bytes(&bytes, 4, 4, u32::MAX, format, 1).is_err());
+    }
+}
+
+// Level counts are validated before any level is sized
+#[test]
+fn huge_level_count() {
+    let bytes = [0u8; 64];
+
+    for &format in FORMATS {
+        assert!(MipChain::from_bytes(&bytes, 4, 4, format, u32::MAX).is_err());
+        assert!(CubeMap::from_bytes(&bytes, 4, format, u32::MAX).is_err());
+        assert!(CubeMap::from_bytes(&bytes, 4, format, 0).is_err());
+        assert!(VolumeTexture::from_bytes(&bytes, 4, 4, 4, format, u32::MAX).is_err());
+    }
+}
+
+#[test]
+fn truncated_surfaces() {
+    for &format in FORMATS {
+        let size = format.surface_size(8, 8);
+        let bytes = vec![0u8; size];
+
+        for len in [0, 1, size / 2, size - 1] {
+            assert!(
+                format
+                    .decode(&bytes[..len], 8, 8, Some(&[0u8; 1024]))
+                    .is_err()
+            );
+        }
+    }
+}
+
+// An RLE header claiming 65535 x 65290 pixels with no pixel data
+#[cfg(feature = "interchange")]
+#[test]
+fn tga_oversized_rle() {
+    use anyxplore::format::image::interchange::read_tga;
+
+    let tga = [
+        0x00, 0x02, 0x0A, 0xFF, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x0B, 0xFF, 0xFF, 0x0A,
+        0xFF, 0x18, 0x00,
+    ];
+
+    assert!(read_tga(&tga[..]).is_err());
+    assert!(read_tga(&[0xFFu8; 18][..]).is_err());
+}
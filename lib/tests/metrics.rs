@@ -0,0 +1,216 @@
+mod common;
+
+use anyxplore::{
+    Error,
+    format::image::{
+        Image, ImageFormat,
+        codec::ImageCodec,
+        encoder::EncodeQuality,
+        metrics::{Metrics, Weighting},
+    },
+};
+use common::XorShift;
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "{} != {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn identical_images() {
+    let rgba = XorShift::new(7).bytes(16 * 12 * 4);
+
+    for weighting in [Weighting::Uniform, Weighting::Alpha] {
+        let metrics = Metrics::from_rgba(&rgba, &rgba, 16, 12, weighting).unwrap();
+
+        for channel in metrics.channels.iter().chain([&metrics.overall]) {
+            assert_eq!(channel.rmse, 0.0);
+            assert_eq!(channel.psnr, f64::INFINITY);
+            assert_close(channel.ssim, 1.0);
+        }
+    }
+}
+
+#[test]
+fn constant_offset() {
+    let reference = XorShift::new(11)
+        .bytes(9 * 9 * 4)
+        .iter()
+        .map(|&b| b / 2)
+        .collect::<Vec<u8>>();
+
+    // Red is 10 higher everywhere, the other channels are untouched
+    let test: Vec<u8> = reference
+        .chunks_exact(4)
+        .flat_map(|t| [t[0] + 10, t[1], t[2], t[3]])
+        .collect();
+
+    let metrics = Metrics::from_rgba(&reference, &test, 9, 9, Weighting::Uniform).unwrap();
+
+    assert_close(metrics.channels[0].rmse, 10.0);
+    assert_close(metrics.channels[0].psnr, 20.0 * 25.5f64.log10());
+    assert!(metrics.channels[0].ssim < 1.0);
+
+    for channel in &metrics.channels[1..] {
+        assert_eq!(channel.rmse, 0.0);
+    }
+
+    // The squared error is spread over all four channels
+    assert_close(metrics.overall.rmse, 5.0);
+}
+
+// Two texels whose green is off by 3 and 4, small enough to work out by hand
+#[test]
+fn hand_computed() {
+    let reference = [0, 0, 0, 255, 0, 0, 0, 255];
+    let test = [0, 3, 0, 255, 0, 4, 0, 255];
+
+    let metrics = Metrics::from_rgba(&reference, &test, 2, 1, Weighting::Uniform).unwrap();
+
+    // (3² + 4²) / 2
+    let mse: f64 = 12.5;
+    assert_close(metrics.channels[1].rmse, mse.sqrt());
+    assert_close(
+        metrics.channels[1].psnr,
+        10.0 * (255.0 * 255.0 / mse).log10(),
+    );
+    assert_close(metrics.overall.rmse, (mse / 4.0).sqrt());
+
+    // One window, with means 0 and 3.5, variances 0 and 0.25 and no covariance
+    let (c1, c2) = (2.55 * 2.55, 7.65 * 7.65);
+    let ssim = (c1 * c2) / ((3.5 * 3.5 + c1) * (0.25 + c2));
+    assert_close(metrics.channels[1].ssim, ssim);
+    assert_close(metrics.overall.ssim, (3.0 + ssim) / 4.0);
+
+    for channel in [0, 2, 3] {
+        assert_eq!(metrics.channels[channel].rmse, 0.0);
+        assert_close(metrics.channels[channel].ssim, 1.0);
+    }
+
+    // Red off by 10 on an opaque texel and by 20 on one at alpha 51, which counts a fifth as much
+    let reference = [0, 0, 0, 255, 0, 0, 0, 51];
+    let test = [10, 0, 0, 255, 20, 0, 0, 51];
+
+    let uniform = Metrics::from_rgba(&reference, &test, 2, 1, Weighting::Uniform).unwrap();
+    let weighted = Metrics::from_rgba(&reference, &test, 2, 1, Weighting::Alpha).unwrap();
+
+    assert_close(uniform.channels[0].rmse, 250f64.sqrt());
+    assert_close(weighted.channels[0].rmse, 150f64.sqrt());
+}
+
+#[test]
+fn alpha_weighting_ignores_transparent_texels() {
+    let reference: Vec<u8> = (0..64)
+        .flat_map(|i| {
+            if i % 2 == 0 {
+                [50, 60, 70, 255]
+            } else {
+                [0, 0, 0, 0]
+            }
+        })
+        .collect();
+
+    // Only the colour of the transparent texels changes
+    let test: Vec<u8> = reference
+        .chunks_exact(4)
+        .flat_map(|t| {
+            if t[3] == 0 {
+                [200, 200, 200, 0]
+            } else {
+                [t[0], t[1], t[2], t[3]]
+            }
+        })
+        .collect();
+
+    let uniform = Metrics::from_rgba(&reference, &test, 8, 8, Weighting::Uniform).unwrap();
+    let weighted = Metrics::from_rgba(&reference, &test, 8, 8, Weighting::Alpha).unwrap();
+
+    assert!(uniform.overall.rmse > 0.0);
+    assert_eq!(weighted.overall.rmse, 0.0);
+    assert_close(weighted.overall.ssim, 1.0);
+}
+
+#[test]
+fn ssim_falls_with_noise() {
+    let reference = XorShift::new(3).bytes(32 * 32 * 4);
+    let mut noise = XorShift::new(5);
+
+    let mut noisy = |amount: u32| -> Vec<u8> {
+        let offsets = noise.bytes(reference.len());
+
+        reference
+            .iter()
+            .zip(offsets)
+            .map(|(&b, n)| (b as i32 + (n as i32 - 128) * amount as i32 / 128).clamp(0, 255) as u8)
+            .collect()
+    };
+
+    let slight = noisy(4);
+    let heavy = noisy(64);
+
+    let slight = Metrics::from_rgba(&reference, &slight, 32, 32, Weighting::Uniform).unwrap();
+    let heavy = Metrics::from_rgba(&reference, &heavy, 32, 32, Weighting::Uniform).unwrap();
+
+    assert!(slight.overall.ssim > heavy.overall.ssim);
+    assert!(slight.overall.psnr > heavy.overall.psnr);
+}
+
+// Smaller than a single window
+#[test]
+fn tiny_images() {
+    let reference = [10, 20, 30, 255, 40, 50, 60, 255, 70, 80, 90, 255];
+    let test = [12, 20, 30, 255, 40, 50, 60, 255, 70, 80, 90, 255];
+
+    let metrics = Metrics::from_rgba(&reference, &test, 3, 1, Weighting::Uniform).unwrap();
+
+    assert!(metrics.overall.rmse > 0.0);
+    assert!(metrics.overall.ssim > 0.9 && metrics.overall.ssim < 1.0);
+}
+
+#[test]
+fn encoded_texture() {
+    let rgba: Vec<u8> = (0..32 * 32)
+        .flat_map(|i| {
+            let (x, y) = ((i % 32) as u8, (i / 32) as u8);
+            [x * 8, y * 8, 128, 255]
+        })
+        .collect();
+
+    let metrics = |quality| {
+        let image = Image::encode_rgba(&rgba, 32, 32, ImageFormat::DXT1, quality).unwrap();
+        Metrics::from_rgba(&rgba, &image.decode_rgba(), 32, 32, Weighting::Uniform).unwrap()
+    };
+
+    let range_fit = metrics(EncodeQuality::RangeFit);
+    let cluster_fit = metrics(EncodeQuality::ClusterFit);
+
+    // Alpha is untouched by opaque DXT1
+    assert_eq!(cluster_fit.channels[3].rmse, 0.0);
+    assert!(cluster_fit.overall.psnr > 30.0, "{:?}", cluster_fit);
+
+    // Cluster fit only replaces a block's endpoints when it lowers the error
+    assert!(cluster_fit.overall.rmse <= range_fit.overall.rmse);
+    assert!(cluster_fit.overall.psnr >= range_fit.overall.psnr);
+}
+
+#[test]
+fn short_buffers() {
+    let rgba = [0u8; 16];
+
+    let error = Metrics::from_rgba(&rgba, &rgba[..12], 2, 2, Weighting::Uniform).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InsufficientData {
+                expected: 16,
+                actual: 12
+            }
+        ),
+        "{:?}",
+        error
+    );
+}
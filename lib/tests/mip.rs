@@ -0,0 +1,127 @@
+use anyxplore::{
+    Error,
+    format::image::{
+        ImageFormat,
+        mip::{MipChain, MipFilter, downsample},
+        raw::PixelFormat,
+        swizzle::Layout,
+    },
+};
+
+#[test]
+fn non_square_levels() {
+    // Once the short side reaches 1 it stays there while the long side keeps halving
+    assert_eq!(MipChain::full_level_count(16, 4), 5);
+    assert_eq!(
+        (0..5)
+            .map(|level| MipChain::level_dimensions(16, 4, level))
+            .collect::<Vec<_>>(),
+        [(16, 4), (8, 2), (4, 1), (2, 1), (1, 1)]
+    );
+
+    assert_eq!(MipChain::full_level_count(1, 8), 4);
+    assert_eq!(
+        (0..4)
+            .map(|level| MipChain::level_dimensions(1, 8, level))
+            .collect::<Vec<_>>(),
+        [(1, 8), (1, 4), (1, 2), (1, 1)]
+    );
+
+    // Past the end of the chain, including shifts wider than the type
+    assert_eq!(MipChain::level_dimensions(16, 4, 9), (1, 1));
+    assert_eq!(MipChain::level_dimensions(16, 4, 40), (1, 1));
+}
+
+#[test]
+fn level_slicing() {
+    let format = ImageFormat::Raw(PixelFormat::L8, Layout::Linear);
+
+    // 4x2, 2x1 and 1x1 texels at a byte each
+    let bytes: Vec<u8> = (0..11).collect();
+    let chain = MipChain::from_bytes(&bytes, 4, 2, format, 3).unwrap();
+
+    assert_eq!(chain.level_count(), 3);
+    assert_eq!(chain.level_bytes(0).unwrap(), [0, 1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(chain.level_bytes(1).unwrap(), [8, 9]);
+    assert_eq!(chain.level_bytes(2).unwrap(), [10]);
+    assert!(chain.level_bytes(3).is_none());
+    assert_eq!(chain.to_bytes(), bytes);
+
+    // Levels smaller than a block still take up a whole block
+    let chain = MipChain::from_bytes(&[0; 56], 8, 8, ImageFormat::DXT1, 4).unwrap();
+    assert_eq!(
+        (0..4)
+            .map(|level| chain.level_bytes(level).unwrap().len())
+            .collect::<Vec<_>>(),
+        [32, 8, 8, 8]
+    );
+
+    // Trailing bytes past the last level are ignored
+    assert!(MipChain::from_bytes(&[0; 60], 8, 8, ImageFormat::DXT1, 4).is_ok());
+
+    let error = MipChain::from_bytes(&bytes[..10], 4, 2, format, 3).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InsufficientData {
+                expected: 11,
+                actual: 10
+            }
+        ),
+        "{:?}",
+        error
+    );
+}
+
+// 8x8 grey image, black on the left half and white on the right
+fn step_edge() -> Vec<u8> {
+    (0..64)
+        .flat_map(|i| {
+            let value = if i % 8 < 4 { 0 } else { 255 };
+            [value, value, value, 255]
+        })
+        .collect()
+}
+
+fn red_row(rgba: &[u8], width: usize) -> Vec<u8> {
+    rgba[..width * 4].iter().step_by(4).copied().collect()
+}
+
+#[test]
+fn box_and_kaiser_filters() {
+    // A box filter averages each 2x2 square exactly
+    let checkerboard: Vec<u8> = (0..16)
+        .flat_map(|i| {
+            let value = if (i % 4 + i / 4) % 2 == 0 { 0 } else { 255 };
+            [value, 10, 20, 255]
+        })
+        .collect();
+
+    let halved = downsample(&checkerboard, 4, 4, 2, 2, MipFilter::Box);
+    assert_eq!(halved, [128, 10, 20, 255].repeat(4));
+
+    // The box filter never looks across a 2x2 square, so the edge stays hard
+    let halved = downsample(&step_edge(), 8, 8, 4, 4, MipFilter::Box);
+    assert_eq!(red_row(&halved, 4), [0, 0, 255, 255]);
+
+    // Kaiser reaches further, which softens the texels either side of the edge evenly
+    let halved = downsample(&step_edge(), 8, 8, 4, 4, MipFilter::Kaiser);
+    let row = red_row(&halved, 4);
+
+    assert!(row[1] > 0 && row[2] < 255, "{:?}", row);
+    assert_eq!(row[1] as u32 + row[2] as u32, 255);
+    assert_eq!(row[0] as u32 + row[3] as u32, 255);
+
+    // Rows are all alike, and flat channels stay flat
+    assert!(halved.chunks_exact(16).all(|r| r == &halved[..16]));
+    assert!(halved.chunks_exact(4).all(|t| t[3] == 255));
+
+    // Both filters keep a constant image constant
+    let flat = [90, 180, 45, 200].repeat(64);
+    for filter in [MipFilter::Box, MipFilter::Kaiser] {
+        assert_eq!(
+            downsample(&flat, 8, 8, 4, 4, filter),
+            [90, 180, 45, 200].repeat(16)
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use anyxplore::{
+    Error,
+    format::image::{
+        p8::{MAX_PALETTE_SIZE, P8Image},
+        swizzle::Layout,
+    },
+};
+
+// Red, green, blue and a half transparent grey, stored A8R8G8B8 as the Xbox does (B G R A)
+const PALETTE: [u8; 16] = [
+    0, 0, 255, 255, //
+    0, 255, 0, 255, //
+    255, 0, 0, 255, //
+    128, 128, 128, 128,
+];
+
+#[test]
+fn decode_known_palette() {
+    let image = P8Image::from_bytes(&[0, 1, 2, 3], &PALETTE, 2, 2, Layout::Linear).unwrap();
+
+    assert_eq!(
+        image.as_rgba_bytes(),
+        [
+            255, 0, 0, 255, //
+            0, 255, 0, 255, //
+            0, 0, 255, 255, //
+            128, 128, 128, 128,
+        ]
+    );
+    assert_eq!(image.palette_to_bytes(), PALETTE);
+
+    // Indices past the end of the palette decode as transparent black
+    let image = P8Image::from_bytes(&[3, 4, 255, 0], &PALETTE, 2, 2, Layout::Linear).unwrap();
+    assert_eq!(&image.as_rgba_bytes()[4..12], [0; 8]);
+
+    // Swizzled surfaces are unswizzled on load, for 4x4 rows 0 and 1 share the first 8 bytes
+    let swizzled = [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 3, 3, 2, 2, 3, 3];
+    let image = P8Image::from_bytes(&swizzled, &PALETTE, 4, 4, Layout::Swizzled).unwrap();
+    assert_eq!(
+        image.indices(),
+        [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3]
+    );
+    assert_eq!(image.to_bytes(Layout::Swizzled).unwrap(), swizzled);
+}
+
+#[test]
+fn median_cut() {
+    // Two clusters of two shades each, which only fit as one entry per cluster
+    let texels: [[u8; 4]; 4] = [
+        [250, 0, 0, 255],
+        [0, 0, 250, 255],
+        [254, 0, 0, 255],
+        [0, 0, 254, 255],
+    ];
+    let rgba: Vec<u8> = texels.iter().flatten().copied().collect();
+
+    let image = P8Image::from_rgba(&rgba, 2, 2, 2).unwrap();
+
+    // Each cluster contributes its average
+    let palette = image.palette();
+    assert_eq!(palette.len(), 2);
+    assert!(palette.contains(&[252, 0, 0, 255]));
+    assert!(palette.contains(&[0, 0, 252, 255]));
+
+    let red = palette.iter().position(|c| c[0] == 252).unwrap() as u8;
+    assert_eq!(image.indices(), [red, 1 - red, red, 1 - red]);
+
+    // With room for every colour the palette is exact
+    let image = P8Image::from_rgba(&rgba, 2, 2, 4).unwrap();
+    assert_eq!(image.as_rgba_bytes(), rgba);
+}
+
+#[test]
+fn palette_size_cap() {
+    // 1024 distinct colours
+    let rgba: Vec<u8> = (0..32 * 32u32)
+        .flat_map(|i| {
+            [
+                (i % 32 * 8) as u8,
+                (i / 32 * 8) as u8,
+                (i % 7 * 30) as u8,
+                255,
+            ]
+        })
+        .collect();
+
+    let image = P8Image::from_rgba(&rgba, 32, 32, MAX_PALETTE_SIZE).unwrap();
+    assert_eq!(image.palette().len(), MAX_PALETTE_SIZE);
+
+    for size in [0, MAX_PALETTE_SIZE + 1] {
+        assert!(matches!(
+            P8Image::from_rgba(&rgba, 32, 32, size),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    assert!(matches!(
+        P8Image::from_rgba_with_palette(&rgba, 32, 32, vec![[0; 4]; MAX_PALETTE_SIZE + 1]),
+        Err(Error::InvalidArgument(_))
+    ));
+
+    // Palettes on disk are cut off at the largest size an index can address
+    let image = P8Image::from_bytes(&[0; 4], &[7; 2048], 2, 2, Layout::Linear).unwrap();
+    assert_eq!(image.palette().len(), MAX_PALETTE_SIZE);
+}
+
+#[test]
+fn palette_edits() {
+    let mut image = P8Image::from_bytes(&[0, 1, 1, 0], &PALETTE, 2, 2, Layout::Linear).unwrap();
+
+    image.palette_mut()[1] = [9, 8, 7, 6];
+    assert_eq!(&image.as_rgba_bytes()[4..8], [9, 8, 7, 6]);
+    assert_eq!(&image.as_rgba_bytes()[8..12], [9, 8, 7, 6]);
+}
@@ -0,0 +1,310 @@
+mod common;
+
+use anyxplore::format::image::{
+    Image, ImageFormat,
+    alpha::AlphaMode,
+    codec::ImageCodec,
+    encoder::{EncodeQuality, PUNCH_THROUGH_THRESHOLD},
+    raw::PixelFormat,
+    swizzle::Layout,
+};
+use common::{max_channel_error, max_error};
+use proptest::prelude::*;
+
+// Rounding to 5 bits is off by at most 4, and bit replication can add 1 more
+const COLOUR_BOUND: u8 = 5;
+
+// Rounding to 4 bits of explicit alpha
+const EXPLICIT_ALPHA_BOUND: u8 = 8;
+
+fn round_trip(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    format: ImageFormat,
+    quality: EncodeQuality,
+) -> Image {
+    let image = Image::encode_rgba(rgba, width, height, format, quality).unwrap();
+    let bytes = image.to_surface_bytes().unwrap();
+
+    assert_eq!(bytes.len(), format.surface_size(width, height));
+
+    match &image {
+        Image::P8(image) => format
+            .decode(&bytes, width, height, Some(&image.palette_to_bytes()))
+            .unwrap(),
+        _ => format.decode(&bytes, width, height, None).unwrap(),
+    }
+}
+
+// DXT2 and DXT4 are compared before unpremultiplying, which would otherwise magnify the error of
+// texels with low alpha
+fn decode_premultiplied(image: &Image) -> Vec<u8> {
+    match image {
+        Image::DXT2(image) => image.as_rgba_bytes_with(AlphaMode::Premultiplied),
+        Image::DXT4(image) => image.as_rgba_bytes_with(AlphaMode::Premultiplied),
+        image => image.decode_rgba(),
+    }
+}
+
+fn premultiply(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4)
+        .flat_map(|t| {
+            let a = t[3] as u32;
+            let mul = |c: u8| ((c as u32 * a + 127) / 255) as u8;
+            [mul(t[0]), mul(t[1]), mul(t[2]), t[3]]
+        })
+        .collect()
+}
+
+fn quality() -> impl Strategy<Value = EncodeQuality> {
+    prop_oneof![
+        Just(EncodeQuality::RangeFit),
+        Just(EncodeQuality::ClusterFit)
+    ]
+}
+
+fn dxt_format() -> impl Strategy<Value = ImageFormat> {
+    prop_oneof![
+        Just(ImageFormat::DXT1),
+        Just(ImageFormat::DXT2),
+        Just(ImageFormat::DXT3),
+        Just(ImageFormat::DXT4),
+        Just(ImageFormat::DXT5),
+    ]
+}
+
+// Width, height and RGBA8 bytes of a random image up to `max` texels on each side
+fn image(max: u32) -> impl Strategy<Value = (u32, u32, Vec<u8>)> {
+    (1..=max, 1..=max).prop_flat_map(rgba)
+}
+
+fn rgba((width, height): (u32, u32)) -> impl Strategy<Value = (u32, u32, Vec<u8>)> {
+    (
+        Just(width),
+        Just(height),
+        prop::collection::vec(any::<u8>(), (width * height * 4) as usize),
+    )
+}
+
+// A random image with a layout for it, swizzled images always have power of two sides
+fn layout_image() -> impl Strategy<Value = (Layout, (u32, u32, Vec<u8>))> {
+    prop_oneof![
+        (Just(Layout::Linear), image(16)),
+        (
+            Just(Layout::Swizzled),
+            (0..=4u32, 0..=4u32).prop_flat_map(|(x, y)| rgba((1 << x, 1 << y)))
+        ),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn solid_colour(
+        format in dxt_format(),
+        quality in quality(),
+        texel in any::<[u8; 4]>(),
+        width in 1..=9u32,
+        height in 1..=9u32,
+    ) {
+        let texel = match format {
+            // Keep DXT1 opaque, punch-through has its own property
+            ImageFormat::DXT1 => [texel[0], texel[1], texel[2], 255],
+            _ => texel,
+        };
+
+        let rgba = texel.repeat((width * height) as usize);
+        let image = round_trip(&rgba, width, height, format, quality);
+
+        let expected = match format {
+            ImageFormat::DXT2 | ImageFormat::DXT4 => premultiply(&rgba),
+            _ => rgba,
+        };
+        let error = max_channel_error(&expected, &decode_premultiplied(&image));
+
+        prop_assert!(error[..3].iter().all(|&e| e <= COLOUR_BOUND), "{:?}", error);
+
+        let alpha_bound = match format {
+            ImageFormat::DXT2 | ImageFormat::DXT3 => EXPLICIT_ALPHA_BOUND,
+            _ => 0,
+        };
+        prop_assert!(error[3] <= alpha_bound, "{:?}", error);
+    }
+
+    // A block made of any two opaque colours only needs the endpoints
+    #[test]
+    fn two_colours(
+        format in prop_oneof![
+            Just(ImageFormat::DXT1),
+            Just(ImageFormat::DXT3),
+            Just(ImageFormat::DXT5),
+        ],
+        quality in quality(),
+        a in any::<[u8; 3]>(),
+        b in any::<[u8; 3]>(),
+        mask in any::<u16>(),
+    ) {
+        let rgba: Vec<u8> = (0..16)
+            .flat_map(|i| {
+                let c = if mask >> i & 1 == 1 { a } else { b };
+                [c[0], c[1], c[2], 255]
+            })
+            .collect();
+
+        let decoded = round_trip(&rgba, 4, 4, format, quality).decode_rgba();
+
+        prop_assert!(max_error(&rgba, &decoded) <= COLOUR_BOUND);
+    }
+
+    #[test]
+    fn dxt1_punch_through((width, height, rgba) in image(12), quality in quality()) {
+        let decoded = round_trip(&rgba, width, height, ImageFormat::DXT1, quality).decode_rgba();
+
+        for (input, output) in rgba.chunks_exact(4).zip(decoded.chunks_exact(4)) {
+            if input[3] < PUNCH_THROUGH_THRESHOLD {
+                prop_assert_eq!(output, &[0, 0, 0, 0]);
+            } else {
+                prop_assert_eq!(output[3], 255);
+            }
+        }
+    }
+
+    #[test]
+    fn explicit_alpha((width, height, rgba) in image(12)) {
+        let decoded = round_trip(&rgba, width, height, ImageFormat::DXT3, EncodeQuality::RangeFit)
+            .decode_rgba();
+
+        prop_assert!(max_channel_error(&rgba, &decoded)[3] <= EXPLICIT_ALPHA_BOUND);
+    }
+
+    // The encoder only picks six alpha mode when its total squared error is lower than eight alpha
+    // mode over the full range, where no texel is more than half a step out. Every texel is
+    // therefore within two steps.
+    #[test]
+    fn interpolated_alpha(alpha in any::<[u8; 16]>()) {
+        let rgba: Vec<u8> = alpha.iter().flat_map(|&a| [255, 255, 255, a]).collect();
+
+        let decoded = round_trip(&rgba, 4, 4, ImageFormat::DXT5, EncodeQuality::RangeFit)
+            .decode_rgba();
+
+        let min = *alpha.iter().min().unwrap() as u32;
+        let max = *alpha.iter().max().unwrap() as u32;
+        let bound = (2 * (max - min)).div_ceil(7) + 1;
+
+        prop_assert!(max_channel_error(&rgba, &decoded)[3] as u32 <= bound);
+    }
+
+    #[test]
+    fn interpolated_alpha_two_values(a in any::<u8>(), b in any::<u8>(), mask in any::<u16>()) {
+        let rgba: Vec<u8> = (0..16)
+            .flat_map(|i| [0, 0, 0, if mask >> i & 1 == 1 { a } else { b }])
+            .collect();
+
+        let decoded = round_trip(&rgba, 4, 4, ImageFormat::DXT5, EncodeQuality::RangeFit)
+            .decode_rgba();
+
+        prop_assert_eq!(max_channel_error(&rgba, &decoded)[3], 0);
+    }
+
+    #[test]
+    fn raw_formats((layout, (width, height, rgba)) in layout_image()) {
+        // Per channel bounds from each format's bit depth, None where the channel isn't stored
+        let formats: [(PixelFormat, [Option<u8>; 4]); 6] = [
+            (PixelFormat::A8R8G8B8, [Some(0); 4]),
+            (PixelFormat::X8R8G8B8, [Some(0), Some(0), Some(0), None]),
+            (PixelFormat::R5G6B5, [Some(4), Some(2), Some(4), None]),
+            (PixelFormat::X1R5G5B5, [Some(4), Some(4), Some(4), None]),
+            (PixelFormat::A1R5G5B5, [Some(4), Some(4), Some(4), Some(127)]),
+            (PixelFormat::A4R4G4B4, [Some(8); 4]),
+        ];
+
+        for (format, bounds) in formats {
+            let decoded = round_trip(
+                &rgba,
+                width,
+                height,
+                ImageFormat::Raw(format, layout),
+                EncodeQuality::default(),
+            )
+            .decode_rgba();
+
+            let error = max_channel_error(&rgba, &decoded);
+
+            for ch in 0..4 {
+                match bounds[ch] {
+                    Some(bound) => prop_assert!(error[ch] <= bound, "{:?} {:?}", format, error),
+                    None => prop_assert!(decoded.chunks_exact(4).all(|t| t[ch] == 255)),
+                }
+            }
+        }
+    }
+
+    // Decoded output is already representable, so encoding it again must be lossless
+    #[test]
+    fn raw_formats_are_stable((width, height, rgba) in image(8)) {
+        let formats = [
+            PixelFormat::R5G6B5,
+            PixelFormat::X1R5G5B5,
+            PixelFormat::A1R5G5B5,
+            PixelFormat::A4R4G4B4,
+            PixelFormat::L8,
+            PixelFormat::A8,
+        ];
+
+        for format in formats {
+            let format = ImageFormat::Raw(format, Layout::Linear);
+
+            let once = round_trip(&rgba, width, height, format, EncodeQuality::default())
+                .decode_rgba();
+            let twice = round_trip(&once, width, height, format, EncodeQuality::default())
+                .decode_rgba();
+
+            prop_assert_eq!(once, twice, "{:?}", format);
+        }
+    }
+
+    // Up to 256 colours fit in the palette exactly
+    #[test]
+    fn p8_exact((layout, (width, height, rgba)) in layout_image()) {
+        let decoded = round_trip(
+            &rgba,
+            width,
+            height,
+            ImageFormat::P8(layout),
+            EncodeQuality::default(),
+        )
+        .decode_rgba();
+
+        prop_assert_eq!(rgba, decoded);
+    }
+}
+
+// Colours whose difference is orthogonal to (1, 1, 1) used to collapse the principal axis, leaving
+// both endpoints on one colour
+#[test]
+fn two_colours_orthogonal_to_grey() {
+    let rgba: Vec<u8> = (0..16)
+        .flat_map(|i| {
+            if i % 2 == 0 {
+                [200, 100, 150, 255]
+            } else {
+                [100, 200, 150, 255]
+            }
+        })
+        .collect();
+
+    for format in [ImageFormat::DXT1, ImageFormat::DXT3, ImageFormat::DXT5] {
+        for quality in [EncodeQuality::RangeFit, EncodeQuality::ClusterFit] {
+            let decoded = round_trip(&rgba, 4, 4, format, quality).decode_rgba();
+
+            assert!(
+                max_error(&rgba, &decoded) <= COLOUR_BOUND,
+                "{:?} {:?}",
+                format,
+                quality
+            );
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use anyxplore::format::image::types::{R5G6B5Colour, RGBColour};
+
+// The expansion used by D3D and other reference decoders, shifting the value up and repeating its
+// top bits below it
+fn reference(value: u16, bits: u32) -> u8 {
+    let value = value as u32;
+    ((value << (8 - bits)) | (value >> (2 * bits - 8))) as u8
+}
+
+#[test]
+fn expansion_matches_reference() {
+    for raw in 0..=u16::MAX {
+        let colour = RGBColour::from(R5G6B5Colour::from(raw));
+
+        assert_eq!(colour.r, reference(raw >> 11, 5), "red of {:#06x}", raw);
+        assert_eq!(
+            colour.g,
+            reference((raw >> 5) & 0x3F, 6),
+            "green of {:#06x}",
+            raw
+        );
+        assert_eq!(colour.b, reference(raw & 0x1F, 5), "blue of {:#06x}", raw);
+    }
+}
+
+#[test]
+fn expansion_is_close_to_exact_scaling() {
+    for raw in 0..=u16::MAX {
+        let colour = R5G6B5Colour::from(raw);
+        let expanded = RGBColour::from(colour);
+
+        for (channel, value, bits) in [
+            (expanded.r, colour.r(), 5),
+            (expanded.g, colour.g(), 6),
+            (expanded.b, colour.b(), 5),
+        ] {
+            let exact = value as f32 * 255.0 / ((1u32 << bits) - 1) as f32;
+            assert!((channel as f32 - exact).abs() < 1.0, "{:#06x}", raw);
+        }
+    }
+}
+
+#[test]
+fn expansion_covers_full_range() {
+    assert_eq!(
+        RGBColour::from(R5G6B5Colour::from(0x0000)),
+        RGBColour { r: 0, g: 0, b: 0 }
+    );
+    assert_eq!(
+        RGBColour::from(R5G6B5Colour::from(0xFFFF)),
+        RGBColour {
+            r: 255,
+            g: 255,
+            b: 255
+        }
+    );
+    assert_eq!(
+        RGBColour::from(R5G6B5Colour::from(0xF800)),
+        RGBColour { r: 255, g: 0, b: 0 }
+    );
+    assert_eq!(
+        RGBColour::from(R5G6B5Colour::from(0x07E0)),
+        RGBColour { r: 0, g: 255, b: 0 }
+    );
+    assert_eq!(
+        RGBColour::from(R5G6B5Colour::from(0x001F)),
+        RGBColour { r: 0, g: 0, b: 255 }
+    );
+}
+
+#[test]
+fn every_value_round_trips() {
+    for raw in 0..=u16::MAX {
+        let colour = RGBColour::from(R5G6B5Colour::from(raw));
+
+        assert_eq!(R5G6B5Colour::from(colour).raw(), raw, "{:#06x}", raw);
+    }
+}
+
+#[test]
+fn quantisation_rounds_to_nearest() {
+    for value in 0..=u8::MAX {
+        let colour = R5G6B5Colour::from(RGBColour {
+            r: value,
+            g: value,
+            b: value,
+        });
+
+        for (quantised, bits) in [(colour.r(), 5), (colour.g(), 6), (colour.b(), 5)] {
+            let max = (1u32 << bits) - 1;
+            let error = |q: u32| (q as f32 * 255.0 / max as f32 - value as f32).abs();
+
+            // No other representable value is closer
+            for other in 0..=max {
+                assert!(
+                    error(quantised as u32) <= error(other),
+                    "{} quantised to {} of {}, but {} is closer",
+                    value,
+                    quantised,
+                    max,
+                    other
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn byte_order() {
+    for raw in 0..=u16::MAX {
+        let le = raw.to_le_bytes();
+        let be = raw.to_be_bytes();
+
+        assert_eq!(R5G6B5Colour::from_le(le).raw(), raw);
+        assert_eq!(R5G6B5Colour::from_be(be).raw(), raw);
+        assert_eq!(R5G6B5Colour::from_le(le).to_le_bytes(), le);
+        assert_eq!(R5G6B5Colour::from_be(be).to_be_bytes(), be);
+    }
+
+    // Pure red as stored on disk by a DXT block
+    let red = R5G6B5Colour::from_le([0x00, 0xF8]);
+    assert_eq!((red.r(), red.g(), red.b()), (31, 0, 0));
+}
@@ -0,0 +1,350 @@
+mod common;
+
+use anyxplore::format::image::{
+    ImageFormat, alpha::AlphaMode, codec::ImageCodec, dxt2::DXT2, dxt4::DXT4,
+};
+use common::{XorShift, reference_decode};
+
+fn decode(format: ImageFormat, bytes: &[u8], width: u32, height: u32) -> Vec<u8> {
+    format
+        .decode(bytes, width, height, None)
+        .unwrap()
+        .decode_rgba()
+}
+
+// Expands one RGBA texel per row into a whole 4x4 block
+fn rows(rows: [[u8; 4]; 4]) -> Vec<u8> {
+    rows.iter().flat_map(|texel| texel.repeat(4)).collect()
+}
+
+// Red and blue endpoints in four colour mode, with each row using a different palette entry
+const DXT1_FOUR_COLOUR: [u8; 8] = [0x00, 0xF8, 0x1F, 0x00, 0x00, 0x55, 0xAA, 0xFF];
+
+// The same endpoints swapped select three colour mode, where the last entry is transparent black
+const DXT1_THREE_COLOUR: [u8; 8] = [0x1F, 0x00, 0x00, 0xF8, 0x00, 0x55, 0xAA, 0xFF];
+
+// Equal endpoints also select three colour mode, every texel here picks the transparent entry
+const DXT1_EQUAL_ENDPOINTS: [u8; 8] = [0xE0, 0x07, 0xE0, 0x07, 0xFF, 0xFF, 0xFF, 0xFF];
+
+// Pure green from the first endpoint, which only decodes right if the 6 bit channel does
+const DXT1_GREEN: [u8; 8] = [0xE0, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+// Pure red
+const DXT1_RED: [u8; 8] = [0x00, 0xF8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+#[test]
+fn dxt1_four_colour() {
+    let block = DXT1_FOUR_COLOUR;
+
+    let expected = rows([
+        [255, 0, 0, 255],
+        [0, 0, 255, 255],
+        [170, 0, 85, 255],
+        [85, 0, 170, 255],
+    ]);
+
+    assert_eq!(decode(ImageFormat::DXT1, &block, 4, 4), expected);
+}
+
+#[test]
+fn dxt1_three_colour() {
+    let block = DXT1_THREE_COLOUR;
+
+    let expected = rows([
+        [0, 0, 255, 255],
+        [255, 0, 0, 255],
+        [127, 0, 127, 255],
+        [0, 0, 0, 0],
+    ]);
+
+    assert_eq!(decode(ImageFormat::DXT1, &block, 4, 4), expected);
+}
+
+#[test]
+fn dxt1_equal_endpoints() {
+    assert_eq!(
+        decode(ImageFormat::DXT1, &DXT1_EQUAL_ENDPOINTS, 4, 4),
+        [0, 0, 0, 0].repeat(16)
+    );
+}
+
+#[test]
+fn dxt1_green() {
+    assert_eq!(
+        decode(ImageFormat::DXT1, &DXT1_GREEN, 4, 4),
+        [0, 255, 0, 255].repeat(16)
+    );
+}
+
+// Blocks are stored in rows, left to right, so a red block followed by a green one sits beside
+// it on a wide surface and above it on a tall one
+#[test]
+fn dxt1_block_order() {
+    let blocks = [DXT1_RED, DXT1_GREEN].concat();
+    let (red, green) = ([255, 0, 0, 255], [0, 255, 0, 255]);
+
+    let wide: Vec<u8> = [red.repeat(4), green.repeat(4)].concat().repeat(4);
+    assert_eq!(decode(ImageFormat::DXT1, &blocks, 8, 4), wide);
+
+    let tall: Vec<u8> = [red.repeat(16), green.repeat(16)].concat();
+    assert_eq!(decode(ImageFormat::DXT1, &blocks, 4, 8), tall);
+}
+
+// Only the top left texel of a 1x1 image is kept
+#[test]
+fn dxt1_partial_block() {
+    let block = [0x00, 0xF8, 0x1F, 0x00, 0xFC, 0xFF, 0xFF, 0xFF];
+
+    assert_eq!(decode(ImageFormat::DXT1, &block, 1, 1), [255, 0, 0, 255]);
+    assert_eq!(
+        decode(ImageFormat::DXT1, &block, 2, 1),
+        [255, 0, 0, 255, 85, 0, 170, 255]
+    );
+}
+
+// Mid grey colour premultiplied by alpha 255, 136, 0 and 68 on each row
+const DXT2_BLOCK: [u8; 16] = [
+    0xFF, 0xFF, 0x88, 0x88, 0x00, 0x00, 0x44, 0x44, 0x10, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[test]
+fn dxt2_straight() {
+    // Fully transparent texels keep their stored colour, and colour brighter than alpha allows is
+    // clamped
+    let expected = rows([
+        [132, 130, 132, 255],
+        [248, 244, 248, 136],
+        [132, 130, 132, 0],
+        [255, 255, 255, 68],
+    ]);
+
+    assert_eq!(decode(ImageFormat::DXT2, &DXT2_BLOCK, 4, 4), expected);
+}
+
+#[test]
+fn dxt2_premultiplied() {
+    let image = DXT2::from_bytes(&DXT2_BLOCK, 4, 4).unwrap();
+
+    let expected = rows([
+        [132, 130, 132, 255],
+        [132, 130, 132, 136],
+        [132, 130, 132, 0],
+        [132, 130, 132, 68],
+    ]);
+
+    assert_eq!(image.as_rgba_bytes_with(AlphaMode::Premultiplied), expected);
+
+    let mut out = vec![0u8; 64];
+    image
+        .decode_into_with(&mut out, AlphaMode::Premultiplied)
+        .unwrap();
+    assert_eq!(out, expected);
+}
+
+// Every explicit alpha value in texel order over a green block
+const DXT3_EXPLICIT_ALPHA: [u8; 16] = [
+    0x10, 0x32, 0x54, 0x76, 0x98, 0xBA, 0xDC, 0xFE, 0xE0, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+// Opaque red, which reads the same as DXT2, DXT3 or DXT4
+const OPAQUE_RED: [u8; 16] = [
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0xF8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[test]
+fn dxt3_explicit_alpha() {
+    let expected: Vec<u8> = (0..16u8).flat_map(|i| [0, 255, 0, i * 17]).collect();
+
+    assert_eq!(
+        decode(ImageFormat::DXT3, &DXT3_EXPLICIT_ALPHA, 4, 4),
+        expected
+    );
+
+    for format in [ImageFormat::DXT2, ImageFormat::DXT3, ImageFormat::DXT4] {
+        assert_eq!(
+            decode(format, &OPAQUE_RED, 4, 4),
+            [255, 0, 0, 255].repeat(16)
+        );
+    }
+}
+
+// Alpha indices 0-7 in order, twice, over a white block
+const fn dxt5_block(alpha_0: u8, alpha_1: u8) -> [u8; 16] {
+    [
+        alpha_0, alpha_1, 0x88, 0xC6, 0xFA, 0x88, 0xC6, 0xFA, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ]
+}
+
+const DXT5_EIGHT_ALPHA: [u8; 16] = dxt5_block(255, 0);
+const DXT5_SIX_ALPHA: [u8; 16] = dxt5_block(0, 255);
+
+fn with_alpha(alpha: [u8; 8]) -> Vec<u8> {
+    alpha
+        .repeat(2)
+        .into_iter()
+        .flat_map(|a| [255, 255, 255, a])
+        .collect()
+}
+
+#[test]
+fn dxt5_eight_alpha() {
+    assert_eq!(
+        decode(ImageFormat::DXT5, &DXT5_EIGHT_ALPHA, 4, 4),
+        with_alpha([255, 0, 218, 182, 145, 109, 72, 36])
+    );
+}
+
+#[test]
+fn dxt5_six_alpha() {
+    assert_eq!(
+        decode(ImageFormat::DXT5, &DXT5_SIX_ALPHA, 4, 4),
+        with_alpha([0, 255, 51, 102, 153, 204, 0, 255])
+    );
+}
+
+// Dark grey premultiplied by a constant alpha of 128
+const DXT4_PREMULTIPLIED: [u8; 16] = [
+    0x80, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x42, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[test]
+fn dxt4_premultiplied() {
+    let block = DXT4_PREMULTIPLIED;
+
+    assert_eq!(
+        decode(ImageFormat::DXT4, &block, 4, 4),
+        [131, 129, 131, 128].repeat(16)
+    );
+
+    let image = DXT4::from_bytes(&block, 4, 4).unwrap();
+    assert_eq!(
+        image.as_rgba_bytes_with(AlphaMode::Premultiplied),
+        [66, 65, 66, 128].repeat(16)
+    );
+}
+
+// Name, format, width, height and the blocks repeated to fill it
+type Fixture = (
+    &'static str,
+    ImageFormat,
+    u32,
+    u32,
+    &'static [&'static [u8]],
+);
+
+// Surfaces in tests/data repeat the hand-built blocks above in order, one block after another
+// until the surface is full, and the .rgba files hold what they decode to. They guard against both
+// decoders drifting together, over surfaces with several blocks and partial blocks at the edges.
+const FIXTURES: &[Fixture] = &[
+    (
+        "dxt1_13x7",
+        ImageFormat::DXT1,
+        13,
+        7,
+        &[
+            &DXT1_FOUR_COLOUR,
+            &DXT1_THREE_COLOUR,
+            &DXT1_EQUAL_ENDPOINTS,
+            &DXT1_GREEN,
+            &DXT1_RED,
+        ],
+    ),
+    (
+        "dxt2_8x8",
+        ImageFormat::DXT2,
+        8,
+        8,
+        &[&DXT2_BLOCK, &OPAQUE_RED],
+    ),
+    (
+        "dxt3_6x10",
+        ImageFormat::DXT3,
+        6,
+        10,
+        &[&DXT3_EXPLICIT_ALPHA, &OPAQUE_RED],
+    ),
+    (
+        "dxt4_5x5",
+        ImageFormat::DXT4,
+        5,
+        5,
+        &[&DXT4_PREMULTIPLIED, &OPAQUE_RED],
+    ),
+    (
+        "dxt5_16x12",
+        ImageFormat::DXT5,
+        16,
+        12,
+        &[&DXT5_EIGHT_ALPHA, &DXT5_SIX_ALPHA],
+    ),
+];
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e))
+}
+
+#[test]
+fn fixtures() {
+    for &(name, format, width, height, blocks) in FIXTURES {
+        let bytes = fixture(&format!("{}.bin", name));
+        let expected = fixture(&format!("{}.rgba", name));
+
+        let built: Vec<u8> = blocks
+            .iter()
+            .cycle()
+            .flat_map(|block| block.iter().copied())
+            .take(format.surface_size(width, height))
+            .collect();
+        assert_eq!(
+            bytes, built,
+            "{} is not made of the hand-built blocks",
+            name
+        );
+
+        assert!(
+            decode(format, &bytes, width, height) == expected,
+            "{} does not match the checked in output",
+            name
+        );
+        assert!(
+            reference_decode(format, &bytes, width, height) == expected,
+            "{} does not match the reference decoder",
+            name
+        );
+    }
+}
+
+// Random blocks cover the palette modes and edge cases hand-built blocks miss
+#[test]
+fn random_blocks_match_reference() {
+    let formats = [
+        ImageFormat::DXT1,
+        ImageFormat::DXT2,
+        ImageFormat::DXT3,
+        ImageFormat::DXT4,
+        ImageFormat::DXT5,
+    ];
+    let sizes = [(4, 4), (1, 1), (3, 9), (17, 5), (32, 32)];
+
+    let mut rng = XorShift::new(0x5EED);
+
+    for format in formats {
+        for (width, height) in sizes {
+            for _ in 0..8 {
+                let bytes = rng.bytes(format.surface_size(width, height));
+
+                assert!(
+                    decode(format, &bytes, width, height)
+                        == reference_decode(format, &bytes, width, height),
+                    "{:?} {} x {} differs from the reference decoder for {:02X?}",
+                    format,
+                    width,
+                    height,
+                    bytes
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use anyxplore::format::image::{
+    ImageFormat, raw::PixelFormat, shape::TextureShape, swizzle::Layout,
+};
+
+const L8: ImageFormat = ImageFormat::Raw(PixelFormat::L8, Layout::Linear);
+
+#[test]
+fn plain_textures() {
+    // 4x4, 2x2 and 1x1 levels
+    assert_eq!(
+        TextureShape::from_size(L8, 4, 4, 16),
+        Some(TextureShape::Plain { level_count: 1 })
+    );
+    assert_eq!(
+        TextureShape::from_size(L8, 4, 4, 21),
+        Some(TextureShape::Plain { level_count: 3 })
+    );
+
+    // Each DXT1 level takes at least one 8 byte block
+    assert_eq!(
+        TextureShape::from_size(ImageFormat::DXT1, 8, 8, 32 + 8 + 8 + 8),
+        Some(TextureShape::Plain { level_count: 4 })
+    );
+}
+
+#[test]
+fn cube_maps() {
+    assert_eq!(
+        TextureShape::from_size(L8, 4, 4, 16 * 6),
+        Some(TextureShape::Cube { level_count: 1 })
+    );
+    assert_eq!(
+        TextureShape::from_size(ImageFormat::DXT1, 8, 8, (32 + 8) * 6),
+        Some(TextureShape::Cube { level_count: 2 })
+    );
+
+    // Faces are square
+    assert_eq!(TextureShape::from_size(L8, 8, 2, 16 * 6), None);
+}
+
+#[test]
+fn volumes() {
+    assert_eq!(
+        TextureShape::from_size(L8, 4, 4, 16 * 4),
+        Some(TextureShape::Volume {
+            depth: 4,
+            level_count: 1
+        })
+    );
+
+    // 4x4x4, 2x2x2 and 1x1x1 levels
+    assert_eq!(
+        TextureShape::from_size(L8, 4, 4, 64 + 8 + 1),
+        Some(TextureShape::Volume {
+            depth: 4,
+            level_count: 3
+        })
+    );
+
+    // Depth keeps halving after the sides reach 1: 2x1x8, 1x1x4, 1x1x2, 1x1x1
+    assert_eq!(
+        TextureShape::from_size(L8, 2, 1, 16 + 4 + 2 + 1),
+        Some(TextureShape::Volume {
+            depth: 8,
+            level_count: 4
+        })
+    );
+}
+
+// Sizes that only fit a stack of slices of an unusual count or side aren't taken for volumes
+#[test]
+fn unrecognised() {
+    assert_eq!(TextureShape::from_size(L8, 4, 4, 16 * 3), None);
+    assert_eq!(TextureShape::from_size(L8, 3, 3, 9 * 4), None);
+    assert_eq!(TextureShape::from_size(L8, 4, 4, 17), None);
+    assert_eq!(TextureShape::from_size(L8, 4, 4, 0), None);
+    assert_eq!(TextureShape::from_size(L8, 0, 4, 16), None);
+}
@@ -0,0 +1,171 @@
+use anyxplore::{
+    Error,
+    format::image::{
+        ImageFormat,
+        raw::PixelFormat,
+        swizzle::{
+            Layout, swizzle, swizzle_3d, swizzled_index, swizzled_index_3d, unswizzle, unswizzle_3d,
+        },
+        volume::VolumeTexture,
+    },
+};
+
+// Each texel holds its own linear index, so a swizzled surface spells out where every texel went
+fn indices(width: u32, height: u32) -> Vec<u8> {
+    (0..width * height).map(|i| i as u8).collect()
+}
+
+// Offsets worked out by hand from the nv2a bit interleaving, listed in linear order
+#[test]
+fn known_layouts() {
+    let cases: [(u32, u32, [usize; 16]); 3] = [
+        // x0 y0 x1 y1
+        (4, 4, [0, 1, 4, 5, 2, 3, 6, 7, 8, 9, 12, 13, 10, 11, 14, 15]),
+        // x0 y0 x1 x2, the extra bits of the wider side follow the interleaved ones
+        (8, 2, [0, 1, 4, 5, 8, 9, 12, 13, 2, 3, 6, 7, 10, 11, 14, 15]),
+        // x0 y0 y1 y2, which leaves a two texel wide surface linear
+        (2, 8, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+    ];
+
+    for (width, height, offsets) in cases {
+        for (i, &offset) in offsets.iter().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            assert_eq!(
+                swizzled_index(x, y, width, height),
+                offset,
+                "{}x{} ({}, {})",
+                width,
+                height,
+                x,
+                y
+            );
+        }
+
+        let swizzled = swizzle(&indices(width, height), width, height, 1).unwrap();
+
+        for (i, &offset) in offsets.iter().enumerate() {
+            assert_eq!(swizzled[offset], i as u8, "{}x{}", width, height);
+        }
+    }
+}
+
+#[test]
+fn round_trips() {
+    for (width, height) in [(1, 1), (1, 8), (8, 1), (16, 4), (4, 16), (32, 2), (2, 32)] {
+        for bytes_per_pixel in [1, 2, 4] {
+            let linear: Vec<u8> = (0..width * height * bytes_per_pixel as u32)
+                .map(|i| (i * 7 + i / 251) as u8)
+                .collect();
+
+            let swizzled = swizzle(&linear, width, height, bytes_per_pixel).unwrap();
+            assert_eq!(
+                unswizzle(&swizzled, width, height, bytes_per_pixel).unwrap(),
+                linear,
+                "{}x{} at {} bytes per pixel",
+                width,
+                height,
+                bytes_per_pixel
+            );
+        }
+    }
+}
+
+#[test]
+fn bad_surfaces() {
+    assert!(matches!(
+        swizzle(&[0u8; 12], 3, 4, 1),
+        Err(Error::InvalidDimensions { .. })
+    ));
+    assert!(matches!(
+        unswizzle(&[0u8; 15], 4, 4, 1),
+        Err(Error::InsufficientData { .. })
+    ));
+}
+
+// Volumes interleave a z bit after each pair of x and y bits, while each side has bits left
+#[test]
+fn known_volume_layouts() {
+    let cases = [
+        // x0 y0 z0 x1 y1
+        ((4, 4, 2), (1, 0, 0), 1),
+        ((4, 4, 2), (0, 1, 0), 2),
+        ((4, 4, 2), (0, 0, 1), 4),
+        ((4, 4, 2), (2, 0, 0), 8),
+        ((4, 4, 2), (2, 1, 1), 14),
+        ((4, 4, 2), (3, 3, 1), 31),
+        // x0 y0 z0 x1 z1
+        ((4, 2, 4), (2, 1, 3), 30),
+    ];
+
+    for ((width, height, depth), (x, y, z), offset) in cases {
+        assert_eq!(
+            swizzled_index_3d(x, y, z, width, height, depth),
+            offset,
+            "{}x{}x{} ({}, {}, {})",
+            width,
+            height,
+            depth,
+            x,
+            y,
+            z
+        );
+    }
+
+    // A single slice is swizzled exactly like a 2D texture
+    let linear = indices(8, 4);
+    assert_eq!(
+        swizzle_3d(&linear, 8, 4, 1, 1).unwrap(),
+        swizzle(&linear, 8, 4, 1).unwrap()
+    );
+}
+
+#[test]
+fn volume_round_trips() {
+    for (width, height, depth) in [(2, 2, 2), (4, 4, 2), (8, 2, 4), (1, 1, 8), (2, 16, 4)] {
+        let linear: Vec<u8> = (0..width * height * depth * 2)
+            .map(|i| (i * 13 + i / 251) as u8)
+            .collect();
+
+        let swizzled = swizzle_3d(&linear, width, height, depth, 2).unwrap();
+        assert_eq!(
+            unswizzle_3d(&swizzled, width, height, depth, 2).unwrap(),
+            linear,
+            "{}x{}x{}",
+            width,
+            height,
+            depth
+        );
+    }
+
+    assert!(matches!(
+        swizzle_3d(&[0u8; 24], 2, 4, 3, 1),
+        Err(Error::InvalidDimensions { depth: 3, .. })
+    ));
+}
+
+// Swizzled volumes are unswizzled as a whole, so each slice reads back in row order
+#[test]
+fn swizzled_volumes() {
+    let (width, height, depth) = (4, 4, 2);
+    let linear = indices(width, height * depth);
+
+    let mut swizzled = vec![0u8; linear.len()];
+    for z in 0..depth {
+        for y in 0..height {
+            for x in 0..width {
+                swizzled[swizzled_index_3d(x, y, z, width, height, depth)] =
+                    ((z * height + y) * width + x) as u8;
+            }
+        }
+    }
+
+    let format = ImageFormat::Raw(PixelFormat::L8, Layout::Swizzled);
+    let mut volume = VolumeTexture::from_bytes(&swizzled, width, height, depth, format, 1).unwrap();
+
+    assert_eq!(volume.slice_bytes(0, 0).unwrap(), &linear[..16]);
+    assert_eq!(volume.slice_bytes(1, 0).unwrap(), &linear[16..]);
+    assert_eq!(volume.to_bytes().unwrap(), swizzled);
+
+    volume.set_layout(Layout::Linear).unwrap();
+    assert_eq!(volume.to_bytes().unwrap(), linear);
+}
@@ -0,0 +1,115 @@
+// BNL files mounted as archives of their assets. A BNL file is parsed once when it is opened, the
+// parsed file is kept for viewing and editing assets, and each asset's bytes are a slice of the
+// mapped file.
+
+use std::{
+    any::Any,
+    ops::Range,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use anyxplore::{
+    Error,
+    file::{
+        FileBytes, VirtualFile,
+        archive::ArchiveReader,
+        table::{TableArchive, TableEntry},
+    },
+};
+use bnl::{BNLFile, asset::AssetDescription};
+
+pub struct BnlArchive {
+    table: TableArchive,
+    bnl_file: Mutex<BNLFile>,
+}
+
+impl BnlArchive {
+    // The archive's reader, opening it if it was closed
+    pub fn from_file(file: &dyn VirtualFile) -> Result<Arc<BnlArchive>, Error> {
+        let reader = file.archive_reader()?.ok_or_else(|| {
+            Error::InvalidArgument(format!("{} is not an archive.", file.path().display()))
+        })?;
+
+        (reader as Arc<dyn Any + Send + Sync>)
+            .downcast::<BnlArchive>()
+            .map_err(|_| {
+                Error::InvalidArgument(format!("{} is not a BNL file.", file.path().display()))
+            })
+    }
+
+    // For viewing, edits go to a copy from `into_edit`
+    pub fn bnl_file(&self) -> MutexGuard<'_, BNLFile> {
+        self.bnl_file.lock().unwrap()
+    }
+
+    // A copy to edit and then write, so an edit that fails to save leaves the parsed file as it
+    // was. Takes the archive so it is released before the write, as Windows can't replace a file
+    // while it is mapped. Writing closes the archive, which then parses the new file.
+    pub fn into_edit(self: Arc<Self>) -> BNLFile {
+        self.bnl_file().clone()
+    }
+}
+
+impl ArchiveReader for BnlArchive {
+    fn entry_names(&self) -> Vec<String> {
+        self.table.entry_names()
+    }
+
+    fn read_entry(&self, name: &str) -> Result<Vec<u8>, Error> {
+        self.table.read_entry(name)
+    }
+
+    fn map_entry(&self, name: &str) -> Result<FileBytes, Error> {
+        self.table.map_entry(name)
+    }
+
+    fn entry_size(&self, name: &str) -> Result<u64, Error> {
+        self.table.entry_size(name)
+    }
+
+    fn entry_format(&self, name: &str) -> Option<String> {
+        self.table.entry_format(name)
+    }
+
+    // The parsed file holds about as much again as the mapped one
+    fn memory_size(&self) -> usize {
+        self.table.memory_size() + self.table.bytes().len()
+    }
+}
+
+pub fn open_bnl(bytes: FileBytes) -> Result<Box<dyn ArchiveReader>, Error> {
+    let bnl_file = BNLFile::from_bytes(&bytes)
+        .map_err(|e| Error::InvalidData(format!("Unable to read BNL file: {:?}", e)))?;
+
+    let entries = bnl_file
+        .asset_descriptions()
+        .iter()
+        .map(|desc| TableEntry {
+            name: desc.name().to_string(),
+            range: asset_range(desc),
+            format: asset_format(desc),
+        })
+        .collect();
+
+    Ok(Box::new(BnlArchive {
+        table: TableArchive::new(bytes, entries)?,
+        bnl_file: Mutex::new(bnl_file),
+    }))
+}
+
+// Assets are mounted by their extension if they have one, like packs stored as .bnl assets, and
+// otherwise by their asset type, e.g. "resmodel" for models
+fn asset_format(desc: &AssetDescription) -> Option<String> {
+    if Path::new(desc.name()).extension().is_some() {
+        return None;
+    }
+
+    Some(format!("{:?}", desc.asset_type()).to_ascii_lowercase())
+}
+
+// Where an asset's descriptor is stored in the file
+fn asset_range(desc: &AssetDescription) -> Range<usize> {
+    let start = desc.descriptor_ptr() as usize;
+    start..start + desc.descriptor_size() as usize
+}
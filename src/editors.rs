@@ -1,5 +1,15 @@
 use std::io::{Cursor, Read};
 
+use anyxplore::format::image::{
+    Image, ImageFormat,
+    codec::ImageCodec,
+    cube::{CubeFace, CubeMap},
+    metrics::{ChannelMetrics, Metrics, Weighting},
+    raw::PixelFormat,
+    shape::TextureShape,
+    swizzle::Layout,
+    volume::VolumeTexture,
+};
 use bnl::{
     asset::{
         Asset,
@@ -120,17 +130,28 @@ impl Viewable for TextureData {
 
         descriptor.create_viewer(ctx)?;
 
-        if let Ok(rgba) = self.to_rgba_image() {
-            let color_image =
-                ColorImage::from_rgba_unmultiplied([rgba.width(), rgba.height()], rgba.bytes());
+        let index = ctx.next_viewer_index();
 
-            let texture: TextureHandle = ctx.ui.ctx().load_texture(
-                "some texture",
-                color_image,
-                egui::TextureOptions::LINEAR,
+        if let Some(layered) = LayeredTexture::from_texture(descriptor, self.bytes()) {
+            let selected = surface_selector(
+                ctx.ui,
+                egui::Id::new(("texture_surface", index)),
+                &layered.surface_names(),
             );
 
-            ctx.ui.image(&texture);
+            match layered.decode(selected) {
+                Ok(image) => show_rgba(
+                    ctx.ui,
+                    [image.width() as usize, image.height() as usize],
+                    &image.decode_rgba(),
+                ),
+                Err(error) => {
+                    ctx.ui
+                        .label(format!("Error creating image view: {}", error));
+                }
+            }
+        } else if let Ok(rgba) = self.to_rgba_image() {
+            show_rgba(ctx.ui, [rgba.width(), rgba.height()], rgba.bytes());
         } else {
             ctx.ui.label("Error creating image view.");
         }
@@ -139,6 +160,115 @@ impl Viewable for TextureData {
     }
 }
 
+fn show_rgba(ui: &mut egui::Ui, size: [usize; 2], rgba: &[u8]) {
+    let color_image = ColorImage::from_rgba_unmultiplied(size, rgba);
+
+    let texture: TextureHandle =
+        ui.ctx()
+            .load_texture("some texture", color_image, egui::TextureOptions::LINEAR);
+
+    ui.image(&texture);
+}
+
+// Picks one of several surfaces, remembering the choice between frames
+fn surface_selector(ui: &mut egui::Ui, id: egui::Id, names: &[String]) -> usize {
+    let mut selected = ui
+        .ctx()
+        .data(|data| data.get_temp::<usize>(id))
+        .unwrap_or_default()
+        .min(names.len().saturating_sub(1));
+
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(names.get(selected).map(|n| n.as_str()).unwrap_or_default())
+        .show_ui(ui, |ui| {
+            for (i, name) in names.iter().enumerate() {
+                ui.selectable_value(&mut selected, i, name.as_str());
+            }
+        });
+
+    ui.ctx().data_mut(|data| data.insert_temp(id, selected));
+
+    selected
+}
+
+// Cube maps and volumes hold several surfaces, which bnl only decodes the first of. They are
+// recognised by their size and viewed one face or slice at a time.
+enum LayeredTexture {
+    Cube(CubeMap),
+    Volume(VolumeTexture),
+}
+
+impl LayeredTexture {
+    fn from_texture(descriptor: &TextureDescriptor, bytes: &[u8]) -> Option<LayeredTexture> {
+        let format = image_format(descriptor)?;
+
+        let width = descriptor.width() as u32;
+        let height = descriptor.height() as u32;
+
+        // Texture descriptors don't record a depth or a cube flag, so the shape is worked out from
+        // how many bytes there are
+        match TextureShape::from_size(format, width, height, bytes.len())? {
+            TextureShape::Plain { .. } => None,
+            TextureShape::Cube { level_count } => {
+                CubeMap::from_bytes(bytes, width, format, level_count)
+                    .ok()
+                    .map(LayeredTexture::Cube)
+            }
+            TextureShape::Volume { depth, level_count } => {
+                VolumeTexture::from_bytes(bytes, width, height, depth, format, level_count)
+                    .ok()
+                    .map(LayeredTexture::Volume)
+            }
+        }
+    }
+
+    fn surface_names(&self) -> Vec<String> {
+        match self {
+            LayeredTexture::Cube(_) => CubeFace::ALL
+                .iter()
+                .map(|face| format!("Face {}", face.name()))
+                .collect(),
+            LayeredTexture::Volume(volume) => (0..volume.depth())
+                .map(|slice| format!("Slice {}", slice))
+                .collect(),
+        }
+    }
+
+    fn decode(&self, index: usize) -> Result<Image, anyxplore::Error> {
+        match self {
+            LayeredTexture::Cube(cube) => {
+                let face = CubeFace::ALL
+                    .get(index)
+                    .ok_or(anyxplore::Error::SurfaceOutOfRange {
+                        index: index as u32,
+                        level: 0,
+                    })?;
+
+                cube.decode_face(*face, 0)
+            }
+            LayeredTexture::Volume(volume) => volume.decode_slice(index as u32, 0),
+        }
+    }
+}
+
+// bnl keeps its own list of D3D formats, so they are matched up with anyxplore's by name
+fn image_format(descriptor: &TextureDescriptor) -> Option<ImageFormat> {
+    Some(match format!("{:?}", descriptor.format()).as_str() {
+        "DXT1" => ImageFormat::DXT1,
+        "DXT2" => ImageFormat::DXT2,
+        "DXT3" => ImageFormat::DXT3,
+        "DXT4" => ImageFormat::DXT4,
+        "DXT5" => ImageFormat::DXT5,
+        // Uncompressed Xbox textures are swizzled
+        "A8R8G8B8" => ImageFormat::Raw(PixelFormat::A8R8G8B8, Layout::Swizzled),
+        "X8R8G8B8" => ImageFormat::Raw(PixelFormat::X8R8G8B8, Layout::Swizzled),
+        "R5G6B5" => ImageFormat::Raw(PixelFormat::R5G6B5, Layout::Swizzled),
+        "A1R5G5B5" => ImageFormat::Raw(PixelFormat::A1R5G5B5, Layout::Swizzled),
+        "A4R4G4B4" => ImageFormat::Raw(PixelFormat::A4R4G4B4, Layout::Swizzled),
+        _ => return None,
+    })
+}
+
 impl Viewable for Texture {
     fn create_viewer(&self, ctx: &mut ViewerContext) -> Result<(), CreationFailure> {
         self.data().create_viewer(ctx)
@@ -155,6 +285,79 @@ impl Editable for Texture {
     }
 }
 
+// How much an import lost, comparing the source image with what the texture now decodes to
+pub struct ImportMetrics {
+    uniform: Metrics,
+    alpha_weighted: Metrics,
+    weight_by_alpha: bool,
+}
+
+impl ImportMetrics {
+    pub fn from_import(texture: &Texture, rgba: &[u8]) -> Result<ImportMetrics, anyxplore::Error> {
+        let descriptor = texture.descriptor();
+
+        let format = image_format(descriptor).ok_or_else(|| {
+            anyxplore::Error::UnsupportedFormat(format!("{:?}", descriptor.format()))
+        })?;
+
+        let width = descriptor.width() as u32;
+        let height = descriptor.height() as u32;
+
+        let decoded = format
+            .decode(texture.data().bytes(), width, height, None)?
+            .decode_rgba();
+
+        Ok(ImportMetrics {
+            uniform: Metrics::from_rgba(rgba, &decoded, width, height, Weighting::Uniform)?,
+            alpha_weighted: Metrics::from_rgba(rgba, &decoded, width, height, Weighting::Alpha)?,
+            weight_by_alpha: false,
+        })
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Import quality");
+        ui.checkbox(&mut self.weight_by_alpha, "Weight colour by alpha");
+
+        let metrics = if self.weight_by_alpha {
+            &self.alpha_weighted
+        } else {
+            &self.uniform
+        };
+
+        let rows = ["Red", "Green", "Blue", "Alpha"]
+            .into_iter()
+            .zip(metrics.channels.iter())
+            .chain([("Overall", &metrics.overall)]);
+
+        egui::Grid::new("import_metrics")
+            .striped(true)
+            .show(ui, |ui| {
+                for heading in ["Channel", "RMSE", "PSNR", "SSIM"] {
+                    ui.strong(heading);
+                }
+                ui.end_row();
+
+                for (name, channel) in rows {
+                    metrics_row(ui, name, channel);
+                }
+            });
+    }
+}
+
+fn metrics_row(ui: &mut egui::Ui, name: &str, channel: &ChannelMetrics) {
+    ui.label(name);
+    ui.label(format!("{:.2}", channel.rmse));
+
+    if channel.psnr.is_finite() {
+        ui.label(format!("{:.2} dB", channel.psnr));
+    } else {
+        ui.label("Lossless");
+    }
+
+    ui.label(format!("{:.4}", channel.ssim));
+    ui.end_row();
+}
+
 impl Viewable for Model {
     fn create_viewer(&self, ctx: &mut ViewerContext) -> Result<(), CreationFailure> {
         let textures = self.textures().ok_or(CreationFailure::CompleteFailure(
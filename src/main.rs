@@ -1,14 +1,18 @@
 use std::{
     env,
     fmt::Display,
-    fs::{self},
-    io,
-    path::PathBuf,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyxplore::file::{
+    AssetPath, FileKind, VirtualFile, archive::ArchiveBudget, budget::DEFAULT_MEMORY_BUDGET,
+    os::OsFileSystem, overlay::OverlayFileSystem,
 };
 
 use bnl::{
-    BNLFile,
-    asset::{Asset, AssetDescription, model::Model, script::Script, texture::Texture},
+    asset::{Asset, model::Model, script::Script, texture::Texture},
     game::AssetType,
 };
 use eframe::egui::{
@@ -18,10 +22,12 @@ use eframe::egui::{
 use egui_file_dialog::FileDialog;
 use egui_ltreeview::{Action, RowLayout, TreeView, TreeViewSettings};
 
-use crate::editors::{Editable, Viewable, ViewerContext};
+use crate::bnl_archive::{BnlArchive, open_bnl};
+use crate::editors::{Editable, ImportMetrics, Viewable, ViewerContext};
 
 use image::ImageReader;
 
+mod bnl_archive;
 // mod edit_window;
 mod editors;
 mod widgets;
@@ -31,65 +37,35 @@ enum Message {
     TreeClicked,
 }
 
-#[derive(Debug)]
-struct BNLInners {
-    bnl_file: BNLFile,
-    descriptions: Vec<AssetDescription>,
-}
-
-impl BNLInners {
-    fn bnl_file(&self) -> &BNLFile {
-        &self.bnl_file
-    }
-
-    fn bnl_file_mut(&mut self) -> &mut BNLFile {
-        &mut self.bnl_file
-    }
-
-    // println!("Loading asset descriptions for {}", self.path.display());
-
-    pub fn load_asset_descriptions(&mut self) -> Result<&Vec<AssetDescription>, XError> {
-        self.descriptions = self.bnl_file.asset_descriptions().to_vec();
-        Ok(&self.descriptions)
-    }
-
-    pub fn from_bnl_bytes(bytes: &[u8]) -> Result<Self, std::io::Error> {
-        let bnl_file = bnl::BNLFile::from_bytes(&bytes).expect("Unable to create BNL file.");
-        let descriptions = bnl_file.asset_descriptions().to_owned();
-
-        Ok(BNLInners {
-            bnl_file,
-            descriptions,
-        })
-    }
-}
-
-#[derive(Debug, Default)]
-struct BNLStruct {
-    path: PathBuf,
-    inners: Option<BNLInners>,
-}
+// With a mod directory the game files are never written to, edits go to the mod directory instead
+fn open_vfs(
+    directory: &Path,
+    mod_directory: Option<&Path>,
+    budget: Arc<ArchiveBudget>,
+) -> Result<Arc<dyn VirtualFile>, anyxplore::Error> {
+    let base = OsFileSystem::new(directory)
+        .with_archive_format("bnl", Arc::new(open_bnl))
+        .with_memory_budget(budget.clone());
+
+    let Some(mod_directory) = mod_directory else {
+        return base.open();
+    };
 
-impl BNLStruct {
-    fn inners(&self) -> Option<&BNLInners> {
-        self.inners.as_ref()
-    }
+    fs::create_dir_all(mod_directory)?;
 
-    fn inners_mut(&mut self) -> &mut Option<BNLInners> {
-        &mut self.inners
-    }
-}
+    let top = OsFileSystem::new(mod_directory)
+        .with_archive_format("bnl", Arc::new(open_bnl))
+        .with_memory_budget(budget)
+        .open()?;
 
-#[derive(Debug)]
-enum XError {
-    AlreadyLoaded,
-    TreeError,
-    NodeError,
+    OverlayFileSystem::new(base.read_only().open()?, top).open()
 }
 
-impl Display for XError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+fn tree_label(file: &Arc<dyn VirtualFile>) -> String {
+    if file.is_overridden() {
+        format!("{} [mod]", file.name())
+    } else {
+        file.name().to_string()
     }
 }
 
@@ -102,22 +78,49 @@ struct NodeData {
 #[derive(Clone)]
 struct AssetStruct {
     name: String,
+    path: AssetPath,
     bnl_id: Id,
 }
 
+// One asset path per line, kept in the working directory
+const BOOKMARKS_FILE: &str = "bookmarks.txt";
+
+// Lines that aren't asset paths are returned as errors, so the rest still load
+fn load_bookmarks() -> Vec<Result<AssetPath, anyxplore::Error>> {
+    fs::read_to_string(BOOKMARKS_FILE)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.parse())
+        .collect()
+}
+
+fn save_bookmarks(bookmarks: &[AssetPath]) -> Result<(), anyxplore::Error> {
+    let lines: Vec<String> = bookmarks.iter().map(|path| path.to_string()).collect();
+
+    Ok(fs::write(BOOKMARKS_FILE, lines.join("\n"))?)
+}
+
 #[derive(Default)]
 struct AnyXPloreApp {
+    // Tree ids are hashes of asset paths, so the same asset has the same id in every session
     asset_map: HashMap<Id, AssetStruct>, // Maps an aid to its parent BNL file
-    bnl_map: HashMap<Id, BNLStruct>,     // Maps a BNL id to its BNL struct
+    // Every archive listed in the tree or opened by path, BNL files and the archives inside them
+    archives: HashMap<Id, Arc<dyn VirtualFile>>,
 
-    selected_id: Option<Id>,
+    selected: Option<AssetPath>,
+    bookmarks: Vec<AssetPath>,
+    // Typed or pasted into the side panel
+    open_path: String,
 
     directory: PathBuf,
     directory_valid: bool,
+    vfs: Option<Arc<dyn VirtualFile>>,
+    // Archives are only expanded once they have been opened from the tree, and collapse again when
+    // the memory budget closes them
+    opened_archives: HashSet<Id>,
 
     // flex: fltk::group::Flex,
     // tree: tree::Tree,
-    bnl_structs: Vec<BNLStruct>,
     // edit_window: EditWindow,
     // main_win: window::Window,
     // receiver: app::Receiver<Message>,
@@ -125,90 +128,154 @@ struct AnyXPloreApp {
 
     file_dialog: FileDialog,
     picked_file: Option<PathBuf>,
+
+    // Quality of the last texture import, for the asset it was imported into
+    import_metrics: Option<(Id, Result<ImportMetrics, String>)>,
+
+    // The last error, shown until it is dismissed
+    status: Option<String>,
 }
 
 impl AnyXPloreApp {
+    fn report(&mut self, context: &str, error: impl Display) {
+        self.status = Some(format!("{}: {}", context, error));
+    }
+
     fn create_file_tree(
         &mut self,
-        path: &PathBuf,
+        dir: &Arc<dyn VirtualFile>,
+        dir_path: &AssetPath,
         builder: &mut egui_ltreeview::TreeViewBuilder<'_, Id>,
-        // paths: &mut HashMap<Id, PathBuf>,
-    ) -> Result<(), io::Error> {
-        let entries = fs::read_dir(path)?;
-
-        for entry in entries {
-            let path = entry?.path().clone();
-
-            let bnl_id = Id::new(&path);
-
-            // If we find a bnl file
-            if path.is_file() && path.extension().unwrap_or_default() == "bnl" {
-                // Create a BNLStruct if one doesn't already exist
-                if !self.bnl_map.contains_key(&bnl_id) {
-                    self.bnl_map.insert(
-                        bnl_id,
-                        BNLStruct {
-                            path: path.clone(),
-                            ..Default::default()
-                        },
-                    );
-                }
-
-                // We can unwrap because we just made sure its available
-                let bnl_struct = self.bnl_map.get_mut(&bnl_id).unwrap();
+    ) -> Result<(), anyxplore::Error> {
+        for file in dir.children()? {
+            let path = dir_path.join(file.name());
+            let id = Id::new(&path);
+
+            match file.kind() {
+                FileKind::Archive => {
+                    // Kept current, since writes can move a file into the mod directory
+                    self.archives.insert(id, file.clone());
+
+                    if !self.opened_archives.contains(&id) || !file.is_open() {
+                        builder.leaf(id, tree_label(&file));
+                        continue;
+                    }
 
-                if let Some(inners) = &bnl_struct.inners {
-                    builder.dir(
-                        bnl_id,
-                        path.file_name()
-                            .expect("bruh")
-                            .to_str()
-                            .map(|val| val.to_string())
-                            .unwrap_or("errorfile".to_string()),
-                    );
+                    let entries = file.children().unwrap_or_else(|e| {
+                        self.report(&format!("Unable to list {}", path), e);
+                        Vec::new()
+                    });
 
-                    inners.descriptions.iter().for_each(|desc| {
-                        let name = desc.name();
+                    builder.dir(id, tree_label(&file));
 
-                        let aid_id = Id::new(path.join(name)); // Id generated from full path + aid
+                    for entry in entries {
+                        let entry_path = path.entry(entry.name());
+                        let aid_id = Id::new(&entry_path);
 
                         self.asset_map.insert(
                             aid_id,
                             AssetStruct {
-                                name: name.to_string(),
-                                bnl_id,
+                                name: entry.name().to_string(),
+                                path: entry_path.clone(),
+                                bnl_id: id,
                             },
                         );
-                        builder.leaf(aid_id, name);
-                    });
+
+                        if entry.kind() == FileKind::Archive {
+                            self.create_nested_archive(&entry_path, &entry, builder);
+                        } else {
+                            builder.leaf(aid_id, tree_label(&entry));
+                        }
+                    }
 
                     builder.close_dir();
-                } else {
-                    builder.leaf(
-                        bnl_id,
-                        path.file_name()
-                            .expect("bruh")
-                            .to_str()
-                            .map(|val| val.to_string())
-                            .unwrap_or("errorfile".to_string()),
-                    );
                 }
-            } else if path.is_dir() {
-                builder.dir(
-                    Id::new(&path),
-                    path.file_name()
-                        .expect("bruh")
-                        .to_str()
-                        .map(|val| val.to_string())
-                        .unwrap_or("errorfile".to_string()),
-                );
-                self.create_file_tree(&path, builder)?;
-                builder.close_dir();
+                FileKind::Directory => {
+                    builder.dir(id, tree_label(&file));
+                    self.create_file_tree(&file, &path, builder)?;
+                    builder.close_dir();
+                }
+                // Loose files can't be viewed yet, but are still listed
+                FileKind::File => {
+                    builder.leaf(id, tree_label(&file));
+                }
             }
         }
 
         Ok(())
     }
+
+    fn create_nested_archive(
+        &mut self,
+        path: &AssetPath,
+        archive: &Arc<dyn VirtualFile>,
+        builder: &mut egui_ltreeview::TreeViewBuilder<'_, Id>,
+    ) {
+        let id = Id::new(path);
+        self.archives.insert(id, archive.clone());
+
+        if !self.opened_archives.contains(&id) || !archive.is_open() {
+            builder.leaf(id, tree_label(archive));
+            return;
+        }
+
+        let entries = archive.children().unwrap_or_else(|e| {
+            self.report(&format!("Unable to list {}", path), e);
+            Vec::new()
+        });
+
+        builder.dir(id, tree_label(archive));
+
+        for entry in entries {
+            let entry_path = path.entry(entry.name());
+
+            if entry.kind() == FileKind::Archive {
+                self.create_nested_archive(&entry_path, &entry, builder);
+            } else {
+                builder.leaf(Id::new(&entry_path), tree_label(&entry));
+            }
+        }
+
+        builder.close_dir();
+    }
+
+    // Opens the BNL file holding an asset and selects it, whether or not the tree has shown it yet
+    fn open_asset(&mut self, path: AssetPath) -> Result<(), anyxplore::Error> {
+        let root = self.vfs.clone().ok_or_else(|| {
+            anyxplore::Error::InvalidArgument("No directory is open.".to_string())
+        })?;
+
+        // Assets of nested archives can be browsed but not viewed
+        let (Some(name), Some(archive_path), 1) =
+            (path.asset(), path.archive(), path.entries().len())
+        else {
+            return Err(anyxplore::Error::InvalidArgument(format!(
+                "{} is not an asset of a BNL file.",
+                path
+            )));
+        };
+
+        // Fails for missing assets before anything is loaded
+        path.resolve(&root)?;
+
+        let archive = archive_path.resolve(&root)?;
+        BnlArchive::from_file(archive.as_ref())?;
+
+        let bnl_id = Id::new(&archive_path);
+        self.archives.insert(bnl_id, archive);
+
+        self.asset_map.insert(
+            Id::new(&path),
+            AssetStruct {
+                name: name.to_string(),
+                path: path.clone(),
+                bnl_id,
+            },
+        );
+        self.selected = Some(path);
+
+        Ok(())
+    }
 }
 
 impl eframe::App for AnyXPloreApp {
@@ -217,9 +284,49 @@ impl eframe::App for AnyXPloreApp {
         self.dropped_files
             .extend(ctx.input(|i| i.raw.dropped_files.clone()));
 
+        if let Some(status) = self.status.clone() {
+            egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.colored_label(ui.visuals().error_fg_color, status);
+
+                    if ui.button("Dismiss").clicked() {
+                        self.status = None;
+                    }
+                });
+            });
+        }
+
         egui::SidePanel::left("my_left_panel").show(ctx, |ui| {
             ui.heading("AnyXplore");
 
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.open_path)
+                    .on_hover_text("An asset path, like levels/foo.bnl:aid_name");
+
+                if ui.button("Open").clicked() {
+                    match self.open_path.trim().parse() {
+                        Ok(path) => {
+                            if let Err(e) = self.open_asset(path) {
+                                self.report("Unable to open asset", e);
+                            }
+                        }
+                        Err(e) => self.report("Invalid asset path", e),
+                    }
+                }
+            });
+
+            if !self.bookmarks.is_empty() {
+                ui.collapsing("Bookmarks", |ui| {
+                    for bookmark in self.bookmarks.clone() {
+                        if ui.button(bookmark.to_string()).clicked()
+                            && let Err(e) = self.open_asset(bookmark)
+                        {
+                            self.report("Unable to open bookmark", e);
+                        }
+                    }
+                });
+            }
+
             egui::ScrollArea::vertical().show(ui, |ui| {
                 let tree = TreeView::new(Id::new("tree view")).with_settings(TreeViewSettings {
                     row_layout: RowLayout::CompactAlignedLabels,
@@ -227,8 +334,11 @@ impl eframe::App for AnyXPloreApp {
                 });
 
                 let (_response, actions) = tree.show(ui, |builder| {
-                    self.create_file_tree(&self.directory.clone(), builder)
-                        .unwrap_or_else(|_| eprintln!("Error while building tree."));
+                    if let Some(root) = self.vfs.clone()
+                        && let Err(e) = self.create_file_tree(&root, &AssetPath::root(), builder)
+                    {
+                        self.report("Error while building tree", e);
+                    }
                 });
                 for action in actions {
                     if let Action::Activate(activated) = action {
@@ -236,28 +346,28 @@ impl eframe::App for AnyXPloreApp {
 
                         // If the thing clicked was an asset, and it has a bnl file
                         if let Some(asset_mapping) = self.asset_map.get(&id) {
-                            if self.bnl_map.contains_key(&asset_mapping.bnl_id) {
-                                self.selected_id = Some(id);
+                            let path = asset_mapping.path.clone();
+
+                            if self.archives.contains_key(&asset_mapping.bnl_id) {
+                                self.selected = Some(path);
                             } else {
-                                eprintln!("Asset mapping exists but no BNL exists for the asset.");
+                                self.report(
+                                    &format!("Unable to select {}", path),
+                                    "no BNL file is loaded for it",
+                                );
                             }
                         }
 
-                        if !self.bnl_map.contains_key(&id) {
+                        let Some(archive) = self.archives.get(&id).cloned() else {
                             continue;
-                        }
-
-                        let bnl_struct = self.bnl_map.get_mut(&id).unwrap();
+                        };
 
-                        if bnl_struct.inners().is_none() {
-                            let bnl_inners = BNLInners::from_bnl_bytes(
-                                &fs::read(&bnl_struct.path).unwrap_or_default(),
-                            );
+                        self.opened_archives.insert(id);
 
-                            match bnl_inners {
-                                Ok(inners) => bnl_struct.inners = Some(inners),
-                                Err(e) => eprintln!("Unable to load BNL file.\nError: {}", e),
-                            }
+                        // Opening it may close the least recently used others to stay under the
+                        // memory budget, and those collapse in the tree
+                        if let Err(e) = archive.archive_reader() {
+                            self.report(&format!("Unable to open {}", archive.name()), e);
                         }
                     }
                 }
@@ -266,168 +376,259 @@ impl eframe::App for AnyXPloreApp {
 
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
-                if let Some(selected_id) = self.selected_id {
-                    let asset_struct = self.asset_map.get(&selected_id).unwrap();
-
-                    let bnl_struct = self
-                        .bnl_map
-                        .get_mut(&asset_struct.bnl_id)
-                        .expect("Unable to get BNL struct.");
-
-                    let bnl_path = bnl_struct.path.clone();
-
-                    if let Some(inners) = bnl_struct.inners_mut() {
-                        let bnl_file: &mut BNLFile = &mut inners.bnl_file;
-                        // Now you can mutate `bnl_file` as needed
-
-                        let raw_asset = bnl_file.get_raw_asset(&asset_struct.name).unwrap();
-
-                        let mut viewer_ctx = ViewerContext::new(ui);
-
-                        match raw_asset.asset_type {
-                            AssetType::ResTexture => {
-                                let mut texture: Texture =
-                                    bnl_file.get_asset(&asset_struct.name).unwrap();
-                                texture.create_viewer(&mut viewer_ctx);
-
-                                if ui.button("Set Texture").clicked() {
-                                    self.file_dialog.pick_file();
-                                }
-
-                                self.file_dialog.update(ctx);
-
-                                if let Some(path) = self.file_dialog.take_picked() {
-                                    self.picked_file = Some(path.to_path_buf());
-                                }
-
-                                if let Some(file) = self.picked_file.take() {
-                                    let img = ImageReader::open(&file)
-                                        .expect("Unable to open image")
-                                        .decode()
-                                        .expect("Unable to decode image");
-
-                                    texture.set_from_rgba(
-                                        texture.descriptor().width() as usize,
-                                        texture.descriptor().height() as usize,
-                                        img.as_bytes(),
-                                    );
-
-                                    println!("Updating image");
-
-                                    match bnl_file.update_asset_from_descriptor(
-                                        texture.name(),
-                                        texture.data().descriptor(),
-                                        Some(&texture.data().bytes().to_vec()),
-                                    ) {
-                                        Ok(()) => {
-                                            fs::write(bnl_path, bnl_file.to_bytes())
-                                                .expect("Unable to write");
-                                        }
-                                        Err(e) => {
-                                            eprintln!("Failed to update texture. Error: {}", e);
-                                        }
-                                    };
-                                }
-                            }
-                            AssetType::ResModel => {
-                                let model: Model = bnl_file.get_asset(&asset_struct.name).unwrap();
+                if let Some(selected) = self.selected.clone()
+                    && let Err(e) = self.show_asset(ctx, ui, &selected)
+                {
+                    self.report(&format!("Unable to show {}", selected), e);
+                }
+            });
+        });
+    }
+}
 
-                                model.create_viewer(&mut viewer_ctx);
+impl AnyXPloreApp {
+    fn new(
+        _cc: &eframe::CreationContext<'_>,
+        dir: Option<PathBuf>,
+        mod_dir: Option<PathBuf>,
+        open: Option<String>,
+        memory_budget: usize,
+        arg_error: Option<String>,
+    ) -> Self {
+        // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
+        // Restore app state using cc.storage (requires the "persistence" feature).
+        // Use the cc.gl (a glow::Context) to create graphics shaders and buffers that you can use
+        // for e.g. egui::PaintCallback.
 
-                                /*
-                                if ui.button("Set Texture").clicked() {
-                                    self.file_dialog.pick_file();
-                                }
+        let mut app = AnyXPloreApp {
+            directory_valid: false,
+            ..Default::default()
+        };
+
+        if let Some(d) = dir {
+            match open_vfs(
+                &d,
+                mod_dir.as_deref(),
+                Arc::new(ArchiveBudget::new(memory_budget)),
+            ) {
+                Ok(vfs) => app.vfs = Some(vfs),
+                Err(e) => app.report(&format!("Unable to open {}", d.display()), e),
+            }
 
-                                self.file_dialog.update(ctx);
+            app.directory = d;
+        }
 
-                                if let Some(path) = self.file_dialog.take_picked() {
-                                    self.picked_file = Some(path.to_path_buf());
-                                }
+        for bookmark in load_bookmarks() {
+            match bookmark {
+                Ok(path) => app.bookmarks.push(path),
+                Err(e) => app.report("Skipping a bookmark", e),
+            }
+        }
 
-                                if let Some(file) = self.picked_file.take() {
-                                    let img = ImageReader::open(&file)
-                                        .expect("Unable to open image")
-                                        .decode()
-                                        .expect("Unable to decode image");
+        if let Some(open) = open {
+            match open.parse() {
+                Ok(path) => {
+                    if let Err(e) = app.open_asset(path) {
+                        app.report(&format!("Unable to open {}", open), e);
+                    }
+                }
+                Err(e) => app.report("Invalid asset path", e),
+            }
+        }
 
-                                    texture.set_from_rgba(
-                                        texture.descriptor().width() as usize,
-                                        texture.descriptor().height() as usize,
-                                        img.as_bytes(),
-                                    );
+        if let Some(e) = arg_error {
+            app.report("Invalid arguments", e);
+        }
 
-                                    println!("Updating image");
-                                    bnl_file.update_asset(&asset_struct.name, &texture);
+        app
+    }
 
-                                    fs::write(bnl_path, &bnl_file.to_bytes()).expect("Unable to write");
-                                }
-                                */
-                            }
-                            AssetType::ResScript => {
-                                if let Ok(mut script) =
-                                    bnl_file.get_asset::<Script>(&asset_struct.name)
-                                {
-                                    script.create_editor(&mut viewer_ctx);
-
-                                    let descriptor = script.descriptor_mut();
-                                    if let Some(request) = viewer_ctx.delete_request_mut().take() {
-                                        descriptor.operations_mut().remove(request.deletion_index);
-                                    }
-
-                                    if viewer_ctx.update_bnl {
-                                        bnl_file
-                                            .update_asset_from_descriptor(
-                                                &asset_struct.name,
-                                                descriptor,
-                                                None,
-                                            )
-                                            .unwrap_or_else(|e| {
-                                                eprintln!("Unable to update asset: {}", e)
-                                            });
-                                        fs::write(bnl_path, bnl_file.to_bytes())
-                                            .expect("Unable to write");
-
-                                        viewer_ctx.update_bnl = false;
-                                    }
-                                } else {
-                                    viewer_ctx.ui_mut().heading("Error parsing script.");
-                                }
-                            }
+    // Views the selected asset, and edits it in place when asked to
+    fn show_asset(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut egui::Ui,
+        selected: &AssetPath,
+    ) -> Result<(), String> {
+        let selected_id = Id::new(selected);
+
+        // Only assets the tree has listed or that were opened by path are known
+        let Some(asset_struct) = self.asset_map.get(&selected_id).cloned() else {
+            self.selected = None;
+            return Err("the asset is not listed anymore".to_string());
+        };
+
+        let bnl_vfs_file = self
+            .archives
+            .get(&asset_struct.bnl_id)
+            .cloned()
+            .ok_or("its BNL file is not listed")?;
+
+        // Opened again if the memory budget closed it while other archives were browsed
+        let bnl_archive = BnlArchive::from_file(bnl_vfs_file.as_ref())
+            .map_err(|e| format!("Unable to open BNL file: {}", e))?;
+
+        ui.horizontal(|ui| {
+            ui.label(selected.to_string());
+
+            if ui.button("Copy path").clicked() {
+                ui.ctx().copy_text(selected.to_string());
+            }
+
+            if !self.bookmarks.contains(selected) && ui.button("Bookmark").clicked() {
+                self.bookmarks.push(selected.clone());
+
+                if let Err(e) = save_bookmarks(&self.bookmarks) {
+                    self.report("Unable to save bookmarks", e);
+                }
+            }
+        });
 
-                            _ => (),
+        let bnl_file = bnl_archive.bnl_file();
+
+        let raw_asset = bnl_file
+            .get_raw_asset(&asset_struct.name)
+            .map_err(|e| format!("{:?}", e))?;
+
+        let mut viewer_ctx = ViewerContext::new(ui);
+
+        match raw_asset.asset_type {
+            AssetType::ResTexture => {
+                let mut texture: Texture = bnl_file
+                    .get_asset(&asset_struct.name)
+                    .map_err(|e| format!("{:?}", e))?;
+                texture.create_viewer(&mut viewer_ctx);
+
+                if ui.button("Set Texture").clicked() {
+                    self.file_dialog.pick_file();
+                }
+
+                self.file_dialog.update(ctx);
+
+                if let Some(path) = self.file_dialog.take_picked() {
+                    self.picked_file = Some(path.to_path_buf());
+                }
+
+                if let Some(file) = self.picked_file.take() {
+                    let img = ImageReader::open(&file)
+                        .map_err(|e| format!("Unable to open {}: {}", file.display(), e))?
+                        .decode()
+                        .map_err(|e| format!("Unable to decode {}: {}", file.display(), e))?
+                        .to_rgba8();
+
+                    let width = texture.descriptor().width() as u32;
+                    let height = texture.descriptor().height() as u32;
+
+                    texture.set_from_rgba(width as usize, height as usize, img.as_raw());
+
+                    // Metrics only make sense when nothing was cropped or padded
+                    let metrics = if img.dimensions() == (width, height) {
+                        ImportMetrics::from_import(&texture, img.as_raw())
+                            .map_err(|e| e.to_string())
+                    } else {
+                        Err(format!(
+                            "The image is {} x {} but the texture is {} x {}.",
+                            img.width(),
+                            img.height(),
+                            width,
+                            height
+                        ))
+                    };
+                    self.import_metrics = Some((selected_id, metrics));
+
+                    drop(bnl_file);
+                    let mut edited = bnl_archive.into_edit();
+
+                    edited
+                        .update_asset_from_descriptor(
+                            texture.name(),
+                            texture.data().descriptor(),
+                            Some(&texture.data().bytes().to_vec()),
+                        )
+                        .map_err(|e| format!("Failed to update texture: {}", e))?;
+
+                    bnl_vfs_file
+                        .write(&edited.to_bytes())
+                        .map_err(|e| format!("Unable to write: {}", e))?;
+                }
+
+                if let Some((id, metrics)) = &mut self.import_metrics
+                    && *id == selected_id
+                {
+                    match metrics {
+                        Ok(metrics) => metrics.show(ui),
+                        Err(e) => {
+                            ui.label(format!("No import metrics: {}", e));
                         }
                     }
                 }
-            });
-        });
-    }
-}
+            }
+            AssetType::ResModel => {
+                let model: Model = bnl_file
+                    .get_asset(&asset_struct.name)
+                    .map_err(|e| format!("{:?}", e))?;
 
-impl AnyXPloreApp {
-    fn new(_cc: &eframe::CreationContext<'_>, dir: Option<PathBuf>) -> Self {
-        // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
-        // Restore app state using cc.storage (requires the "persistence" feature).
-        // Use the cc.gl (a glow::Context) to create graphics shaders and buffers that you can use
-        // for e.g. egui::PaintCallback.
+                model.create_viewer(&mut viewer_ctx);
+            }
+            AssetType::ResScript => {
+                if let Ok(mut script) = bnl_file.get_asset::<Script>(&asset_struct.name) {
+                    script.create_editor(&mut viewer_ctx);
 
-        match dir {
-            Some(d) => AnyXPloreApp {
-                directory: d,
-                directory_valid: false,
-                ..Default::default()
-            },
+                    let descriptor = script.descriptor_mut();
+                    if let Some(request) = viewer_ctx.delete_request_mut().take() {
+                        descriptor.operations_mut().remove(request.deletion_index);
+                    }
 
-            None => AnyXPloreApp {
-                directory_valid: false,
-                ..Default::default()
-            },
+                    if viewer_ctx.update_bnl {
+                        viewer_ctx.update_bnl = false;
+
+                        drop(bnl_file);
+                        let mut edited = bnl_archive.into_edit();
+
+                        edited
+                            .update_asset_from_descriptor(&asset_struct.name, descriptor, None)
+                            .map_err(|e| format!("Unable to update asset: {}", e))?;
+
+                        bnl_vfs_file
+                            .write(&edited.to_bytes())
+                            .map_err(|e| format!("Unable to write: {}", e))?;
+                    }
+                } else {
+                    viewer_ctx.ui_mut().heading("Error parsing script.");
+                }
+            }
+
+            _ => (),
         }
+
+        Ok(())
     }
 }
 
+// anyxplorer [game directory] [mod directory] [--open levels/foo.bnl:aid_name]
+//     [--memory-budget MiB]
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args = Vec::new();
+    let mut open = None;
+    // Shared by every archive, BNL files included
+    let mut memory_budget = DEFAULT_MEMORY_BUDGET;
+    // Shown in the window, as there is often no console to print to
+    let mut arg_error = None;
+
+    let mut env_args = env::args().skip(1);
+    while let Some(arg) = env_args.next() {
+        match arg.as_str() {
+            "--open" => open = env_args.next(),
+            "--memory-budget" => match env_args.next().map(|mib| mib.parse::<usize>()) {
+                Some(Ok(mib)) => memory_budget = mib.saturating_mul(1 << 20),
+                _ => {
+                    arg_error =
+                        Some("--memory-budget needs a size in MiB, the default is used".to_string())
+                }
+            },
+            _ => args.push(arg),
+        }
+    }
 
     let native_options = eframe::NativeOptions {
         ..Default::default()
@@ -438,7 +639,11 @@ fn main() {
         Box::new(|cc| {
             Ok(Box::new(AnyXPloreApp::new(
                 cc,
+                args.first().map(PathBuf::from),
                 args.get(1).map(PathBuf::from),
+                open,
+                memory_budget,
+                arg_error,
             )))
         }),
     );